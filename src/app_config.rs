@@ -0,0 +1,482 @@
+use std::env;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::app_context;
+use crate::logging;
+use crate::opt::*;
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+const CONFIG_PATH_ENV_KEY: &str = "DRAFT_CLAW_CONFIG";
+const MASKED_VALUE: &str = "********";
+
+// Every setting with the environment variable that overrides the file
+const ENV_KEYS: [(&str, &str); 17] = [
+    ("db.host", "SURREAL_DB_HOST"),
+    ("db.user", "SURREAL_DB_USER"),
+    ("db.pass", "SURREAL_DB_PASS"),
+    ("discord.token", "DISCORD_TOKEN"),
+    ("imgur.client_id", "IMGUR_CLIENT_ID"),
+    ("capture.android_host", "ANDROID_HOST"),
+    ("capture.adb_path", "ANDROID_ADB_PATH"),
+    ("capture.tess_data", "TESS_DATA"),
+    ("capture.runtime_path", "RUNTIME_PATH"),
+    ("capture.game_id", "GAME_ID"),
+    ("resources.card_data_path", "CARD_DATA_PATH"),
+    ("resources.card_rating_path", "CARD_RATING_PATH"),
+    ("resources.card_import_dir", "CARD_IMPORT_DIR"),
+    ("log.level", "LOG_LEVEL"),
+    ("log.filters", "LOG_FILTERS"),
+    ("log.format", "LOG_FORMAT"),
+    ("metrics.listen", "METRICS_LISTEN"),
+];
+const SECRET_KEYS: [&str; 3] = ["db.pass", "discord.token", "imgur.client_id"];
+
+static CONFIG: OnceLock<AppConfig> = OnceLock::new();
+
+// What a command needs from the configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigMode {
+    Bot,
+    Capture,
+    Database,
+    Offline,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DbConfig {
+    // Host name only, the https scheme is added by the client
+    pub host: String,
+    pub user: String,
+    pub pass: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordConfig {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImgurConfig {
+    pub client_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    pub android_host: String,
+    pub adb_path: String,
+    pub tess_data: String,
+    // Where screenshots are written
+    pub runtime_path: String,
+    pub game_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResourceConfig {
+    pub card_data_path: String,
+    pub card_rating_path: String,
+    // The only directory the bot reads card exports from, empty turns the import off
+    pub card_import_dir: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    // One object per line for the log collector
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    // Level for everything without a filter
+    pub level: String,
+    // Per module levels, e.g. "draft_claw::db_access=debug,serenity=warn"
+    pub filters: String,
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    // Address of the metrics endpoint, e.g. "0.0.0.0:9091", empty to turn it off
+    pub listen: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub db: DbConfig,
+    pub discord: DiscordConfig,
+    pub imgur: ImgurConfig,
+    pub capture: CaptureConfig,
+    pub resources: ResourceConfig,
+    pub log: LogConfig,
+    pub metrics: MetricsConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigArgs {
+    pub path: Option<PathBuf>,
+    pub overrides: Vec<(String, String)>,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            android_host: String::new(),
+            adb_path: "adb".to_string(),
+            tess_data: "./resource/tessdata".to_string(),
+            runtime_path: "./".to_string(),
+            game_id: String::new(),
+        }
+    }
+}
+
+impl Default for ResourceConfig {
+    fn default() -> Self {
+        Self {
+            card_data_path: "./resource/eternal-cards.json".to_string(),
+            card_rating_path: "./resource/card_rating.txt".to_string(),
+            card_import_dir: String::new(),
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            filters: String::new(),
+            format: LogFormat::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn from_toml(text: &str) -> Res<Self> {
+        toml::from_str(text).err_to(Error::Config)
+    }
+
+    pub fn from_file(path: &Path) -> Res<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| Error::Config(format!("Unable to read {}: {}", path.display(), err)))?;
+        Self::from_toml(&text)
+            .map_err(|err| Error::Config(format!("Invalid config {}: {}", path.display(), err)))
+    }
+
+    fn to_value(&self) -> toml::value::Table {
+        match toml::Value::try_from(self) {
+            Ok(toml::Value::Table(table)) => table,
+            _ => panic!("config does not serialize to a table"),
+        }
+    }
+
+    pub fn keys() -> Vec<String> {
+        ENV_KEYS.iter().map(|(key, _)| key.to_string()).collect()
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let (section, name) = key.split_once('.')?;
+        match self.to_value().get(section)?.get(name)? {
+            toml::Value::String(value) => Some(value.to_string()),
+            value => Some(value.to_string()),
+        }
+    }
+
+    // Typed through the serialized form so a key only has to be declared on the struct
+    pub fn set(&mut self, key: &str, value: &str) -> Res<()> {
+        let unknown = || Error::Config(format!("Unknown config key [{}]", key));
+        let (section, name) = key.split_once('.').ok_or_else(unknown)?;
+
+        let mut table = self.to_value();
+        let field = table
+            .get_mut(section)
+            .and_then(|section| section.get_mut(name))
+            .ok_or_else(unknown)?;
+        *field = toml::Value::String(value.to_string());
+
+        *self = toml::Value::Table(table)
+            .try_into()
+            .map_err(|err| Error::Config(format!("{}: {}", key, err)))?;
+        Ok(())
+    }
+
+    pub fn apply_vars(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Res<()> {
+        for (key, var) in ENV_KEYS.iter() {
+            if let Some(value) = lookup(var) {
+                self.set(key, &value)
+                    .map_err(|err| Error::Config(format!("{} from {}", err, var)))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply_env(&mut self) -> Res<()> {
+        self.apply_vars(|var| env::var(var).ok())
+    }
+
+    pub fn apply_overrides(&mut self, overrides: &[(String, String)]) -> Res<()> {
+        for (key, value) in overrides.iter() {
+            self.set(key, value)?;
+        }
+        Ok(())
+    }
+
+    pub fn issues(&self, mode: ConfigMode) -> Vec<String> {
+        let mut required = match mode {
+            ConfigMode::Offline => vec![],
+            _ => vec!["db.host", "db.user", "db.pass"],
+        };
+        match mode {
+            ConfigMode::Bot => required.push("discord.token"),
+            ConfigMode::Capture => required.push("imgur.client_id"),
+            _ => {}
+        }
+
+        let mut issues = required
+            .into_iter()
+            .filter(|key| self.get(key).map_or(true, |value| value.is_empty()))
+            .map(|key| format!("{} is not set", key))
+            .collect::<Vec<String>>();
+
+        if mode != ConfigMode::Offline && self.db.host.contains("://") {
+            issues.push(format!(
+                "db.host [{}] should be a host name without a scheme",
+                self.db.host
+            ));
+        }
+
+        if let Err(err) = logging::env_filter(&self.log) {
+            issues.push(format!("log: {}", err));
+        }
+
+        if !self.metrics.listen.is_empty() && self.metrics.listen.parse::<SocketAddr>().is_err() {
+            issues.push(format!(
+                "metrics.listen [{}] should be an address with a port, e.g. 0.0.0.0:9091",
+                self.metrics.listen
+            ));
+        }
+
+        let mut paths = vec![
+            ("resources.card_data_path", &self.resources.card_data_path),
+            (
+                "resources.card_rating_path",
+                &self.resources.card_rating_path,
+            ),
+        ];
+        if mode == ConfigMode::Capture {
+            paths.push(("capture.tess_data", &self.capture.tess_data));
+        }
+        if !self.resources.card_import_dir.is_empty() {
+            paths.push(("resources.card_import_dir", &self.resources.card_import_dir));
+        }
+        for (key, path) in paths {
+            if !Path::new(path).exists() {
+                issues.push(format!("{} [{}] does not exist", key, path));
+            }
+        }
+
+        issues
+    }
+
+    pub fn validate(&self, mode: ConfigMode) -> Res<()> {
+        match self.issues(mode) {
+            issues if issues.is_empty() => Ok(()),
+            issues => Err(Error::Config(format!(
+                "Invalid configuration:\n{}",
+                issues.join("\n")
+            ))),
+        }
+    }
+
+    // Secrets are masked so the output can be pasted in a bug report
+    pub fn to_text(&self) -> String {
+        let mut config = self.clone();
+        for key in SECRET_KEYS.iter() {
+            if self.get(key).map_or(false, |value| !value.is_empty()) {
+                config.set(key, MASKED_VALUE).ok();
+            }
+        }
+        toml::to_string_pretty(&config).expect("config serializes to toml")
+    }
+}
+
+// Value of a --set flag, e.g. db.host=example.com
+pub fn parse_override(arg: &str) -> Res<(String, String)> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .ok_or_else(|| Error::Config(format!("Expected key=value, got [{}]", arg)))
+}
+
+pub fn default_config_path() -> Res<PathBuf> {
+    Ok(app_context::project_dirs()?
+        .config_dir()
+        .join(CONFIG_FILE_NAME))
+}
+
+// The flag wins over the environment, the default location may be missing
+pub fn config_path(args: &ConfigArgs) -> Res<(PathBuf, bool)> {
+    match args
+        .path
+        .clone()
+        .or_else(|| env::var(CONFIG_PATH_ENV_KEY).ok().map(PathBuf::from))
+    {
+        Some(path) => Ok((path, true)),
+        None => Ok((default_config_path()?, false)),
+    }
+}
+
+// Defaults, then the config file, then the environment, then command line overrides
+pub fn load(args: &ConfigArgs) -> Res<AppConfig> {
+    let (path, explicit) = config_path(args)?;
+    let mut config = if explicit || path.exists() {
+        AppConfig::from_file(&path)?
+    } else {
+        AppConfig::default()
+    };
+
+    config.apply_env()?;
+    config.apply_overrides(&args.overrides)?;
+    Ok(config)
+}
+
+pub fn init(config: AppConfig) {
+    if CONFIG.set(config).is_err() {
+        warn!("Configuration is already initialized");
+    }
+}
+
+// Falls back to the defaults when nothing was loaded, e.g. in tests
+pub fn get() -> &'static AppConfig {
+    CONFIG.get_or_init(AppConfig::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_from_toml() {
+        let config = AppConfig::from_toml(
+            r#"
+            [db]
+            host = "db.example.com"
+
+            [log]
+            filters = "draft_claw::db_access=debug"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.db.host, "db.example.com");
+        assert_eq!(config.log.filters, "draft_claw::db_access=debug");
+        assert_eq!(config.log.level, "info");
+        assert_eq!(config.capture.adb_path, "adb");
+
+        assert!(AppConfig::from_toml("[db]\nhots = \"typo\"").is_err());
+        assert!(AppConfig::from_toml("[log]\nformat = \"xml\"").is_err());
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        let mut config = AppConfig::default();
+        config.set("capture.game_id", "game").unwrap();
+        config.set("log.format", "json").unwrap();
+
+        assert_eq!(config.capture.game_id, "game");
+        assert_eq!(config.log.format, LogFormat::Json);
+        assert_eq!(config.get("log.format"), Some("json".to_string()));
+        assert_eq!(config.get("capture.adb_path"), Some("adb".to_string()));
+
+        assert!(config.set("log.format", "xml").is_err());
+        assert!(config.set("db.port", "8000").is_err());
+        assert!(config.set("db", "host").is_err());
+
+        // Every key with an environment variable exists on the struct
+        for key in AppConfig::keys() {
+            assert!(config.get(&key).is_some(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut config = AppConfig::from_toml("[db]\nhost = \"file\"\nuser = \"file\"").unwrap();
+        let env = HashMap::from([("SURREAL_DB_HOST", "env"), ("LOG_LEVEL", "debug")]);
+        config
+            .apply_vars(|var| env.get(var).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.db.host, "env");
+        assert_eq!(config.db.user, "file");
+        assert_eq!(config.log.level, "debug");
+
+        let overrides = vec![
+            parse_override("db.host=flag").unwrap(),
+            parse_override("db.pass=a=b").unwrap(),
+        ];
+        config.apply_overrides(&overrides).unwrap();
+        assert_eq!(config.db.host, "flag");
+        assert_eq!(config.db.pass, "a=b");
+
+        assert!(parse_override("db.host").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = AppConfig::default();
+        let issues = config.issues(ConfigMode::Bot);
+        assert!(issues.contains(&"discord.token is not set".to_string()));
+        assert!(issues.contains(&"db.host is not set".to_string()));
+        assert!(!issues.contains(&"imgur.client_id is not set".to_string()));
+
+        config.db = DbConfig {
+            host: "https://db.example.com".to_string(),
+            user: "user".to_string(),
+            pass: "pass".to_string(),
+        };
+        config.discord.token = "token".to_string();
+        assert_eq!(
+            config.issues(ConfigMode::Bot),
+            vec!["db.host [https://db.example.com] should be a host name without a scheme"]
+        );
+
+        config.db.host = "db.example.com".to_string();
+        assert!(config.validate(ConfigMode::Bot).is_ok());
+        assert!(AppConfig::default().validate(ConfigMode::Offline).is_ok());
+
+        config.metrics.listen = "9091".to_string();
+        assert!(config.validate(ConfigMode::Bot).is_err());
+        config.metrics.listen = "127.0.0.1:9091".to_string();
+        assert!(config.validate(ConfigMode::Bot).is_ok());
+
+        config.log.level = "loud".to_string();
+        assert!(config.validate(ConfigMode::Bot).is_err());
+
+        config.resources.card_rating_path = "./missing.txt".to_string();
+        assert!(config.validate(ConfigMode::Bot).is_err());
+    }
+
+    #[test]
+    fn test_to_text_masks_secrets() {
+        let mut config = AppConfig::default();
+        config.discord.token = "secret-token".to_string();
+
+        let text = config.to_text();
+        assert!(!text.contains("secret-token"));
+        assert!(text.contains(MASKED_VALUE));
+        assert!(text.contains("[resources]"));
+        assert_eq!(AppConfig::from_toml(&text).unwrap().db, config.db);
+    }
+}
@@ -1,117 +1,354 @@
-use std::fs::OpenOptions;
-use std::io::{Read, Write};
-use std::sync::{RwLock, Arc};
-use std::{collections::HashMap, fs::File, path::Path};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::opt::*;
 
 const APP_NAME: &str = "draft-claw";
 const APP_AUTHOR: &str = "akio";
 const APP_QUALIFIER: &str = "com";
 
 const CONFIG_FILE_NAME: &str = "runtime_data.json";
+const TEMP_FILE_SUFFIX: &str = "tmp";
+const BACKUP_FILE_SUFFIX: &str = "bak";
+
+pub const STATE_VERSION: u32 = 1;
+pub const RECENT_GAME_LIMIT: usize = 10;
+// The only key of the unversioned string map
+const LEGACY_CURRENT_GAME_ID_KEY: &str = "current_game_id";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScreenProfile {
+    #[default]
+    #[serde(rename = "1920x1080")]
+    Hd,
+    #[serde(rename = "2560x1600")]
+    Wqxga,
+}
+
+impl ScreenProfile {
+    pub const ALL: [ScreenProfile; 2] = [ScreenProfile::Hd, ScreenProfile::Wqxga];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScreenProfile::Hd => "1920x1080",
+            ScreenProfile::Wqxga => "2560x1600",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|profile| profile.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeState {
+    pub version: u32,
+    pub current_game_id: Option<String>,
+    // Most recent first, the current game included
+    pub recent_game_ids: Vec<String>,
+    pub auto_mode_all: bool,
+    pub screen_profile: ScreenProfile,
+}
 
 pub struct AppContext {
-    pub data: Arc<RwLock<HashMap<String, String>>>,
-    config_file_path: String,
+    state: Arc<RwLock<RuntimeState>>,
+    state_file_path: PathBuf,
+}
+
+impl Default for RuntimeState {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION,
+            current_game_id: None,
+            recent_game_ids: Vec::new(),
+            auto_mode_all: false,
+            screen_profile: ScreenProfile::default(),
+        }
+    }
 }
 
-pub fn create_context() -> AppContext {
-    let project_dirs = ProjectDirs::from(APP_QUALIFIER, APP_AUTHOR, APP_NAME)
-        .expect("Failed to get the project directory");
+impl RuntimeState {
+    pub fn set_current_game(&mut self, game_id: &str) {
+        self.current_game_id = Some(game_id.to_string());
+        self.recent_game_ids.retain(|id| id != game_id);
+        self.recent_game_ids.insert(0, game_id.to_string());
+        self.recent_game_ids.truncate(RECENT_GAME_LIMIT);
+    }
+}
+
+pub fn project_dirs() -> Res<ProjectDirs> {
+    ProjectDirs::from(APP_QUALIFIER, APP_AUTHOR, APP_NAME).ok_or(Error::Config(
+        "Unable to find the project directory".to_string(),
+    ))
+}
+
+pub fn create_context() -> Res<AppContext> {
+    let project_dirs = project_dirs()?;
 
-    let runtime_dir = project_dirs
-        .data_local_dir();
-    println!("Runtime directory: {:?}", runtime_dir);
+    let runtime_dir = project_dirs.data_local_dir();
+    info!("Runtime directory: {:?}", runtime_dir);
 
-    std::fs::create_dir_all(&runtime_dir).expect("Failed to create the runtime directory");
+    std::fs::create_dir_all(runtime_dir).map_err(|err| {
+        Error::Config(format!(
+            "Unable to create {}: {}",
+            runtime_dir.display(),
+            err
+        ))
+    })?;
 
-    let config_file_path = runtime_dir.join(CONFIG_FILE_NAME);
-    load_data_from_file(config_file_path.to_str().unwrap())
+    load_context(&runtime_dir.join(CONFIG_FILE_NAME))
 }
 
-pub fn load_data_from_file(config_file_path: &str) -> AppContext {
-    let path = Path::new(config_file_path);
-    let data = if path.exists() {
-        let mut file = File::open(path).expect("Failed to open file");
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
-            .expect("Failed to read file");
-        let data: HashMap<String, String> =
-            serde_json::from_str(&contents).expect("Failed to parse JSON");
-        data
-    } else {
-        HashMap::new()
-    };
+fn migrate_legacy(value: serde_json::Value) -> Res<RuntimeState> {
+    let data: HashMap<String, String> = serde_json::from_value(value).err_to(Error::Data)?;
+    let mut state = RuntimeState::default();
+    if let Some(game_id) = data.get(LEGACY_CURRENT_GAME_ID_KEY) {
+        state.set_current_game(game_id);
+    }
+    Ok(state)
+}
 
-    AppContext {
-        data: Arc::new(RwLock::new(data)),
-        config_file_path: config_file_path.to_string(),
+pub fn parse_state(contents: &str) -> Res<RuntimeState> {
+    let value: serde_json::Value = serde_json::from_str(contents).err_to(Error::Data)?;
+    match value.get("version") {
+        None => migrate_legacy(value),
+        Some(version) if version.as_u64() == Some(STATE_VERSION as u64) => {
+            serde_json::from_value(value).err_to(Error::Data)
+        }
+        // Written by a newer build, resetting it would lose state that build still needs
+        Some(version) if version.as_u64() > Some(STATE_VERSION as u64) => {
+            Err(Error::Config(format!(
+                "State version {} is newer than {}, update draft-claw to use it",
+                version, STATE_VERSION
+            )))
+        }
+        Some(version) => Err(Error::Data(format!(
+            "Unsupported state version {}",
+            version
+        ))),
     }
 }
 
-impl AppContext {
-    pub fn read_data(&self, key: &str) -> Option<String> {
-        let data_read = self.data.read().unwrap();
-        data_read.get(key).map(|s| s.to_string())
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{}", suffix));
+    path.with_file_name(file_name)
+}
+
+// Timestamped so a second bad file never overwrites the first backup
+fn backup_path(path: &Path) -> PathBuf {
+    let timestamp = chrono::Local::now().format("%Y%m%d%H%M%S%3f");
+    sibling_path(path, &format!("{}.{}", timestamp, BACKUP_FILE_SUFFIX))
+}
+
+// A file that cannot be read is kept aside for inspection instead of stopping the app,
+// a file from a newer version stops it
+fn load_state(path: &Path) -> Res<RuntimeState> {
+    if !path.exists() {
+        return Ok(RuntimeState::default());
     }
 
-    pub fn write_data(&self, key: &str, value: &str) {
-        let mut data_write = self.data.write().unwrap();
-        data_write.insert(key.to_string(), value.to_string());
-        save_data(&self.config_file_path, &data_write);
+    match std::fs::read_to_string(path)
+        .err_to(Error::Data)
+        .and_then(|contents| parse_state(&contents))
+    {
+        Ok(state) => Ok(state),
+        Err(err @ Error::Config(_)) => Err(err),
+        Err(err) => {
+            let backup_path = backup_path(path);
+            warn!(
+                "Unable to load {:?}: {}, moving it to {:?} and starting over",
+                path, err, backup_path
+            );
+            if let Err(err) = std::fs::rename(path, &backup_path) {
+                warn!("Unable to move {:?}: {}", path, err);
+            }
+            Ok(RuntimeState::default())
+        }
     }
 }
 
-fn save_data(config_file_path: &str, data: &HashMap<String, String>) {
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(config_file_path)
-        .expect("Failed to create or open the file");
-    let content = serde_json::to_string(data).expect("Failed to serialize the data");
-    file.write_all(content.as_bytes())
-        .expect("Failed to write to the file");
+// Written to a temp file first so a crash never leaves a half written state
+fn save_state(path: &Path, state: &RuntimeState) -> Res<()> {
+    let content = serde_json::to_string_pretty(state).err_to(Error::Data)?;
+    let temp_path = sibling_path(path, TEMP_FILE_SUFFIX);
+    std::fs::write(&temp_path, content).err_to(Error::Data)?;
+    std::fs::rename(&temp_path, path).err_to(Error::Data)
+}
+
+pub fn load_context(state_file_path: &Path) -> Res<AppContext> {
+    Ok(AppContext {
+        state: Arc::new(RwLock::new(load_state(state_file_path)?)),
+        state_file_path: state_file_path.to_path_buf(),
+    })
+}
+
+impl AppContext {
+    pub fn state(&self) -> RuntimeState {
+        self.state.read().unwrap().clone()
+    }
+
+    pub fn update_state(&self, update: impl FnOnce(&mut RuntimeState)) -> Res<()> {
+        let mut state = self.state.write().unwrap();
+        update(&mut state);
+        save_state(&self.state_file_path, &state)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::NamedTempFile;
+    use tempfile::TempDir;
+
+    fn state_file_path(dir: &TempDir) -> PathBuf {
+        dir.path().join(CONFIG_FILE_NAME)
+    }
 
-    fn create_temp_file_path() -> String {
-        NamedTempFile::new()
-            .expect("Failed to create a temp file")
-            .path()
-            .to_str()
+    fn backup_contents(dir: &TempDir) -> Vec<String> {
+        let mut paths = std::fs::read_dir(dir.path())
             .unwrap()
-            .to_string()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .map_or(false, |ext| ext == BACKUP_FILE_SUFFIX)
+            })
+            .collect::<Vec<PathBuf>>();
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| std::fs::read_to_string(path).unwrap())
+            .collect()
     }
 
     #[test]
-    fn test_read_write_data() {
-        let config_file_path = create_temp_file_path();
-        let runtime_data = load_data_from_file(&config_file_path);
+    fn test_update_state() {
+        let dir = TempDir::new().unwrap();
+        let context = load_context(&state_file_path(&dir)).unwrap();
 
-        assert_eq!(runtime_data.read_data("key"), None);
+        assert_eq!(context.state(), RuntimeState::default());
 
-        runtime_data.write_data("key", "value");
-        assert_eq!(runtime_data.read_data("key"), Some("value".to_string()));
+        context
+            .update_state(|state| state.set_current_game("game"))
+            .unwrap();
+        assert_eq!(context.state().current_game_id, Some("game".to_string()));
+        assert!(!sibling_path(&state_file_path(&dir), TEMP_FILE_SUFFIX).exists());
     }
 
     #[test]
     fn test_persistence() {
-        let config_file_path = create_temp_file_path();
+        let dir = TempDir::new().unwrap();
 
         {
-            let runtime_data = load_data_from_file(&config_file_path);
-            runtime_data.write_data("key", "value");
+            let context = load_context(&state_file_path(&dir)).unwrap();
+            context
+                .update_state(|state| {
+                    state.set_current_game("game");
+                    state.auto_mode_all = true;
+                    state.screen_profile = ScreenProfile::Wqxga;
+                })
+                .unwrap();
         }
 
         {
-            let runtime_data = load_data_from_file(&config_file_path);
-            assert_eq!(runtime_data.read_data("key"), Some("value".to_string()));
+            let state = load_context(&state_file_path(&dir)).unwrap().state();
+            assert_eq!(state.current_game_id, Some("game".to_string()));
+            assert!(state.auto_mode_all);
+            assert_eq!(state.screen_profile, ScreenProfile::Wqxga);
         }
     }
+
+    #[test]
+    fn test_screen_profile_name() {
+        for profile in ScreenProfile::ALL {
+            assert_eq!(ScreenProfile::from_name(profile.name()), Some(profile));
+        }
+        assert_eq!(ScreenProfile::from_name("800x600"), None);
+    }
+
+    #[test]
+    fn test_recent_games() {
+        let mut state = RuntimeState::default();
+        for idx in 0..RECENT_GAME_LIMIT + 2 {
+            state.set_current_game(&format!("game{}", idx));
+        }
+        state.set_current_game("game5");
+
+        assert_eq!(state.recent_game_ids.len(), RECENT_GAME_LIMIT);
+        assert_eq!(state.recent_game_ids[0], "game5");
+        assert_eq!(state.recent_game_ids[1], "game11");
+        assert_eq!(
+            state
+                .recent_game_ids
+                .iter()
+                .filter(|id| *id == "game5")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_parse_state() {
+        let legacy = parse_state(r#"{"current_game_id":"game"}"#).unwrap();
+        assert_eq!(legacy.version, STATE_VERSION);
+        assert_eq!(legacy.current_game_id, Some("game".to_string()));
+        assert_eq!(legacy.recent_game_ids, vec!["game".to_string()]);
+
+        let state = parse_state(r#"{"version":1,"screen_profile":"2560x1600"}"#).unwrap();
+        assert_eq!(state.screen_profile, ScreenProfile::Wqxga);
+        assert_eq!(state.current_game_id, None);
+
+        // Fields dropped from the state are ignored
+        let state = parse_state(r#"{"version":1,"rating_format":"14.0"}"#).unwrap();
+        assert_eq!(state, RuntimeState::default());
+
+        assert_eq!(
+            parse_state(r#"{"version":2}"#).unwrap_err().kind(),
+            "config"
+        );
+        assert_eq!(parse_state(r#"{"version":0}"#).unwrap_err().kind(), "data");
+        assert!(parse_state("{").is_err());
+    }
+
+    #[test]
+    fn test_corrupt_file_recovery() {
+        let dir = TempDir::new().unwrap();
+        let path = state_file_path(&dir);
+        std::fs::write(&path, "{\"current_game_id\":").unwrap();
+
+        let context = load_context(&path).unwrap();
+        assert_eq!(context.state(), RuntimeState::default());
+        assert!(!path.exists());
+        assert_eq!(
+            backup_contents(&dir),
+            vec!["{\"current_game_id\":".to_string()]
+        );
+
+        context
+            .update_state(|state| state.auto_mode_all = true)
+            .unwrap();
+        assert!(load_context(&path).unwrap().state().auto_mode_all);
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        std::fs::write(&path, "[]").unwrap();
+        load_context(&path).unwrap();
+        assert_eq!(backup_contents(&dir).len(), 2);
+    }
+
+    #[test]
+    fn test_newer_version() {
+        let dir = TempDir::new().unwrap();
+        let path = state_file_path(&dir);
+        let contents = format!("{{\"version\":{}}}", STATE_VERSION + 1);
+        std::fs::write(&path, &contents).unwrap();
+
+        assert!(load_context(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+        assert!(backup_contents(&dir).is_empty());
+    }
 }
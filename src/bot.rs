@@ -1,8 +1,13 @@
 mod bot_reply;
+mod rate_limiter;
 
 use crate::opt::*;
 
-use std::{collections::HashMap, env, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use indicium::simple::SearchIndex;
 
@@ -14,15 +19,33 @@ use serenity::{
 };
 
 use itertools::Itertools;
-
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::app_config;
+use crate::card_query::CardQuery;
+use crate::deck_builder;
+use crate::deck_export::{self, DeckExportFormat};
+use crate::draft_replay::{self, DraftReplay};
+use crate::draft_signals::{self, DraftSignals};
+use crate::draft_simulator::DraftSimulator;
+use crate::draft_stats;
+use crate::metrics;
 use crate::models::{card::*, draft_data::DraftPick};
+use crate::pool_analysis::{self, PoolAnalysisFormat};
+use crate::team_rating::{self, TeamRating};
+use crate::vote_aggregation::{self, VoteTally};
 use crate::{app_context::AppContext, models::draft_game::DraftGame};
 use crate::{
     db_access::{self, get_last_draft_record},
-    models::draft_game::DraftVote,
+    models::card_rating::TEAM_RATING_FORMAT,
+    models::draft_data::{DraftRecord, DRAFT_PICK_COUNT},
+    models::draft_game::{DraftVote, VoteMode},
+    models::draft_result::DraftResult,
+    models::guild_config::{GuildConfig, DEFAULT_THREAD_PREFIX},
 };
 
 use self::bot_reply::BotReply;
+use self::rate_limiter::{RateLimit, RateLimiter, TtlCache};
 
 const DRAFT_CMD: &str = if cfg!(debug_assertions) {
     "!dd"
@@ -33,30 +56,123 @@ const DRAFT_CMD_HELP: &str = r#"
 !draft - Get the current draft selection
 !draft pic - Get the screenshot of current draft selection
 !draft reg <game_id> - Register an existing draft
+!draft new - Start and own a new game in its own thread
 !draft own <game_id> - Register and own a game
+!draft sim [set_name] - Practice a draft against rating-based bots. Vote and commit as usual, it ends after 6 idle hours or a bot restart
 !draft deck - Get the current deck
+!draft export [eternal|text|json] - Export the current deck. Eternal format can be imported in the client
+!draft stats [text|json] - Show the cost curve, card types, influence and top cards of the drafted pool
+!draft build - Suggest factions, a 45 card deck and a power base from the drafted cards
+!draft signals - Show which factions look open from the cards passed in each pack
+!draft history [@user] - List your past games, or the games of another user
+!draft pick <n> [game_id] - Show what was taken and passed at pick n
+!draft replay [game_id] - Step through a draft from the first pick
+!draft replay next|prev - Show the next or previous pick of the draft being replayed
 !draft vote <card_id|card_name> - Vote for a card
+!draft vote <card> > <card> > ... - Vote for several cards in order of preference
+!draft unvote - Remove your vote for the current pick
+!draft votes - Show the votes for the current pick
+!draft mode <plurality|weighted|ranked> - Set how votes are counted for the game
+!draft result - Show the match results of the game
+!draft result <win|loss> [notes] - Record a match. Only the owner can perform this.
+!draft final [deck] - Save the final deck, pasted from the Eternal client or the drafted cards by default
+!draft winrate [@user] - Show the record across all your games, or the games of another user
+!draft cardstats [card_name] - Show average pick and win rate of drafted cards
+!draft teamratings - Show where team ratings from picks, votes and results differ from the published list, recomputed at most hourly
+!draft teamratings <card_name> - Show the team rating of a card
+!draft migrate - Ask to move votes and games recorded under your display name to your Discord account
+!draft migrate confirm <@user> - Bot owner only, move the records of a user who asked for it
 !draft commit - Commit the highest voted card. Only the owner can perform this.
+
+!card <card_name> - Show a card with its text and rating
+!card <n> - Show card n from the last list of matches
+!card <filters> - Search cards, e.g. cost<=3 influence:FJ type:unit set:"Empty Throne" rating>=B
+!card <filters> - Abilities can be searched too, e.g. keyword:flying has:removal|sweeper|damage|draw|tokens
+
+!channel list - Show the channels the bot listens to in this server
+!channel add [#channel] - Allow a channel or thread. Defaults to the current one
+!channel remove [#channel] - Remove a channel or thread from the allow-list
+!channel prefix <prefix> - Allow every channel and thread whose name starts with the prefix
+!channel prefix clear - Stop allowing channels by name
+
+!carddata update <file_name> - Load a card export from the import directory of the bot host, report what changed and use it right away. Bot owner only
+!carddata reload - Reload the current card data file. Bot owner only
 "#;
 
 const DRAFT_HELP_CMD: &str = "help";
 const DRAFT_REG_CMD: &str = "reg";
+const DRAFT_NEW_CMD: &str = "new";
 const DRAFT_OWN_CMD: &str = "own";
+const DRAFT_SIM_CMD: &str = "sim";
 const DRAFT_DECK_CMD: &str = "deck";
+const DRAFT_EXPORT_CMD: &str = "export";
+const DRAFT_BUILD_CMD: &str = "build";
+const DRAFT_STATS_CMD: &str = "stats";
+const DRAFT_SIGNALS_CMD: &str = "signals";
+const DRAFT_HISTORY_CMD: &str = "history";
+const DRAFT_PICK_CMD: &str = "pick";
+const DRAFT_REPLAY_CMD: &str = "replay";
+const DRAFT_RESULT_CMD: &str = "result";
+const DRAFT_FINAL_CMD: &str = "final";
+const DRAFT_WINRATE_CMD: &str = "winrate";
+const DRAFT_CARDSTATS_CMD: &str = "cardstats";
+const DRAFT_TEAMRATINGS_CMD: &str = "teamratings";
+const DRAFT_REPLAY_NEXT: &str = "next";
+const DRAFT_REPLAY_PREV: &str = "prev";
 const DRAFT_VOTE_CMD: &str = "vote";
+const DRAFT_UNVOTE_CMD: &str = "unvote";
+const DRAFT_VOTES_CMD: &str = "votes";
+const DRAFT_MODE_CMD: &str = "mode";
+const DRAFT_MIGRATE_CMD: &str = "migrate";
+const DRAFT_MIGRATE_CONFIRM_CMD: &str = "confirm";
 const DRAFT_COMMIT_CMD: &str = "commit";
 const DRAFT_PIC_CMD: &str = "pic";
 
-const CARD_COMMAND: &str = "!card";
+const VOTE_RANKING_SEPARATOR: char = '>';
 
-const DRAFT_THREAD_PREFIX: &str = "draft";
+const CARD_COMMAND: &str = "!card";
+const CARD_CHOICE_LIMIT: usize = 10;
+const HISTORY_GAME_LIMIT: usize = 10;
+const WINRATE_GAME_LIMIT: usize = 1000;
+const CARD_STATS_LIMIT: usize = 15;
+const TEAM_RATING_LIMIT: usize = 15;
 
 const CHANNEL_LIST_FILE: &str = "./resource/discord_channels.txt";
 
 const CHANNEL_LIST_KEY: &str = "_channel_list";
 
+const PING_CMD: &str = "!ping";
+
+// Threads are archived by Discord after a day without messages
+const DRAFT_THREAD_ARCHIVE_MINUTES: u16 = 1440;
+
+const CHANNEL_CMD: &str = "!channel";
+const CHANNEL_LIST_CMD: &str = "list";
+const CHANNEL_ADD_CMD: &str = "add";
+const CHANNEL_REMOVE_CMD: &str = "remove";
+const CHANNEL_PREFIX_CMD: &str = "prefix";
+const CHANNEL_PREFIX_CLEAR: &str = "clear";
+
+const CARD_DATA_CMD: &str = "!carddata";
+const CARD_DATA_UPDATE_CMD: &str = "update";
+const CARD_DATA_RELOAD_CMD: &str = "reload";
+const CARD_DATA_REPORT_LIMIT: usize = 10;
+
+const USER_RATE_LIMIT: usize = 5;
+const USER_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(10);
+const CHANNEL_RATE_LIMIT: usize = 20;
+const CHANNEL_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+const CHANNEL_INFO_TTL: Duration = Duration::from_secs(10 * 60);
+const MAX_CONCURRENT_DB_COMMANDS: usize = 4;
+const DB_COMMAND_WAIT: Duration = Duration::from_secs(10);
+const MIGRATION_REQUEST_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const TEAM_RATING_TTL: Duration = Duration::from_secs(60 * 60);
+const CARD_CHOICE_TTL: Duration = Duration::from_secs(10 * 60);
+const REPLAY_CURSOR_TTL: Duration = Duration::from_secs(60 * 60);
+const SIMULATION_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
 async fn create_bot() {
-    let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
+    let token = app_config::get().discord.token.to_string();
     let intents = GatewayIntents::GUILD_MESSAGES
         | GatewayIntents::DIRECT_MESSAGES
         | GatewayIntents::MESSAGE_CONTENT;
@@ -68,10 +184,10 @@ async fn create_bot() {
 
     tokio::spawn(async move {
         if let Err(why) = client.start().await {
-            println!("Client error: {:?}", why);
+            error!("Client error: {:?}", why);
         }
     });
-    println!("Bot started");
+    info!("Bot started");
 }
 
 struct BotCardData;
@@ -84,20 +200,81 @@ impl TypeMapKey for BotCardIndex {
     type Value = Arc<SearchIndex<String>>;
 }
 
+struct BotCardRating;
+impl TypeMapKey for BotCardRating {
+    type Value = Arc<HashMap<String, String>>;
+}
+
 struct BotCache;
 impl TypeMapKey for BotCache {
     type Value = Arc<RwLock<HashMap<String, String>>>;
 }
 
+// Simulators only live in memory, an idle one is dropped after SIMULATION_TTL
+struct BotSimulations;
+impl TypeMapKey for BotSimulations {
+    type Value = Arc<Mutex<TtlCache<String, Arc<Mutex<DraftSimulator>>>>>;
+}
+
+struct BotUserNames;
+impl TypeMapKey for BotUserNames {
+    type Value = Arc<RwLock<HashMap<String, String>>>;
+}
+
+struct BotRateLimits {
+    user: RateLimiter<u64>,
+    channel: RateLimiter<u64>,
+}
+
+struct BotRateLimit;
+impl TypeMapKey for BotRateLimit {
+    type Value = Arc<Mutex<BotRateLimits>>;
+}
+
+struct BotChannelCache;
+impl TypeMapKey for BotChannelCache {
+    type Value = Arc<RwLock<TtlCache<u64, bool>>>;
+}
+
+// Display names waiting for the bot owner to confirm a migration, by user key
+struct BotMigrationRequests;
+impl TypeMapKey for BotMigrationRequests {
+    type Value = Arc<RwLock<TtlCache<String, String>>>;
+}
+
+// Matches of the last ambiguous !card search, by channel and user key
+struct BotCardChoices;
+impl TypeMapKey for BotCardChoices {
+    type Value = Arc<RwLock<TtlCache<(u64, String), Vec<String>>>>;
+}
+
+// Game and pick each channel and user key is replaying
+struct BotReplayCursors;
+impl TypeMapKey for BotReplayCursors {
+    type Value = Arc<RwLock<TtlCache<(u64, String), (String, u8)>>>;
+}
+
+// Recomputing reads every pick, vote and result, so it runs at most once per TTL
+struct BotTeamRatings;
+impl TypeMapKey for BotTeamRatings {
+    type Value = Arc<RwLock<TtlCache<String, Arc<Vec<TeamRating>>>>>;
+}
+
+struct BotGuildConfigs;
+impl TypeMapKey for BotGuildConfigs {
+    type Value = Arc<RwLock<HashMap<u64, GuildConfig>>>;
+}
+
+struct BotDbPermits;
+impl TypeMapKey for BotDbPermits {
+    type Value = Arc<tokio::sync::Semaphore>;
+}
+
 struct BotAppContext;
 impl TypeMapKey for BotAppContext {
     type Value = Arc<AppContext>;
 }
 
-fn log(s: String) {
-    log_if(s.as_str(), DbgFlg::Bot);
-}
-
 fn load_channel_list() -> Vec<ChannelId> {
     let file = std::fs::File::open(CHANNEL_LIST_FILE).expect("Failed to open channel list file");
     let reader = std::io::BufReader::new(file);
@@ -143,7 +320,7 @@ fn get_card_internal(
     card_index: &Arc<SearchIndex<String>>,
     card_data: &Arc<HashMap<String, Card>>,
     input_str: &str,
-) -> Result<Card, String> {
+) -> Res<Card> {
     let search_result = card_index.search(input_str);
     let found_card_name: &str;
     match search_result.len() {
@@ -151,7 +328,7 @@ fn get_card_internal(
             found_card_name = search_result[0];
         }
         0 => {
-            return Err("No card found".to_string());
+            return Err(Error::Data("No card found".to_string()));
         }
         _ => {
             let found_cards = search_result
@@ -160,20 +337,23 @@ fn get_card_internal(
                 .map(|s| format!("[{}]", s))
                 .collect::<Vec<String>>()
                 .join(", ");
-            return Err(format!("Multiple cards found: {}", &found_cards));
+            return Err(Error::Data(format!(
+                "Multiple cards found: {}",
+                &found_cards
+            )));
         }
     }
 
     card_data.get(found_card_name).cloned().ok_or_else(|| {
-        format!(
+        Error::Data(format!(
             "Card {} not found in data. Data contains {} cards",
             found_card_name,
             card_data.len()
-        )
+        ))
     })
 }
 
-async fn get_cards(ctx: &Context, input_strs: &[String]) -> Result<Vec<Card>, String> {
+async fn get_cards(ctx: &Context, input_strs: &[String]) -> Res<Vec<Card>> {
     let card_index = {
         let data = ctx.data.read().await;
         data.get::<BotCardIndex>()
@@ -195,24 +375,86 @@ async fn get_cards(ctx: &Context, input_strs: &[String]) -> Result<Vec<Card>, St
     })
 }
 
-async fn get_card(ctx: &Context, input_str: &str) -> Result<Card, String> {
+// Decklists hold exact names, a fuzzy match could swap in the wrong card
+async fn get_deck_cards(ctx: &Context, names: &[String]) -> Res<Vec<Card>> {
+    let card_data = {
+        let data = ctx.data.read().await;
+        data.get::<BotCardData>()
+            .expect("Expected CardData in TypeMap.")
+            .clone()
+    };
+
+    crate::card_loader::cards_by_names(&card_data, names)
+}
+
+async fn get_card(ctx: &Context, input_str: &str) -> Res<Card> {
     get_cards(ctx, &[input_str.to_string()])
         .await
         .and_then(|mut v| {
             if v.len() == 1 {
                 Ok(v.remove(0))
             } else {
-                Err("No card found".to_string())
+                Err(Error::Data("No card found".to_string()))
             }
         })
 }
 
+fn get_user_key(user: &User) -> String {
+    user.id.to_string()
+}
+
+async fn remember_user_name(ctx: &Context, user: &User) {
+    let user_names_lock = {
+        let data = ctx.data.read().await;
+        data.get::<BotUserNames>()
+            .expect("Expected BotUserNames in TypeMap.")
+            .clone()
+    };
+
+    let mut user_names = user_names_lock.write().await;
+    user_names.insert(get_user_key(user), user.name.to_string());
+}
+
+async fn get_user_display_name(ctx: &Context, user_key: &str) -> String {
+    let user_names_lock = {
+        let data = ctx.data.read().await;
+        data.get::<BotUserNames>()
+            .expect("Expected BotUserNames in TypeMap.")
+            .clone()
+    };
+
+    if let Some(name) = user_names_lock.read().await.get(user_key) {
+        return name.to_string();
+    }
+
+    // Records that predate the migration are still keyed by display name
+    let user_id = match user_key.parse::<u64>() {
+        Ok(user_id) => UserId(user_id),
+        Err(_) => return user_key.to_string(),
+    };
+
+    match user_id.to_user(&ctx.http).await {
+        Ok(user) => {
+            let mut user_names = user_names_lock.write().await;
+            user_names.insert(user_key.to_string(), user.name.to_string());
+            user.name
+        }
+        Err(err) => {
+            warn!("Unable to look up user {}: {:?}", user_key, err);
+            user_key.to_string()
+        }
+    }
+}
+
 async fn send_message(ctx: &Context, channel_id: ChannelId, msg: &str) {
-    if let Err(why) = channel_id.say(&ctx.http, msg).await {
-        println!("Error sending message: {:?}", why);
+    for message in bot_reply::split_message(msg, bot_reply::MESSAGE_LIMIT) {
+        if let Err(why) = channel_id.say(&ctx.http, &message).await {
+            warn!("Error sending message: {:?}", why);
+            break;
+        }
     }
 
-    log(format!("Send to channel {}: {}", channel_id, msg));
+    debug!("Send to channel {}: {}", channel_id, msg);
 }
 
 async fn register_game_in_cache(ctx: &Context, channel_id: u64, game_id: &str) {
@@ -232,31 +474,184 @@ async fn register_game_in_cache(ctx: &Context, channel_id: u64, game_id: &str) {
     }
 }
 
-async fn own_game(ctx: &Context, user: &str, channel_id: u64, game_id: &str) -> Result<(), String> {
+async fn register_game(ctx: &Context, channel_id: u64, game_id: &str) -> Res<()> {
+    if game_id.is_empty() {
+        return Err(Error::Data(
+            "Give the id of the game to register".to_string(),
+        ));
+    }
+    if !DraftGame::is_valid_id(game_id) {
+        return Err(Error::Data(format!("Invalid game id [{}]", game_id)));
+    }
+    register_game_in_cache(ctx, channel_id, game_id).await;
+    Ok(())
+}
+
+async fn own_game(ctx: &Context, user: &str, channel_id: u64, game_id: &str) -> Res<DraftGame> {
+    if game_id.is_empty() {
+        return Err(Error::Data("Give the id of the game to own".to_string()));
+    }
+    if !DraftGame::is_valid_id(game_id) {
+        return Err(Error::Data(format!("Invalid game id [{}]", game_id)));
+    }
     register_game_in_cache(ctx, channel_id, game_id).await;
 
     let mut draft_game: DraftGame;
-    match db_access::get_draft_game(game_id)
-        .await
-        .map_err(|err| err.to_string())?
-    {
+    match db_access::get_draft_game(game_id).await? {
         Some(game) => {
             draft_game = game;
         }
         None => {
-            draft_game = db_access::insert_draft_game(game_id)
-                .await
-                .map_err(|err| err.to_string())?;
+            draft_game = db_access::insert_draft_game(game_id).await?;
         }
     }
     draft_game.user_id = Some(user.to_string());
-    db_access::upsert_draft_game(&draft_game)
+    db_access::upsert_draft_game(&draft_game).await?;
+
+    Ok(draft_game)
+}
+
+async fn create_game_thread(
+    ctx: &Context,
+    channel_id: ChannelId,
+    draft_game: &mut DraftGame,
+) -> Res<ChannelId> {
+    let channel = match ctx
+        .http
+        .get_channel(channel_id.0)
+        .await
+        .err_to(Error::Discord)?
+    {
+        Channel::Guild(channel) => channel,
+        _ => {
+            return Err(Error::Data(
+                "Threads can only be created in a server".to_string(),
+            ))
+        }
+    };
+
+    // Threads cannot hold threads of their own, so use the parent channel instead
+    let parent_id = match channel.kind {
+        ChannelType::PublicThread | ChannelType::PrivateThread => {
+            channel.parent_id.unwrap_or(channel.id)
+        }
+        _ => channel.id,
+    };
+
+    let mut guild_config = get_guild_config(ctx, channel.guild_id).await;
+    let thread_prefix = if guild_config.thread_prefix.is_empty() {
+        DEFAULT_THREAD_PREFIX.to_string()
+    } else {
+        guild_config.thread_prefix.to_string()
+    };
+
+    let starter_message = parent_id
+        .say(
+            &ctx.http,
+            format!("Draft thread for game [{}]", draft_game.game_id),
+        )
         .await
-        .map_err(|err| err.to_string())?;
+        .err_to(Error::Discord)?;
+    let thread = parent_id
+        .create_public_thread(&ctx.http, starter_message.id, |thread| {
+            thread
+                .name(format!("{}-{}", thread_prefix, draft_game.game_id))
+                .kind(ChannelType::PublicThread)
+                .auto_archive_duration(DRAFT_THREAD_ARCHIVE_MINUTES)
+        })
+        .await
+        .err_to(Error::Discord)?;
+
+    // Without a prefix the name does not allow the thread, so it is added by id
+    if !guild_config.matches_prefix(&thread.name)
+        && !guild_config.has_channel(&parent_id.to_string())
+        && guild_config.add_channel(&thread.id.to_string())
+    {
+        save_guild_config(ctx, channel.guild_id, guild_config).await?;
+    }
+
+    register_game_in_cache(ctx, thread.id.0, &draft_game.game_id).await;
+
+    draft_game.thread_id = Some(thread.id.to_string());
+    db_access::upsert_draft_game(draft_game).await?;
+
+    let mut reply = BotReply::new();
+    reply.add(format!("Game [{}]", draft_game.game_id));
+    match get_draft_data(&draft_game.game_id).await {
+        Some((draft_pick, selection_text)) => {
+            reply.add(format!(
+                "Card {} of {}",
+                draft_pick.pick_id, DRAFT_PICK_COUNT
+            ));
+            reply.add_boxed(selection_text);
+        }
+        None => {
+            reply.add("Waiting for the first pick".to_string());
+        }
+    }
+    send_message(ctx, thread.id, &reply.to_string()).await;
+
+    Ok(thread.id)
+}
+
+async fn get_or_create_game_thread(
+    ctx: &Context,
+    channel_id: ChannelId,
+    draft_game: &mut DraftGame,
+) -> Res<ChannelId> {
+    if let Some(thread_id) = draft_game
+        .thread_id
+        .as_ref()
+        .and_then(|thread_id| thread_id.parse::<u64>().ok())
+    {
+        register_game_in_cache(ctx, thread_id, &draft_game.game_id).await;
+        return Ok(ChannelId(thread_id));
+    }
+
+    create_game_thread(ctx, channel_id, draft_game).await
+}
+
+async fn finish_game_thread(ctx: &Context, game_id: &str) -> Res<()> {
+    let draft_game = db_access::get_draft_game(game_id)
+        .await?
+        .ok_or(Error::Data(format!("Game [{}] does not exist", game_id)))?;
+
+    let thread_id = match draft_game
+        .thread_id
+        .and_then(|thread_id| thread_id.parse::<u64>().ok())
+    {
+        Some(thread_id) => ChannelId(thread_id),
+        None => return Ok(()),
+    };
+
+    let mut reply = BotReply::new();
+    reply.add(format!("Draft [{}] is complete. Final deck:", game_id));
+    reply.add_boxed(get_decklist(ctx, game_id).await);
+    send_message(ctx, thread_id, &reply.to_string()).await;
+
+    thread_id
+        .edit_thread(&ctx.http, |thread| thread.archived(true))
+        .await
+        .err_to(Error::Discord)?;
 
     Ok(())
 }
 
+async fn get_registered_game(ctx: &Context, channel_id: ChannelId) -> Option<String> {
+    if let Some(game_id) = get_cached_data(ctx, channel_id.to_string().as_ref()).await {
+        return Some(game_id);
+    }
+
+    // The cache is lost on restart, but game threads are stored with their game
+    match db_access::get_draft_game_by_thread(&channel_id.to_string()).await {
+        Ok(Some(draft_game)) => {
+            register_game_in_cache(ctx, channel_id.0, &draft_game.game_id).await;
+            Some(draft_game.game_id)
+        }
+        _ => None,
+    }
+}
+
 async fn get_decklist(ctx: &Context, game_id: &str) -> String {
     let deck_list = match db_access::get_decklist(game_id).await {
         Ok(deck_list) => get_cards(ctx, &deck_list)
@@ -272,171 +667,998 @@ async fn get_decklist(ctx: &Context, game_id: &str) -> String {
                     .join("\n")
                     .to_string()
             })
-            .unwrap_or_else(|err| err.to_string()),
+            .unwrap_or_else(|err| err.user_message()),
         _ => "No data".to_string(),
     };
 
     deck_list
 }
 
-async fn get_last_pic(game_id: &str) -> Option<String> {
-    db_access::get_last_draft_record(game_id)
-        .await
-        .ok()?
-        .and_then(|record| record.image_url.map(String::from))
-}
+async fn get_deck_export(ctx: &Context, game_id: &str, format_text: &str) -> Res<String> {
+    let format = match format_text.trim() {
+        "" => DeckExportFormat::default(),
+        format_text => format_text
+            .parse::<DeckExportFormat>()
+            .map_err(|_| Error::Data(format!("Unknown export format [{}]", format_text)))?,
+    };
 
-async fn get_chosen_pick(game_id: &str) -> Result<u8, String> {
-    let draft_record = db_access::get_last_draft_record(game_id)
-        .await
-        .map_err(|err| err.to_string())?
-        .ok_or(format!(
-            "Unable to get last draft record for game {}",
-            game_id
-        ))?;
+    let deck_list = db_access::get_decklist(game_id).await?;
+    let cards = get_deck_cards(ctx, &deck_list).await?;
+
+    deck_export::export_deck(&cards, &format)
+}
 
-    let highest_pick = db_access::get_highest_voted_pick(&game_id, &draft_record.pick).await?;
+async fn get_deck_suggestion(ctx: &Context, game_id: &str) -> Res<String> {
+    let deck_list = db_access::get_decklist(game_id).await?;
+    let cards = get_deck_cards(ctx, &deck_list).await?;
+    let ratings = get_card_ratings(ctx).await;
 
-    highest_pick.ok_or(format!(
-        "Unable to get highest voted pick for pick {} in game [{}]",
-        draft_record.pick.to_string(),
-        game_id.to_string(),
-    ))
+    deck_builder::build_deck(&cards, &ratings).map(|suggestion| suggestion.to_text(&ratings))
 }
 
-async fn pick_card(game_id: &str, pick_idx: u8) -> Result<(DraftPick, String), String> {
-    let draft_record = db_access::get_last_draft_record(game_id)
-        .await
-        .map_err(|err| err.to_string())?;
-    match draft_record {
-        Some(draft_record) => {
-            if draft_record.selection_vec.len() > 0
-                && pick_idx as usize >= draft_record.selection_vec.len()
-            {
-                return Err(format!(
-                    "Pick index {} is out of bounds for game {}",
-                    pick_idx, game_id
-                ));
-            }
+async fn get_pool_analysis(ctx: &Context, game_id: &str, format_text: &str) -> Res<String> {
+    let format = match format_text.trim() {
+        "" => PoolAnalysisFormat::default(),
+        format_text => format_text
+            .parse::<PoolAnalysisFormat>()
+            .map_err(|_| Error::Data(format!("Unknown stats format [{}]", format_text)))?,
+    };
 
-            let mut draft_record_with_pick = draft_record.clone();
-            draft_record_with_pick.pick_card(pick_idx);
+    let deck_list = db_access::get_decklist(game_id).await?;
+    let cards = get_deck_cards(ctx, &deck_list).await?;
+    let ratings = get_card_ratings(ctx).await;
 
-            log(format!("picking card at index {}", pick_idx));
+    pool_analysis::analyze_pool(&cards, &ratings).render(&format)
+}
 
-            db_access::upsert_draft_record(&draft_record_with_pick)
-                .await
-                .map_err(|err| err.to_string())?;
+async fn get_draft_signals(ctx: &Context, game_id: &str) -> Res<DraftSignals> {
+    let records = db_access::get_draft_records(game_id).await?;
+    let card_data = {
+        let data = ctx.data.read().await;
+        data.get::<BotCardData>()
+            .expect("Expected CardData in TypeMap.")
+            .clone()
+    };
+    let ratings = get_card_ratings(ctx).await;
 
-            let selected_card_text = if draft_record_with_pick.selection_vec.len() > 0 {
-                draft_record_with_pick.selection_vec[pick_idx as usize].to_string()
-            } else {
-                format!("Card at position {}", pick_idx + 1)
-            };
+    Ok(draft_signals::analyze_signals(
+        &records, &card_data, &ratings,
+    ))
+}
 
-            return Ok((draft_record_with_pick.pick, selected_card_text));
+fn parse_user_id(text: &str) -> Option<String> {
+    text.trim()
+        .trim_start_matches("<@")
+        .trim_start_matches('!')
+        .trim_end_matches('>')
+        .parse::<u64>()
+        .ok()
+        .map(|user_id| user_id.to_string())
+}
+
+async fn get_history_text(ctx: &Context, user_key: &str, args: &str) -> Res<String> {
+    let user_key = match args.trim() {
+        "" => user_key.to_string(),
+        mention => {
+            parse_user_id(mention).ok_or(Error::Data(format!("Unknown user [{}]", mention)))?
         }
-        None => {}
+    };
+
+    let games = db_access::get_draft_games_by_user(&user_key, HISTORY_GAME_LIMIT).await?;
+    if games.is_empty() {
+        return Ok(format!(
+            "No games found for {}",
+            get_user_display_name(ctx, &user_key).await
+        ));
     }
 
-    Err(format!("Unable to find draft record for game {}", game_id))
+    let mut lines = vec![format!(
+        "Games of {}",
+        get_user_display_name(ctx, &user_key).await
+    )];
+    let game_ids = games
+        .iter()
+        .map(|game| game.game_id.to_string())
+        .collect::<Vec<String>>();
+    let pick_counts = db_access::get_taken_pick_counts(&game_ids).await?;
+    for game in games.iter() {
+        lines.push(format!(
+            "{:10} {:25} {:>2} picks",
+            game.game_id,
+            game.time,
+            pick_counts.get(&game.game_id).unwrap_or(&0)
+        ));
+    }
+
+    Ok(lines.join("\n"))
 }
 
-async fn vote_card(
+// Tags the command span so every event of the command carries the game
+fn record_game_id(game_id: &str) {
+    tracing::Span::current().record("game_id", game_id);
+}
+
+// An explicit game id wins, then the game of the channel, then the last game of the user
+async fn resolve_game_id(
     ctx: &Context,
-    game_id: &str,
-    user: &str,
-    vote_text: &str,
-) -> Res<(DraftPick, String)> {
-    let draft_record = get_last_draft_record(game_id)
-        .await
-        .map_err(|err| err.to_string())?
-        .ok_or("Could not find draft record".to_string())?;
+    channel_id: ChannelId,
+    user_key: &str,
+    game_id: Option<&str>,
+) -> Res<String> {
+    let game_id = match game_id.filter(|game_id| !game_id.is_empty()) {
+        Some(game_id) if !DraftGame::is_valid_id(game_id) => {
+            return Err(Error::Data(format!("Invalid game id [{}]", game_id)));
+        }
+        Some(game_id) => game_id.to_string(),
+        None => match get_registered_game(ctx, channel_id).await {
+            Some(game_id) => game_id,
+            None => db_access::get_last_draft_game_by_user(user_key)
+                .await?
+                .map(|game| game.game_id)
+                .ok_or(Error::Data(
+                    "No game is registered in this channel and you have no past games".to_string(),
+                ))?,
+        },
+    };
+    record_game_id(&game_id);
+    Ok(game_id)
+}
 
-    let vote_idx: u8;
-    if let Ok(pick_num) = vote_text.parse::<u8>() {
-        let pick_num = pick_num - 1;
-        match draft_record.selection_vec.iter().nth((pick_num) as usize) {
-            Some(card_name) => {
-                vote_idx = pick_num;
-            }
-            _ => {
-                return Err(format!("Unable to find card at index {}", pick_num));
-            }
+async fn get_pick_text(
+    ctx: &Context,
+    channel_id: ChannelId,
+    user_key: &str,
+    args: &str,
+) -> Res<String> {
+    let mut args = args.split_whitespace();
+    let pick_id = args
+        .next()
+        .and_then(|pick| pick.parse::<u8>().ok())
+        .filter(|pick| (1..=DRAFT_PICK_COUNT).contains(pick))
+        .ok_or(Error::Data(format!(
+            "Pick must be between 1 and {}",
+            DRAFT_PICK_COUNT
+        )))?;
+    let game_id = resolve_game_id(ctx, channel_id, user_key, args.next()).await?;
+
+    let record = db_access::get_draft_record(&game_id, &DraftPick::new(pick_id))
+        .await?
+        .ok_or(Error::Data(format!(
+            "Pick {} of game [{}] was not recorded",
+            pick_id, game_id
+        )))?;
+    let ratings = get_card_ratings(ctx).await;
+
+    Ok(format!(
+        "Game [{}]\n{}",
+        game_id,
+        draft_replay::pick_text(&record, &ratings)
+    ))
+}
+
+async fn get_replay_cursors(ctx: &Context) -> Arc<RwLock<TtlCache<(u64, String), (String, u8)>>> {
+    let data = ctx.data.read().await;
+    data.get::<BotReplayCursors>()
+        .expect("Expected BotReplayCursors in TypeMap.")
+        .clone()
+}
+
+async fn replay_draft(
+    ctx: &Context,
+    channel_id: ChannelId,
+    user_key: &str,
+    args: &str,
+) -> Res<String> {
+    let replay_key = (channel_id.0, user_key.to_string());
+    let args = args.trim();
+
+    let (game_id, current_pick) = match args {
+        DRAFT_REPLAY_NEXT | DRAFT_REPLAY_PREV => {
+            let (game_id, pick_id) = get_replay_cursors(ctx)
+                .await
+                .read()
+                .await
+                .get(&replay_key, Instant::now())
+                .ok_or(Error::Data(
+                    "No draft is being replayed, start one with `replay [game_id]`".to_string(),
+                ))?;
+            (game_id, Some(pick_id))
         }
+        game_id => (
+            resolve_game_id(ctx, channel_id, user_key, Some(game_id)).await?,
+            None,
+        ),
+    };
+
+    let replay = DraftReplay::new(&game_id, db_access::get_draft_records(&game_id).await?);
+    if replay.is_empty() {
+        return Err(Error::Data(format!(
+            "No picks were recorded for game [{}]",
+            game_id
+        )));
+    }
+
+    let pick_id = match (args, current_pick) {
+        (DRAFT_REPLAY_NEXT, Some(pick_id)) => replay
+            .next_pick(pick_id)
+            .ok_or(Error::Data("This was the last recorded pick".to_string()))?,
+        (DRAFT_REPLAY_PREV, Some(pick_id)) => replay
+            .prev_pick(pick_id)
+            .ok_or(Error::Data("This was the first recorded pick".to_string()))?,
+        _ => replay.first_pick().unwrap_or(1),
+    };
+    get_replay_cursors(ctx).await.write().await.insert(
+        replay_key,
+        (game_id.to_string(), pick_id),
+        Instant::now(),
+    );
+
+    let ratings = get_card_ratings(ctx).await;
+    let pick_text = replay
+        .record(pick_id)
+        .map(|record| draft_replay::pick_text(record, &ratings))
+        .unwrap_or_default();
+
+    if current_pick.is_none() {
+        Ok(format!(
+            "Replaying game [{}]\n{}\n\n{}",
+            game_id,
+            replay.summary(),
+            pick_text
+        ))
     } else {
-        vote_idx = find_card_in_list(&draft_record.selection_vec, vote_text)
-            .await
-            .ok_or("Unable to find card in list with vote text".to_string())?;
+        Ok(format!("Game [{}]\n{}", game_id, pick_text))
     }
+}
 
-    let draft_vote = DraftVote::new(game_id, user, &draft_record.pick, vote_idx);
-    db_access::upsert_draft_vote(&draft_vote)
-        .await
-        .map_err(|err| err.to_string())?;
+async fn get_owned_draft_result(ctx: &Context, game_id: &str, user: &str) -> Res<DraftResult> {
+    let draft_game = db_access::get_draft_game(game_id)
+        .await?
+        .ok_or(Error::Data(format!("Game [{}] does not exist", game_id)))?;
+    ensure_game_owner(ctx, &draft_game, user, "record results").await?;
 
-    Ok((
-        draft_record.pick.clone(),
-        draft_record.selection_vec[vote_idx as usize].to_string(),
-    ))
+    Ok(db_access::get_draft_result(game_id)
+        .await?
+        .unwrap_or_else(|| DraftResult::new(game_id)))
 }
 
-async fn get_cached_data(ctx: &Context, cache_key: &str) -> Option<String> {
-    let cache_lock = {
-        let data = ctx.data.read().await;
-        data.get::<BotCache>()
-            .expect("Expected BotCache in TypeMap.")
-            .clone()
+async fn record_result(ctx: &Context, game_id: &str, user: &str, args: &str) -> Res<String> {
+    let (outcome, notes) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+    if outcome.is_empty() {
+        return Ok(db_access::get_draft_result(game_id)
+            .await?
+            .map(|result| result.to_text())
+            .unwrap_or("No results recorded yet".to_string()));
+    }
+
+    let won = match outcome.to_lowercase().as_str() {
+        "win" | "w" => true,
+        "loss" | "l" => false,
+        _ => {
+            return Err(Error::Data(format!(
+                "Expected win or loss, got [{}]",
+                outcome
+            )))
+        }
     };
 
-    let cache = cache_lock.read().await;
-    cache.get(cache_key).cloned()
-}
+    let mut draft_result = get_owned_draft_result(ctx, game_id, user).await?;
+    draft_result.add_match(won, Some(notes))?;
 
-async fn process_draft_command(ctx: &Context, channel_id: ChannelId, user: User, args: &str) {
-    let mut reply = BotReply::new();
-    let mut cmd_parts = args.splitn(2, char::is_whitespace);
-    let cmd = cmd_parts.next();
-    let channel_id_number = channel_id.as_u64().to_owned();
+    // Results without a saved deck fall back to everything that was drafted
+    if draft_result.final_deck.is_empty() {
+        draft_result.final_deck = db_access::get_decklist(game_id).await?;
+    }
+    db_access::upsert_draft_result(&draft_result).await?;
 
-    match cmd {
-        Some(sub_cmd) => {
-            let args = cmd_parts.next().unwrap_or("");
+    Ok(draft_result.to_text())
+}
 
-            match sub_cmd {
-                DRAFT_HELP_CMD => {
-                    reply.add(get_help_text());
-                }
-                DRAFT_REG_CMD => {
-                    let game_id = args;
+async fn save_final_deck(ctx: &Context, game_id: &str, user: &str, args: &str) -> Res<String> {
+    let names = if args.trim().is_empty() {
+        db_access::get_decklist(game_id).await?
+    } else {
+        deck_export::parse_eternal_deck(args)?
+    };
+    let cards = get_deck_cards(ctx, &names).await?;
 
-                    register_game_in_cache(&ctx, channel_id_number, game_id).await;
+    let mut draft_result = get_owned_draft_result(ctx, game_id, user).await?;
+    draft_result.final_deck = cards.into_iter().map(|card| card.name).collect();
+    db_access::upsert_draft_result(&draft_result).await?;
 
-                    reply.add(format!(
-                        "Game [{}] is now registered to {}",
-                        game_id, &user.name
-                    ));
-                }
-                DRAFT_OWN_CMD => {
-                    let game_id = args;
+    Ok(format!(
+        "Saved a final deck of {} cards",
+        draft_result.final_deck.len()
+    ))
+}
 
-                    match own_game(ctx, &user.name, channel_id_number, game_id).await {
-                        Ok(_) => {
+async fn get_winrate_text(ctx: &Context, user_key: &str, args: &str) -> Res<String> {
+    let user_key = match args.trim() {
+        "" => user_key.to_string(),
+        mention => {
+            parse_user_id(mention).ok_or(Error::Data(format!("Unknown user [{}]", mention)))?
+        }
+    };
+
+    let games = db_access::get_draft_games_by_user(&user_key, WINRATE_GAME_LIMIT).await?;
+    let results = db_access::get_draft_results().await?;
+    let stats = draft_stats::user_stats(&user_key, &games, &results);
+
+    Ok(format!(
+        "{}: {}",
+        get_user_display_name(ctx, &user_key).await,
+        stats.to_text()
+    ))
+}
+
+async fn get_card_stats_text(ctx: &Context, args: &str) -> Res<String> {
+    let simulated = db_access::get_simulated_game_ids().await?;
+    let records = draft_stats::without_games(
+        db_access::get_taken_draft_records().await?,
+        &simulated,
+        |record| record.game_id.as_str(),
+    );
+    let results = draft_stats::without_games(
+        db_access::get_draft_results().await?,
+        &simulated,
+        |result| result.game_id.as_str(),
+    );
+    let stats = draft_stats::card_stats(&records, &results);
+
+    if !args.trim().is_empty() {
+        let card = get_cards(ctx, &[args.trim().to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(Error::Data(format!("Unknown card [{}]", args.trim())))?;
+        return Ok(stats
+            .iter()
+            .find(|stat| stat.name == card.name)
+            .map(|stat| stat.to_text())
+            .unwrap_or(format!("{} was never drafted", card.name)));
+    }
+
+    let best = draft_stats::best_cards(&stats, CARD_STATS_LIMIT);
+    if best.is_empty() {
+        return Ok(format!(
+            "No card was drafted in {} games with results yet",
+            draft_stats::CARD_STATS_MIN_GAMES
+        ));
+    }
+
+    Ok(best.iter().map(|stat| stat.to_text()).join("\n"))
+}
+
+async fn get_team_ratings(
+    ctx: &Context,
+    ratings: &HashMap<String, String>,
+) -> Res<Arc<Vec<TeamRating>>> {
+    let team_ratings_lock = {
+        let data = ctx.data.read().await;
+        data.get::<BotTeamRatings>()
+            .expect("Expected BotTeamRatings in TypeMap.")
+            .clone()
+    };
+    if let Some(team_ratings) = team_ratings_lock
+        .read()
+        .await
+        .get(&TEAM_RATING_FORMAT.to_string(), Instant::now())
+    {
+        return Ok(team_ratings);
+    }
+
+    let simulated = db_access::get_simulated_game_ids().await?;
+    let records = draft_stats::without_games(
+        db_access::get_taken_draft_records().await?,
+        &simulated,
+        |record| record.game_id.as_str(),
+    );
+    let votes = draft_stats::without_games(
+        db_access::get_all_draft_votes().await?,
+        &simulated,
+        |vote| vote.game_id.as_str(),
+    );
+    let results = draft_stats::without_games(
+        db_access::get_draft_results().await?,
+        &simulated,
+        |result| result.game_id.as_str(),
+    );
+
+    let team_ratings = Arc::new(team_rating::compute_team_ratings(
+        ratings, &records, &votes, &results,
+    ));
+    db_access::upsert_team_ratings(&team_rating::to_card_ratings(&team_ratings)).await?;
+
+    team_ratings_lock.write().await.insert(
+        TEAM_RATING_FORMAT.to_string(),
+        team_ratings.clone(),
+        Instant::now(),
+    );
+    Ok(team_ratings)
+}
+
+async fn get_team_ratings_text(ctx: &Context, args: &str) -> Res<String> {
+    let ratings = get_card_ratings(ctx).await;
+
+    if !args.trim().is_empty() {
+        let card = get_cards(ctx, &[args.trim().to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(Error::Data(format!("Unknown card [{}]", args.trim())))?;
+        let team_rating = db_access::get_team_rating(&card.name).await?;
+        return Ok(format!(
+            "{}: published {}, team {}",
+            card.name,
+            ratings.get(&card.name).map(|r| r.as_str()).unwrap_or("NA"),
+            team_rating
+                .map(|rating| rating.rating)
+                .unwrap_or("NA".to_string())
+        ));
+    }
+
+    let team_ratings = get_team_ratings(ctx, &ratings).await?;
+    let disagreements = team_rating::disagreements(&team_ratings, TEAM_RATING_LIMIT);
+    if disagreements.is_empty() {
+        return Ok(format!(
+            "Rated {} cards, all in line with the published list",
+            team_ratings.len()
+        ));
+    }
+
+    Ok(format!(
+        "Rated {} cards, biggest differences:\n{}",
+        team_ratings.len(),
+        disagreements
+            .iter()
+            .map(|rating| rating.to_text())
+            .join("\n")
+    ))
+}
+
+async fn get_simulations(
+    ctx: &Context,
+) -> Arc<Mutex<TtlCache<String, Arc<Mutex<DraftSimulator>>>>> {
+    let data = ctx.data.read().await;
+    data.get::<BotSimulations>()
+        .expect("Expected BotSimulations in TypeMap.")
+        .clone()
+}
+
+async fn start_simulation(
+    ctx: &Context,
+    channel_id: ChannelId,
+    user_key: &str,
+    set_name: &str,
+) -> Res<String> {
+    let cards = {
+        let data = ctx.data.read().await;
+        data.get::<BotCardData>()
+            .expect("Expected CardData in TypeMap.")
+            .values()
+            .cloned()
+            .collect::<Vec<Card>>()
+    };
+    let ratings = get_card_ratings(ctx).await;
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_nanos() as u64)
+        .unwrap_or_default();
+
+    let game_id = DraftGame::generate_id();
+    let simulator = DraftSimulator::new(&game_id, &cards, set_name, &ratings, seed)?;
+    let record = simulator
+        .current_record()
+        .ok_or(Error::Data("The simulated draft has no picks".to_string()))?;
+
+    let mut draft_game = own_game(ctx, user_key, channel_id.0, &game_id).await?;
+    draft_game.simulated = true;
+    db_access::upsert_draft_game(&draft_game).await?;
+    db_access::upsert_draft_record(&record).await?;
+    get_simulations(ctx).await.lock().await.insert(
+        game_id.to_string(),
+        Arc::new(Mutex::new(simulator)),
+        Instant::now(),
+    );
+
+    Ok(format!(
+        "Simulated game [{}]\n{}",
+        game_id,
+        draft_replay::pick_text(&record, &ratings)
+    ))
+}
+
+// Passes a committed pick on to the simulator and stores the next pack it deals
+async fn advance_simulation(ctx: &Context, game_id: &str, pick_idx: u8) -> Res<Option<String>> {
+    let simulations = get_simulations(ctx).await;
+    // The shared map is only locked to look the game up, never across the database write
+    let simulator = simulations
+        .lock()
+        .await
+        .get(&game_id.to_string(), Instant::now());
+    let simulator = match simulator {
+        Some(simulator) => simulator,
+        None => return Ok(None),
+    };
+
+    let record = {
+        let mut simulator = simulator.lock().await;
+        simulator.pick(pick_idx)?;
+        simulator.current_record()
+    };
+    let record = match record {
+        Some(record) => record,
+        None => {
+            simulations.lock().await.remove(&game_id.to_string());
+            return Ok(None);
+        }
+    };
+    // Every pick keeps the simulation around for another TTL
+    simulations
+        .lock()
+        .await
+        .insert(game_id.to_string(), simulator, Instant::now());
+    db_access::upsert_draft_record(&record).await?;
+
+    let ratings = get_card_ratings(ctx).await;
+    Ok(Some(draft_replay::pick_text(&record, &ratings)))
+}
+
+async fn get_last_pic(game_id: &str) -> Option<String> {
+    db_access::get_last_draft_record(game_id)
+        .await
+        .ok()?
+        .and_then(|record| record.image_url.map(String::from))
+}
+
+async fn get_vote_mode(game_id: &str) -> Res<VoteMode> {
+    let draft_game = db_access::get_draft_game(game_id).await?;
+    Ok(draft_game.map(|game| game.vote_mode).unwrap_or_default())
+}
+
+async fn get_vote_tally(game_id: &str) -> Res<(DraftRecord, VoteTally)> {
+    let draft_record = db_access::get_last_draft_record(game_id)
+        .await?
+        .ok_or(Error::Data(format!(
+            "Unable to get last draft record for game {}",
+            game_id
+        )))?;
+
+    let vote_mode = get_vote_mode(game_id).await?;
+    let votes = db_access::get_draft_votes(game_id, &draft_record.pick).await?;
+
+    Ok((draft_record, vote_aggregation::tally_votes(&votes, &vote_mode)))
+}
+
+async fn get_chosen_pick(game_id: &str) -> Res<u8> {
+    let (draft_record, tally) = get_vote_tally(game_id).await?;
+
+    tally.winner().ok_or(Error::Data(format!(
+        "Unable to get highest voted pick for pick {} in game [{}]",
+        draft_record.pick.to_string(),
+        game_id.to_string(),
+    )))
+}
+
+async fn get_votes_text(ctx: &Context, game_id: &str) -> Res<String> {
+    let (draft_record, tally) = get_vote_tally(game_id).await?;
+
+    let mut lines = vec![format!(
+        "Votes for pick {} [{}]",
+        draft_record.pick.to_string(),
+        tally.mode
+    )];
+
+    if tally.candidates.is_empty() {
+        lines.push("No votes yet".to_string());
+    }
+
+    for candidate in tally.candidates.iter() {
+        let card_name = draft_record
+            .selection_vec
+            .get(candidate.vote_idx as usize)
+            .cloned()
+            .unwrap_or_else(|| format!("Card at position {}", candidate.vote_idx + 1));
+        let score = match tally.mode {
+            VoteMode::Plurality => format!("{:>2} votes", candidate.score),
+            _ => format!("{:>2} pts  ", candidate.score),
+        };
+        let mut voters = Vec::new();
+        for voter in candidate.voters.iter() {
+            voters.push(get_user_display_name(ctx, voter).await);
+        }
+        lines.push(format!(
+            "{} {:<2} {:30} {}",
+            score,
+            candidate.vote_idx + 1,
+            card_name,
+            voters.join(", ")
+        ));
+    }
+
+    Ok(lines.join("\n"))
+}
+
+async fn ensure_game_owner(
+    ctx: &Context,
+    draft_game: &DraftGame,
+    user: &str,
+    action: &str,
+) -> Res<()> {
+    match &draft_game.user_id {
+        Some(owner) if owner != user => Err(Error::Data(format!(
+            "Only the owner [{}] can {}",
+            get_user_display_name(ctx, owner).await,
+            action
+        ))),
+        _ => Ok(()),
+    }
+}
+
+async fn set_vote_mode(
+    ctx: &Context,
+    game_id: &str,
+    user: &str,
+    mode_text: &str,
+) -> Res<VoteMode> {
+    let vote_mode = mode_text
+        .trim()
+        .parse::<VoteMode>()
+        .map_err(|_| Error::Data(format!("Unknown vote mode [{}]", mode_text.trim())))?;
+
+    let mut draft_game = db_access::get_draft_game(game_id)
+        .await?
+        .ok_or(Error::Data(format!("Game [{}] does not exist", game_id)))?;
+    ensure_game_owner(ctx, &draft_game, user, "change the vote mode").await?;
+
+    draft_game.vote_mode = vote_mode.clone();
+    db_access::upsert_draft_game(&draft_game).await?;
+
+    Ok(vote_mode)
+}
+
+async fn pick_card(game_id: &str, pick_idx: u8) -> Res<(DraftPick, String)> {
+    let draft_record = db_access::get_last_draft_record(game_id).await?;
+    match draft_record {
+        Some(draft_record) => {
+            if draft_record.selection_vec.len() > 0
+                && pick_idx as usize >= draft_record.selection_vec.len()
+            {
+                return Err(Error::Data(format!(
+                    "Pick index {} is out of bounds for game {}",
+                    pick_idx, game_id
+                )));
+            }
+
+            let mut draft_record_with_pick = draft_record.clone();
+            draft_record_with_pick.pick_card(pick_idx);
+
+            debug!("picking card at index {}", pick_idx);
+
+            db_access::upsert_draft_record(&draft_record_with_pick).await?;
+
+            let selected_card_text = if draft_record_with_pick.selection_vec.len() > 0 {
+                draft_record_with_pick.selection_vec[pick_idx as usize].to_string()
+            } else {
+                format!("Card at position {}", pick_idx + 1)
+            };
+
+            return Ok((draft_record_with_pick.pick, selected_card_text));
+        }
+        None => {}
+    }
+
+    Err(Error::Data(format!(
+        "Unable to find draft record for game {}",
+        game_id
+    )))
+}
+
+async fn find_vote_idx(draft_record: &DraftRecord, vote_text: &str) -> Res<u8> {
+    if let Ok(pick_num) = vote_text.parse::<u8>() {
+        let pick_num = pick_num
+            .checked_sub(1)
+            .ok_or(Error::Data("Card index starts at 1".to_string()))?;
+        match draft_record.selection_vec.get(pick_num as usize) {
+            Some(_) => Ok(pick_num),
+            _ => Err(Error::Data(format!(
+                "Unable to find card at index {}",
+                pick_num + 1
+            ))),
+        }
+    } else {
+        find_card_in_list(&draft_record.selection_vec, vote_text)
+            .await
+            .ok_or(Error::Data(format!(
+                "Unable to find card in list with vote text [{}]",
+                vote_text
+            )))
+    }
+}
+
+fn get_selection_name(draft_record: &DraftRecord, vote_idx: u8) -> String {
+    draft_record
+        .selection_vec
+        .get(vote_idx as usize)
+        .cloned()
+        .unwrap_or_else(|| format!("Card at position {}", vote_idx + 1))
+}
+
+async fn vote_card(
+    ctx: &Context,
+    game_id: &str,
+    user: &str,
+    vote_text: &str,
+) -> Res<(DraftPick, Vec<String>, Option<Vec<String>>)> {
+    let draft_record = get_last_draft_record(game_id)
+        .await?
+        .ok_or(Error::Data("Could not find draft record".to_string()))?;
+
+    let mut ranking: Vec<u8> = Vec::new();
+    for text in vote_text.split(VOTE_RANKING_SEPARATOR) {
+        let vote_idx = find_vote_idx(&draft_record, text.trim()).await?;
+        if !ranking.contains(&vote_idx) {
+            ranking.push(vote_idx);
+        }
+    }
+    if ranking.is_empty() {
+        return Err(Error::Data("No card to vote for".to_string()));
+    }
+
+    let previous_vote = db_access::get_draft_vote(game_id, user, &draft_record.pick).await?;
+
+    let draft_vote =
+        DraftVote::new(game_id, user, &draft_record.pick, ranking[0]).with_ranking(&ranking);
+    db_access::upsert_draft_vote(&draft_vote).await?;
+
+    let to_names = |vote: &DraftVote| {
+        vote.preferences()
+            .iter()
+            .map(|idx| get_selection_name(&draft_record, *idx))
+            .collect::<Vec<String>>()
+    };
+
+    Ok((
+        draft_record.pick.clone(),
+        to_names(&draft_vote),
+        previous_vote.as_ref().map(to_names),
+    ))
+}
+
+async fn unvote_card(game_id: &str, user: &str) -> Res<(DraftPick, Vec<String>)> {
+    let draft_record = get_last_draft_record(game_id)
+        .await?
+        .ok_or(Error::Data("Could not find draft record".to_string()))?;
+
+    let draft_vote = db_access::get_draft_vote(game_id, user, &draft_record.pick)
+        .await?
+        .ok_or(Error::Data(format!(
+            "No vote found for pick [{}]",
+            draft_record.pick.to_string()
+        )))?;
+
+    db_access::delete_draft_vote(&draft_vote).await?;
+
+    let removed = draft_vote
+        .preferences()
+        .iter()
+        .map(|idx| get_selection_name(&draft_record, *idx))
+        .collect();
+
+    Ok((draft_record.pick, removed))
+}
+
+async fn get_cached_data(ctx: &Context, cache_key: &str) -> Option<String> {
+    let cache_lock = {
+        let data = ctx.data.read().await;
+        data.get::<BotCache>()
+            .expect("Expected BotCache in TypeMap.")
+            .clone()
+    };
+
+    let cache = cache_lock.read().await;
+    cache.get(cache_key).cloned()
+}
+
+// A display name proves nothing and is not unique, so the user asks for their own
+// name to be moved and the bot owner confirms it
+async fn migrate_user(ctx: &Context, user: &User, args: &str) -> Res<String> {
+    let requests_lock = {
+        let data = ctx.data.read().await;
+        data.get::<BotMigrationRequests>()
+            .expect("Expected BotMigrationRequests in TypeMap.")
+            .clone()
+    };
+
+    let mut arg_parts = args.split_whitespace();
+    match arg_parts.next() {
+        None => {
+            // Records are keyed by user id now, a numeric name could be another account
+            if user.name.parse::<u64>().is_ok() {
+                return Err(Error::Data(format!(
+                    "[{}] looks like a user id and cannot be migrated",
+                    user.name
+                )));
+            }
+            requests_lock.write().await.insert(
+                get_user_key(user),
+                user.name.to_string(),
+                Instant::now(),
+            );
+            Ok(format!(
+                "Moving the records of [{}] to your account is waiting for the bot owner: {} {} {} <@{}>",
+                user.name, DRAFT_CMD, DRAFT_MIGRATE_CMD, DRAFT_MIGRATE_CONFIRM_CMD, user.id
+            ))
+        }
+        Some(DRAFT_MIGRATE_CONFIRM_CMD) => {
+            if !is_app_owner(ctx, user).await? {
+                return Err(Error::Data(
+                    "Only the bot owner can confirm a migration".to_string(),
+                ));
+            }
+            let user_id = arg_parts.next().and_then(parse_user_id).ok_or(Error::Data(
+                "Give the user to migrate, e.g. @name".to_string(),
+            ))?;
+            let old_user_id = requests_lock
+                .read()
+                .await
+                .get(&user_id, Instant::now())
+                .ok_or(Error::Data(format!(
+                    "<@{}> has not asked for a migration",
+                    user_id
+                )))?;
+
+            let migration = db_access::migrate_user_id(&old_user_id, &user_id).await?;
+            requests_lock.write().await.remove(&user_id);
+
+            let mut text = format!(
+                "Moved {} games and {} votes from [{}] to <@{}>",
+                migration.games, migration.votes, old_user_id, user_id
+            );
+            if migration.skipped_votes > 0 {
+                text.push_str(&format!(
+                    "\n{} votes were already cast by <@{}> and are left under [{}]",
+                    migration.skipped_votes, user_id, old_user_id
+                ));
+            }
+            Ok(text)
+        }
+        Some(other) => Err(Error::Data(format!("Unknown migrate option [{}]", other))),
+    }
+}
+
+async fn process_draft_command(ctx: &Context, channel_id: ChannelId, user: User, args: &str) {
+    let mut reply = BotReply::new();
+    let mut cmd_parts = args.splitn(2, char::is_whitespace);
+    let cmd = cmd_parts.next();
+    let channel_id_number = channel_id.as_u64().to_owned();
+    let user_key = get_user_key(&user);
+
+    remember_user_name(ctx, &user).await;
+
+    match cmd {
+        Some(sub_cmd) => {
+            let args = cmd_parts.next().unwrap_or("");
+
+            match sub_cmd {
+                DRAFT_HELP_CMD => {
+                    reply.add(get_help_text());
+                }
+                DRAFT_REG_CMD => {
+                    let game_id = args.trim();
+                    record_game_id(game_id);
+
+                    match register_game(&ctx, channel_id_number, game_id).await {
+                        Ok(_) => {
+                            reply.add(format!(
+                                "Game [{}] is now registered to {}",
+                                game_id, &user.name
+                            ));
+                        }
+                        Err(err) => {
+                            reply.add_error("Unable to register game", &err);
+                        }
+                    }
+                }
+                DRAFT_NEW_CMD | DRAFT_OWN_CMD => {
+                    let game_id = if sub_cmd == DRAFT_NEW_CMD {
+                        DraftGame::generate_id()
+                    } else {
+                        args.trim().to_string()
+                    };
+                    record_game_id(&game_id);
+
+                    match own_game(ctx, &user_key, channel_id_number, &game_id).await {
+                        Ok(mut draft_game) => {
                             reply.add(format!(
                                 "Game [{}] is now owned by [{}]",
                                 game_id, &user.name
                             ));
+
+                            match get_or_create_game_thread(ctx, channel_id, &mut draft_game).await
+                            {
+                                Ok(thread_id) => {
+                                    reply.add(format!("Draft thread: <#{}>", thread_id));
+                                }
+                                Err(err) => {
+                                    reply.add_error("Unable to create draft thread", &err);
+                                }
+                            }
                         }
                         Err(err) => {
-                            reply.add(format!("Unable to own game: [{}]", err));
+                            reply.add_error("Unable to own game", &err);
                         }
                     }
                 }
+                DRAFT_HISTORY_CMD => match get_history_text(ctx, &user_key, args).await {
+                    Ok(history) => {
+                        reply.add_boxed(history);
+                    }
+                    Err(err) => {
+                        reply.add_error("Unable to get history", &err);
+                    }
+                },
+                DRAFT_PICK_CMD => match get_pick_text(ctx, channel_id, &user_key, args).await {
+                    Ok(pick_text) => {
+                        reply.add_boxed(pick_text);
+                    }
+                    Err(err) => {
+                        reply.add_error("Unable to get pick", &err);
+                    }
+                },
+                DRAFT_REPLAY_CMD => match replay_draft(ctx, channel_id, &user_key, args).await {
+                    Ok(replay_text) => {
+                        reply.add_boxed(replay_text);
+                    }
+                    Err(err) => {
+                        reply.add_error("Unable to replay draft", &err);
+                    }
+                },
+                DRAFT_WINRATE_CMD => match get_winrate_text(ctx, &user_key, args).await {
+                    Ok(winrate) => {
+                        reply.add(winrate);
+                    }
+                    Err(err) => {
+                        reply.add_error("Unable to get win rate", &err);
+                    }
+                },
+                DRAFT_CARDSTATS_CMD => match get_card_stats_text(ctx, args).await {
+                    Ok(card_stats) => {
+                        reply.add_boxed(card_stats);
+                    }
+                    Err(err) => {
+                        reply.add_error("Unable to get card stats", &err);
+                    }
+                },
+                DRAFT_TEAMRATINGS_CMD => match get_team_ratings_text(ctx, args).await {
+                    Ok(team_ratings) => {
+                        reply.add_boxed(team_ratings);
+                    }
+                    Err(err) => {
+                        reply.add_error("Unable to get team ratings", &err);
+                    }
+                },
+                DRAFT_SIM_CMD => match start_simulation(ctx, channel_id, &user_key, args).await {
+                    Ok(simulation) => {
+                        reply.add_boxed(simulation);
+                    }
+                    Err(err) => {
+                        reply.add_error("Unable to start simulation", &err);
+                    }
+                },
+                DRAFT_MIGRATE_CMD => match migrate_user(ctx, &user, args).await {
+                    Ok(migration) => {
+                        reply.add(migration);
+                    }
+                    Err(err) => {
+                        reply.add_error("Unable to migrate", &err);
+                    }
+                },
                 other => {
-                    let game_id =
-                        get_cached_data(&ctx, channel_id_number.to_string().as_ref()).await;
+                    let game_id = get_registered_game(ctx, channel_id).await;
                     let game_id = match game_id {
                         Some(game_id) => {
+                            record_game_id(&game_id);
                             reply.add(format!("Game [{}]", game_id));
 
                             game_id
@@ -453,23 +1675,60 @@ async fn process_draft_command(ctx: &Context, channel_id: ChannelId, user: User,
                         DRAFT_VOTE_CMD => {
                             let vote = args;
 
-                            match vote_card(&ctx, &game_id, &user.name, vote).await {
-                                Ok((draft_pick, picked_card_str)) => {
+                            match vote_card(&ctx, &game_id, &user_key, vote).await {
+                                Ok((draft_pick, voted_cards, previous_cards)) => {
                                     reply.add(format!(
                                         "[{}] voted card for pick [{}]:\n{}",
                                         user.name.to_string(),
                                         draft_pick.to_string(),
-                                        picked_card_str,
+                                        voted_cards.join(" > "),
                                     ));
+                                    if let Some(previous_cards) = previous_cards {
+                                        if previous_cards != voted_cards {
+                                            reply.add(format!(
+                                                "Changed from: {}",
+                                                previous_cards.join(" > ")
+                                            ));
+                                        }
+                                    }
                                 }
                                 Err(err) => {
-                                    reply.add(format!("Err: {}", err));
+                                    reply.add_error("Unable to vote", &err);
 
                                     send_message(&ctx, channel_id, &reply.to_string()).await;
                                     return;
                                 }
                             }
                         }
+                        DRAFT_UNVOTE_CMD => match unvote_card(&game_id, &user_key).await {
+                            Ok((draft_pick, removed_cards)) => {
+                                reply.add(format!(
+                                    "[{}] removed vote for pick [{}]:\n{}",
+                                    user.name.to_string(),
+                                    draft_pick.to_string(),
+                                    removed_cards.join(" > ")
+                                ));
+                            }
+                            Err(err) => {
+                                reply.add_error("Unable to unvote", &err);
+                            }
+                        },
+                        DRAFT_VOTES_CMD => match get_votes_text(ctx, &game_id).await {
+                            Ok(votes_text) => {
+                                reply.add_boxed(votes_text);
+                            }
+                            Err(err) => {
+                                reply.add_error("Unable to get votes", &err);
+                            }
+                        },
+                        DRAFT_MODE_CMD => match set_vote_mode(ctx, &game_id, &user_key, args).await {
+                            Ok(vote_mode) => {
+                                reply.add(format!("Vote mode is now [{}]", vote_mode));
+                            }
+                            Err(err) => {
+                                reply.add_error("Unable to set vote mode", &err);
+                            }
+                        },
                         DRAFT_COMMIT_CMD => match get_chosen_pick(&game_id).await {
                             Ok(chosen_pick) => match pick_card(&game_id, chosen_pick).await {
                                 Ok((draft_pick, chosen_pick_str)) => {
@@ -479,16 +1738,34 @@ async fn process_draft_command(ctx: &Context, channel_id: ChannelId, user: User,
                                         draft_pick.to_string(),
                                         chosen_pick_str
                                     ));
+
+                                    match advance_simulation(ctx, &game_id, chosen_pick).await {
+                                        Ok(Some(next_pick)) => {
+                                            reply.add_boxed(next_pick);
+                                        }
+                                        Ok(None) => {}
+                                        Err(err) => {
+                                            reply.add_error("Unable to advance simulation", &err);
+                                        }
+                                    }
+
+                                    if draft_pick.is_last() {
+                                        send_message(&ctx, channel_id, &reply.to_string()).await;
+                                        if let Err(err) = finish_game_thread(ctx, &game_id).await {
+                                            warn!("Unable to finish draft thread: {}", err);
+                                        }
+                                        return;
+                                    }
                                 }
                                 Err(err) => {
-                                    reply.add(format!("Unable to pick: {}", err));
+                                    reply.add_error("Unable to pick", &err);
 
                                     send_message(&ctx, channel_id, &reply.to_string()).await;
                                     return;
                                 }
                             },
                             Err(err) => {
-                                reply.add(format!("Unable to get pick: {}", err));
+                                reply.add_error("Unable to get pick", &err);
 
                                 send_message(&ctx, channel_id, &reply.to_string()).await;
                                 return;
@@ -497,6 +1774,58 @@ async fn process_draft_command(ctx: &Context, channel_id: ChannelId, user: User,
                         DRAFT_DECK_CMD => {
                             reply.add_boxed(get_decklist(ctx, &game_id).await);
                         }
+                        DRAFT_EXPORT_CMD => match get_deck_export(ctx, &game_id, args).await {
+                            Ok(exported) => {
+                                reply.add_boxed(exported);
+                            }
+                            Err(err) => {
+                                reply.add_error("Unable to export deck", &err);
+                            }
+                        },
+                        DRAFT_RESULT_CMD => {
+                            match record_result(ctx, &game_id, &user_key, args).await {
+                                Ok(result) => {
+                                    reply.add_boxed(result);
+                                }
+                                Err(err) => {
+                                    reply.add_error("Unable to record result", &err);
+                                }
+                            }
+                        }
+                        DRAFT_FINAL_CMD => {
+                            match save_final_deck(ctx, &game_id, &user_key, args).await {
+                                Ok(saved) => {
+                                    reply.add(saved);
+                                }
+                                Err(err) => {
+                                    reply.add_error("Unable to save final deck", &err);
+                                }
+                            }
+                        }
+                        DRAFT_STATS_CMD => match get_pool_analysis(ctx, &game_id, args).await {
+                            Ok(analysis) => {
+                                reply.add_boxed(analysis);
+                            }
+                            Err(err) => {
+                                reply.add_error("Unable to analyze pool", &err);
+                            }
+                        },
+                        DRAFT_SIGNALS_CMD => match get_draft_signals(ctx, &game_id).await {
+                            Ok(signals) => {
+                                reply.add_boxed(signals.to_text());
+                            }
+                            Err(err) => {
+                                reply.add_error("Unable to read signals", &err);
+                            }
+                        },
+                        DRAFT_BUILD_CMD => match get_deck_suggestion(ctx, &game_id).await {
+                            Ok(suggestion) => {
+                                reply.add_boxed(suggestion);
+                            }
+                            Err(err) => {
+                                reply.add_error("Unable to build deck", &err);
+                            }
+                        },
                         DRAFT_PIC_CMD => {
                             let msg = match get_last_pic(&game_id).await {
                                 Some(image_url) => image_url,
@@ -509,9 +1838,14 @@ async fn process_draft_command(ctx: &Context, channel_id: ChannelId, user: User,
                             let draft_data = get_draft_data(&game_id).await;
                             match draft_data {
                                 Some(draft_data_unwrap) => {
-                                    reply
-                                        .add(format!("Card {} of 48", draft_data_unwrap.0.pick_id));
+                                    reply.add(format!(
+                                        "Card {} of {}",
+                                        draft_data_unwrap.0.pick_id, DRAFT_PICK_COUNT
+                                    ));
                                     reply.add_boxed(draft_data_unwrap.1);
+                                    if let Ok(signals) = get_draft_signals(ctx, &game_id).await {
+                                        reply.add(signals.summary());
+                                    }
                                 }
                                 None => {
                                     reply.add(format!("No draft data available"));
@@ -530,19 +1864,154 @@ async fn process_draft_command(ctx: &Context, channel_id: ChannelId, user: User,
     send_message(&ctx, channel_id, &reply.to_string()).await;
 }
 
+fn get_card_detail_text(card: &Card, rating: Option<&String>) -> String {
+    let stats =
+        if card.card_type.is(&CardTypeEnum::Unit) || card.card_type.is(&CardTypeEnum::Weapon) {
+            format!(" {}/{}", card.attack, card.health)
+        } else {
+            String::new()
+        };
+
+    let header = format!("{} {}{}", card.cost, card.influence.to_text(), stats);
+    let details = vec![
+        format!("{:<10}{}", "Cost", header),
+        format!("{:<10}{}", "Faction", card.influence.group_name()),
+        format!("{:<10}{}", "Type", card.card_type.to_text()),
+        format!("{:<10}{:?}", "Rarity", card.rarity),
+        format!("{:<10}{} (Set {})", "Set", card.set_name, card.set_number),
+        format!(
+            "{:<10}{}",
+            "Rating",
+            rating.map(|r| r.as_str()).unwrap_or("NA")
+        ),
+        String::new(),
+        card.plain_text(),
+    ];
+
+    format!("```\n{}\n{}\n```", card.name, details.join("\n"))
+}
+
+async fn get_card_choices(ctx: &Context) -> Arc<RwLock<TtlCache<(u64, String), Vec<String>>>> {
+    let data = ctx.data.read().await;
+    data.get::<BotCardChoices>()
+        .expect("Expected BotCardChoices in TypeMap.")
+        .clone()
+}
+
+async fn search_cards(ctx: &Context, args: &str) -> Res<Vec<Card>> {
+    let card_data = {
+        let data = ctx.data.read().await;
+        data.get::<BotCardData>()
+            .expect("Expected CardData in TypeMap.")
+            .clone()
+    };
+
+    if CardQuery::is_filter_query(args) {
+        let card_ratings = get_card_ratings(ctx).await;
+        let query = CardQuery::parse(args)?;
+        return Ok(query
+            .run(card_data.values(), &card_ratings)
+            .into_iter()
+            .cloned()
+            .collect());
+    }
+
+    let card_index = {
+        let data = ctx.data.read().await;
+        data.get::<BotCardIndex>()
+            .expect("Expected CardIndex in TypeMap.")
+            .clone()
+    };
+
+    let search_result = card_index.search(args);
+    if let Some(exact_match) = search_result
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(args.trim()))
+    {
+        return Ok(card_data.get(*exact_match).cloned().into_iter().collect());
+    }
+
+    Ok(search_result
+        .iter()
+        .filter_map(|name| card_data.get(*name))
+        .cloned()
+        .collect())
+}
+
+async fn get_card_ratings(ctx: &Context) -> Arc<HashMap<String, String>> {
+    let data = ctx.data.read().await;
+    data.get::<BotCardRating>()
+        .expect("Expected CardRating in TypeMap.")
+        .clone()
+}
+
 async fn process_card_command(ctx: &Context, channel_id: ChannelId, user: User, args: &str) {
     let mut reply = BotReply::new();
+    let choices_key = (channel_id.0, get_user_key(&user));
+
+    debug!("Searching for card with string: {}", args);
 
-    let card_name = args;
+    let found_cards = match args.trim().parse::<usize>() {
+        Ok(choice) => {
+            let choices = get_card_choices(ctx)
+                .await
+                .read()
+                .await
+                .get(&choices_key, Instant::now())
+                .unwrap_or_default();
+            // Choices are listed from 1
+            match choice.checked_sub(1).and_then(|idx| choices.get(idx)) {
+                Some(card_name) => get_cards(ctx, &[card_name.to_string()]).await,
+                None => Err(Error::Data(format!(
+                    "No card {} in the last search",
+                    choice
+                ))),
+            }
+        }
+        Err(_) => search_cards(ctx, args).await,
+    };
 
-    log(format!("Searching for card with string: {}", card_name));
-    let find_card_result = get_card(&ctx, &card_name).await;
-    match find_card_result {
-        Ok(found_card) => {
-            reply.add(format!("{}", found_card.image_url.to_string()));
+    match found_cards {
+        Ok(cards) if cards.len() == 1 => {
+            let card_ratings = get_card_ratings(ctx).await;
+            let card = &cards[0];
+            reply.add(get_card_detail_text(card, card_ratings.get(&card.name)));
+            reply.add(card.image_url.to_string());
+        }
+        Ok(cards) if cards.is_empty() => {
+            reply.add("Unable to find card: No card found".to_string());
+        }
+        Ok(cards) => {
+            let choices = cards
+                .iter()
+                .take(CARD_CHOICE_LIMIT)
+                .map(|card| card.name.to_string())
+                .collect::<Vec<String>>();
+            get_card_choices(ctx)
+                .await
+                .write()
+                .await
+                .insert(choices_key, choices, Instant::now());
+
+            reply.add(format!(
+                "Found {} cards, use `{} <n>` to pick one:",
+                cards.len(),
+                CARD_COMMAND
+            ));
+            reply.add_boxed(
+                cards
+                    .iter()
+                    .take(CARD_CHOICE_LIMIT)
+                    .enumerate()
+                    .map(|(idx, card)| {
+                        format!("{:<2} {}", idx + 1, card.to_text(CARD_TO_TEXT_OPT_DEFAULT))
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            );
         }
         Err(e) => {
-            reply.add(format!("Unable to find card: {}", e));
+            reply.add_error("Unable to find card", &e);
         }
     }
 
@@ -553,57 +2022,579 @@ fn get_help_text() -> String {
     format!("```{}```", DRAFT_CMD_HELP)
 }
 
+async fn get_guild_config(ctx: &Context, guild_id: GuildId) -> GuildConfig {
+    let guild_configs_lock = {
+        let data = ctx.data.read().await;
+        data.get::<BotGuildConfigs>()
+            .expect("Expected BotGuildConfigs in TypeMap.")
+            .clone()
+    };
+
+    if let Some(guild_config) = guild_configs_lock.read().await.get(guild_id.as_u64()) {
+        return guild_config.clone();
+    }
+
+    match db_access::get_guild_config(&guild_id.to_string()).await {
+        Ok(guild_config) => {
+            let guild_config =
+                guild_config.unwrap_or_else(|| GuildConfig::new(&guild_id.to_string()));
+            guild_configs_lock
+                .write()
+                .await
+                .insert(guild_id.0, guild_config.clone());
+            guild_config
+        }
+        Err(err) => {
+            warn!("Unable to get guild config: {}", err);
+            GuildConfig::new(&guild_id.to_string())
+        }
+    }
+}
+
+async fn save_guild_config(ctx: &Context, guild_id: GuildId, guild_config: GuildConfig) -> Res<()> {
+    db_access::upsert_guild_config(&guild_config).await?;
+
+    let (guild_configs_lock, channel_cache_lock) = {
+        let data = ctx.data.read().await;
+        (
+            data.get::<BotGuildConfigs>()
+                .expect("Expected BotGuildConfigs in TypeMap.")
+                .clone(),
+            data.get::<BotChannelCache>()
+                .expect("Expected BotChannelCache in TypeMap.")
+                .clone(),
+        )
+    };
+
+    guild_configs_lock
+        .write()
+        .await
+        .insert(guild_id.0, guild_config);
+    channel_cache_lock.write().await.clear();
+
+    Ok(())
+}
+
+async fn is_channel_allowed(ctx: &Context, channel_id: ChannelId) -> bool {
+    let channel_list_str: String = get_cached_data(ctx, CHANNEL_LIST_KEY)
+        .await
+        .unwrap_or_default();
+    if channel_list_str
+        .split(',')
+        .any(|s| s.trim() == &channel_id.to_string())
+    {
+        return true;
+    }
+
+    let channel_cache_lock = {
+        let data = ctx.data.read().await;
+        data.get::<BotChannelCache>()
+            .expect("Expected BotChannelCache in TypeMap.")
+            .clone()
+    };
+
+    if let Some(allowed) = channel_cache_lock
+        .read()
+        .await
+        .get(channel_id.as_u64(), Instant::now())
+    {
+        return allowed;
+    }
+
+    let allowed = match ctx.http.get_channel(channel_id.0).await {
+        Ok(Channel::Guild(channel)) => {
+            let guild_config = get_guild_config(ctx, channel.guild_id).await;
+            guild_config.has_channel(&channel.id.to_string())
+                || channel.parent_id.map_or(false, |parent_id| {
+                    guild_config.has_channel(&parent_id.to_string())
+                })
+                || guild_config.matches_prefix(&channel.name)
+        }
+        Ok(_) => false,
+        Err(e) => {
+            warn!("Error getting channel info: {:?}", e);
+            return false;
+        }
+    };
+
+    channel_cache_lock
+        .write()
+        .await
+        .insert(channel_id.0, allowed, Instant::now());
+
+    allowed
+}
+
+// Server wide permissions from the roles the message carries, so anyone can be
+// turned away with a single request
+async fn is_guild_admin(ctx: &Context, guild_id: GuildId, msg: &Message) -> Res<bool> {
+    let member = match msg.member.as_ref() {
+        Some(member) => member,
+        None => return Ok(false),
+    };
+    let guild = guild_id
+        .to_partial_guild(&ctx.http)
+        .await
+        .err_to(Error::Discord)?;
+    if guild.owner_id == msg.author.id {
+        return Ok(true);
+    }
+
+    // The @everyone role shares the id of the server
+    let permissions = guild
+        .roles
+        .iter()
+        .filter(|(role_id, _)| role_id.0 == guild_id.0 || member.roles.contains(role_id))
+        .fold(Permissions::empty(), |permissions, (_, role)| {
+            permissions | role.permissions
+        });
+
+    Ok(permissions.contains(Permissions::ADMINISTRATOR)
+        || permissions.contains(Permissions::MANAGE_CHANNELS))
+}
+
+// Owner of the bot application, or a member of its team, for commands that reach
+// every server the bot is in
+async fn is_app_owner(ctx: &Context, user: &User) -> Res<bool> {
+    let info = ctx
+        .http
+        .get_current_application_info()
+        .await
+        .err_to(Error::Discord)?;
+    Ok(info.owner.id == user.id
+        || info.team.map_or(false, |team| {
+            team.members.iter().any(|member| member.user.id == user.id)
+        }))
+}
+
+fn build_card_index(card_data: &HashMap<String, Card>) -> SearchIndex<String> {
+    let mut card_index = SearchIndex::default();
+    for (key, value) in card_data.iter() {
+        card_index.insert(key, value);
+    }
+    card_index
+}
+
+// Commands holding the old data keep their copy, new commands see the new cards
+async fn replace_card_data(ctx: &Context, card_data: HashMap<String, Card>) {
+    let card_index = build_card_index(&card_data);
+    let mut data = ctx.data.write().await;
+    data.insert::<BotCardData>(Arc::new(card_data));
+    data.insert::<BotCardIndex>(Arc::new(card_index));
+}
+
+async fn update_card_data(ctx: &Context, sub_cmd: &str, args: &str) -> Res<String> {
+    match sub_cmd {
+        CARD_DATA_UPDATE_CMD if !args.is_empty() => {
+            let export_path = crate::card_loader::import_path(
+                &app_config::get().resources.card_import_dir,
+                args,
+            )?;
+            let ratings = get_card_ratings(ctx).await;
+            let update = tokio::task::spawn_blocking(move || {
+                crate::card_loader::update_card_data(&export_path, &ratings)
+            })
+            .await
+            .err_to(Error::Data)??;
+            let report = update.to_text(CARD_DATA_REPORT_LIMIT);
+            replace_card_data(ctx, update.cards).await;
+            Ok(report)
+        }
+        CARD_DATA_UPDATE_CMD => Err(Error::Data(
+            "Give the name of the export in the import directory".to_string(),
+        )),
+        CARD_DATA_RELOAD_CMD => {
+            let card_data =
+                tokio::task::spawn_blocking(crate::card_loader::load_card_hashmap_by_name)
+                    .await
+                    .err_to(Error::Data)??;
+            let ratings = get_card_ratings(ctx).await;
+            let stale = crate::card_loader::stale_ratings(&card_data, &ratings);
+            let report = format!(
+                "Reloaded {} cards, {} ratings without a card",
+                card_data.len(),
+                stale.len()
+            );
+            replace_card_data(ctx, card_data).await;
+            Ok(report)
+        }
+        _ => Err(Error::Data(format!(
+            "Unknown card data command [{}]",
+            sub_cmd
+        ))),
+    }
+}
+
+// The card data is shared by every server, so only the bot owner may replace it
+async fn process_card_data_command(ctx: &Context, msg: &Message, args: &str) {
+    match is_app_owner(ctx, &msg.author).await {
+        Ok(true) => {}
+        Ok(false) => {
+            send_message(
+                ctx,
+                msg.channel_id,
+                "Only the bot owner can update the card data",
+            )
+            .await;
+            return;
+        }
+        Err(err) => {
+            warn!("Unable to check permissions: {}", err);
+            send_message(ctx, msg.channel_id, "Unable to check your permissions").await;
+            return;
+        }
+    }
+
+    let mut cmd_parts = args.splitn(2, char::is_whitespace);
+    let sub_cmd = cmd_parts.next().unwrap_or("");
+    let args = cmd_parts.next().unwrap_or("").trim();
+
+    let mut reply = BotReply::new();
+    match update_card_data(ctx, sub_cmd, args).await {
+        Ok(report) => {
+            reply.add_boxed(report);
+        }
+        Err(err) => {
+            reply.add_error("Unable to update card data", &err);
+        }
+    }
+    send_message(ctx, msg.channel_id, &reply.to_string()).await;
+}
+
+fn parse_channel_id(text: &str) -> Option<ChannelId> {
+    text.trim()
+        .trim_start_matches("<#")
+        .trim_end_matches('>')
+        .parse::<u64>()
+        .ok()
+        .map(ChannelId)
+}
+
+async fn process_channel_command(ctx: &Context, msg: &Message, args: &str) {
+    let mut reply = BotReply::new();
+
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            send_message(
+                ctx,
+                msg.channel_id,
+                "Channels can only be managed in a server",
+            )
+            .await;
+            return;
+        }
+    };
+
+    match is_guild_admin(ctx, guild_id, msg).await {
+        Ok(true) => {}
+        Ok(false) => {
+            send_message(
+                ctx,
+                msg.channel_id,
+                "Only members who can manage channels can change the allow-list",
+            )
+            .await;
+            return;
+        }
+        Err(err) => {
+            warn!("Unable to check permissions: {}", err);
+            send_message(ctx, msg.channel_id, "Unable to check your permissions").await;
+            return;
+        }
+    }
+
+    let mut cmd_parts = args.splitn(2, char::is_whitespace);
+    let sub_cmd = cmd_parts.next().unwrap_or("");
+    let args = cmd_parts.next().unwrap_or("").trim();
+
+    let mut guild_config = get_guild_config(ctx, guild_id).await;
+    let target_channel = if args.is_empty() {
+        Some(msg.channel_id)
+    } else {
+        parse_channel_id(args)
+    };
+
+    let changed = match (sub_cmd, target_channel) {
+        (CHANNEL_LIST_CMD, _) => {
+            let channels = guild_config
+                .channel_ids
+                .iter()
+                .map(|id| format!("<#{}>", id))
+                .collect::<Vec<String>>();
+            reply.add(format!(
+                "Allowed channels: {}",
+                if channels.is_empty() {
+                    "none".to_string()
+                } else {
+                    channels.join(", ")
+                }
+            ));
+            if guild_config.thread_prefix.is_empty() {
+                reply.add("Channels starting with: none".to_string());
+            } else {
+                reply.add(format!(
+                    "Channels starting with: [{}]",
+                    guild_config.thread_prefix
+                ));
+            }
+            false
+        }
+        (CHANNEL_ADD_CMD, Some(channel_id)) => {
+            if guild_config.add_channel(&channel_id.to_string()) {
+                reply.add(format!("Added <#{}>", channel_id));
+                true
+            } else {
+                reply.add(format!("<#{}> is already allowed", channel_id));
+                false
+            }
+        }
+        (CHANNEL_REMOVE_CMD, Some(channel_id)) => {
+            if guild_config.remove_channel(&channel_id.to_string()) {
+                reply.add(format!("Removed <#{}>", channel_id));
+                true
+            } else {
+                reply.add(format!("<#{}> is not in the allow-list", channel_id));
+                false
+            }
+        }
+        (CHANNEL_PREFIX_CMD, _) if args.is_empty() => {
+            reply.add(format!(
+                "Give a prefix, or [{}] to stop allowing channels by name",
+                CHANNEL_PREFIX_CLEAR
+            ));
+            false
+        }
+        (CHANNEL_PREFIX_CMD, _) if args == CHANNEL_PREFIX_CLEAR => {
+            guild_config.thread_prefix = String::new();
+            reply.add("Channels are no longer allowed by name".to_string());
+            true
+        }
+        (CHANNEL_PREFIX_CMD, _) => {
+            guild_config.thread_prefix = args.to_string();
+            reply.add(format!("Channels starting with [{}] are now allowed", args));
+            true
+        }
+        (CHANNEL_ADD_CMD, None) | (CHANNEL_REMOVE_CMD, None) => {
+            reply.add(format!("Unable to read channel [{}]", args));
+            false
+        }
+        _ => {
+            reply.add(get_help_text());
+            false
+        }
+    };
+
+    if changed {
+        if let Err(err) = save_guild_config(ctx, guild_id, guild_config).await {
+            reply.add_error("Unable to save channel settings", &err);
+        }
+    }
+
+    send_message(ctx, msg.channel_id, &reply.to_string()).await;
+}
+
+async fn check_rate_limit(ctx: &Context, msg: &Message) -> bool {
+    let rate_limit_lock = {
+        let data = ctx.data.read().await;
+        data.get::<BotRateLimit>()
+            .expect("Expected BotRateLimit in TypeMap.")
+            .clone()
+    };
+
+    let rate_limit = {
+        let mut rate_limits = rate_limit_lock.lock().await;
+        let now = Instant::now();
+        rate_limits.user.prune(now);
+        rate_limits.channel.prune(now);
+
+        // A hit only counts against the user once the channel accepts it as well
+        match rate_limits.user.peek(msg.author.id.as_u64(), now) {
+            RateLimit::Allowed => match rate_limits.channel.check(msg.channel_id.as_u64(), now) {
+                RateLimit::Allowed => {
+                    rate_limits.user.record(msg.author.id.as_u64(), now);
+                    RateLimit::Allowed
+                }
+                limited => limited,
+            },
+            limited => limited,
+        }
+    };
+
+    match rate_limit {
+        RateLimit::Allowed => true,
+        RateLimit::Limited {
+            retry_after,
+            notify,
+        } => {
+            warn!(
+                "Rate limited {} in channel {}",
+                msg.author.name, msg.channel_id
+            );
+            if notify {
+                send_message(
+                    ctx,
+                    msg.channel_id,
+                    &format!(
+                        "Slow down [{}], try again in {} seconds.",
+                        msg.author.name,
+                        retry_after.as_secs().max(1)
+                    ),
+                )
+                .await;
+            }
+            false
+        }
+    }
+}
+
 struct BotHandler;
 
 #[async_trait]
 impl EventHandler for BotHandler {
     async fn message(&self, ctx: Context, msg: Message) {
+        if msg.author.bot {
+            return;
+        }
+
         let mut cmd_parts = msg.content.splitn(2, char::is_whitespace);
         let cmd = cmd_parts.next().ok_or(()).unwrap();
         let args = cmd_parts.next().unwrap_or("");
 
-        let channel_list_str: String = get_cached_data(&ctx, CHANNEL_LIST_KEY)
-            .await
-            .unwrap_or_default();
-        if !channel_list_str
-            .split(',')
-            .any(|s| s.trim() == &msg.channel_id.to_string())
+        if ![
+            DRAFT_CMD,
+            CARD_COMMAND,
+            PING_CMD,
+            CHANNEL_CMD,
+            CARD_DATA_CMD,
+        ]
+        .contains(&cmd)
         {
-            let channel_info = ctx.http.get_channel(msg.channel_id.0).await;
-            match channel_info {
-                Ok(channel) => match channel {
-                    Channel::Guild(channel) if channel.name.starts_with(DRAFT_THREAD_PREFIX) => {}
-                    _ => {
-                        return;
-                    }
-                },
-                Err(e) => {
-                    println!("Error getting channel info: {:?}", e);
-                    return;
-                }
-            }
+            return;
         }
 
-        match cmd {
-            DRAFT_CMD => {
-                process_draft_command(&ctx, msg.channel_id, msg.author, args).await;
-            }
-            CARD_COMMAND => {
-                process_card_command(&ctx, msg.channel_id, msg.author, args).await;
-            }
-            "!ping" => {
-                send_message(&ctx, msg.channel_id, "Pong!").await;
-            }
-            _ => {}
-        };
+        // Channel management has to work before the channel is allowed
+        if cmd != CHANNEL_CMD && !is_channel_allowed(&ctx, msg.channel_id).await {
+            return;
+        }
+
+        if !check_rate_limit(&ctx, &msg).await {
+            return;
+        }
+
+        dispatch_command(&ctx, &msg, cmd, args).await;
     }
     async fn ready(&self, _: Context, ready: Ready) {
-        println!("{} is connected!", ready.user.name);
+        info!("{} is connected!", ready.user.name);
     }
 }
 
-pub async fn init_client(context: &AppContext) -> Client {
-    let token = env::var("DISCORD_TOKEN").expect("Expected DISCORD_TOKEN in the environment");
+// Only known sub commands become metric labels, so free text can't grow the label set
+fn get_metric_sub_command(cmd: &str, args: &str) -> &'static str {
+    let sub_cmds: &[&'static str] = match cmd {
+        DRAFT_CMD => &[
+            DRAFT_HELP_CMD,
+            DRAFT_REG_CMD,
+            DRAFT_NEW_CMD,
+            DRAFT_OWN_CMD,
+            DRAFT_SIM_CMD,
+            DRAFT_DECK_CMD,
+            DRAFT_EXPORT_CMD,
+            DRAFT_BUILD_CMD,
+            DRAFT_STATS_CMD,
+            DRAFT_SIGNALS_CMD,
+            DRAFT_HISTORY_CMD,
+            DRAFT_PICK_CMD,
+            DRAFT_REPLAY_CMD,
+            DRAFT_RESULT_CMD,
+            DRAFT_FINAL_CMD,
+            DRAFT_WINRATE_CMD,
+            DRAFT_CARDSTATS_CMD,
+            DRAFT_TEAMRATINGS_CMD,
+            DRAFT_VOTE_CMD,
+            DRAFT_UNVOTE_CMD,
+            DRAFT_VOTES_CMD,
+            DRAFT_MODE_CMD,
+            DRAFT_MIGRATE_CMD,
+            DRAFT_COMMIT_CMD,
+            DRAFT_PIC_CMD,
+        ],
+        CHANNEL_CMD => &[
+            CHANNEL_LIST_CMD,
+            CHANNEL_ADD_CMD,
+            CHANNEL_REMOVE_CMD,
+            CHANNEL_PREFIX_CMD,
+        ],
+        CARD_DATA_CMD => &[CARD_DATA_UPDATE_CMD, CARD_DATA_RELOAD_CMD],
+        _ => return "",
+    };
+
+    match args.split_whitespace().next() {
+        None => "",
+        Some(sub_cmd) => sub_cmds
+            .iter()
+            .find(|known| **known == sub_cmd)
+            .copied()
+            .unwrap_or("other"),
+    }
+}
+
+#[instrument(
+    skip_all,
+    fields(
+        cmd = cmd,
+        channel = %msg.channel_id,
+        user = %msg.author.name,
+        game_id = tracing::field::Empty
+    )
+)]
+async fn dispatch_command(ctx: &Context, msg: &Message, cmd: &str, args: &str) {
+    debug!(args, "Running command");
+    metrics::count_command(cmd, get_metric_sub_command(cmd, args));
+
+    match cmd {
+        DRAFT_CMD => {
+            let db_permits = {
+                let data = ctx.data.read().await;
+                data.get::<BotDbPermits>()
+                    .expect("Expected BotDbPermits in TypeMap.")
+                    .clone()
+            };
+
+            // Every draft command reads from the database, so only a few are run at once
+            let db_permit = tokio::time::timeout(DB_COMMAND_WAIT, db_permits.acquire_owned()).await;
+            match db_permit {
+                Ok(Ok(_permit)) => {
+                    process_draft_command(ctx, msg.channel_id, msg.author.clone(), args).await;
+                }
+                _ => {
+                    send_message(
+                        ctx,
+                        msg.channel_id,
+                        "The bot is busy right now, please try again in a moment.",
+                    )
+                    .await;
+                }
+            }
+        }
+        CARD_COMMAND => {
+            process_card_command(ctx, msg.channel_id, msg.author.clone(), args).await;
+        }
+        CHANNEL_CMD => {
+            process_channel_command(ctx, msg, args).await;
+        }
+        CARD_DATA_CMD => {
+            process_card_data_command(ctx, msg, args).await;
+        }
+        PING_CMD => {
+            send_message(ctx, msg.channel_id, "Pong!").await;
+        }
+        _ => {}
+    };
+}
+
+pub async fn init_client(context: &AppContext) -> Res<Client> {
+    let token = app_config::get().discord.token.to_string();
     let intents = GatewayIntents::GUILD_MESSAGES
         | GatewayIntents::DIRECT_MESSAGES
         | GatewayIntents::MESSAGE_CONTENT;
@@ -611,25 +2602,25 @@ pub async fn init_client(context: &AppContext) -> Client {
     let client = Client::builder(&token, intents)
         .event_handler(BotHandler)
         .await
-        .expect("Err creating client");
+        .err_to(Error::Discord)?;
     {
         let mut data = client.data.write().await;
-        let card_data = crate::card_loader::load_card_hashmap_by_name();
-        let mut card_index = SearchIndex::default();
+        let card_data = crate::card_loader::load_card_hashmap_by_name()?;
+        let card_index = build_card_index(&card_data);
 
-        for (key, value) in card_data.iter() {
-            card_index.insert(key, value);
+        let rating_validation = crate::rating_validation::check_card_ratings(&card_data)?;
+        if !rating_validation.is_ok() {
+            warn!("{}", rating_validation.to_text());
         }
 
         // Read list of channel from CHANNEL_LIST_FILE
-        let mut file =
-            std::fs::File::open(CHANNEL_LIST_FILE).expect("Unable to open channel list file");
-        let mut contents = String::new();
-        std::io::Read::read_to_string(&mut file, &mut contents)
-            .expect("Unable to read channel list file");
+        let contents = std::fs::read_to_string(CHANNEL_LIST_FILE).map_err(|err| {
+            Error::Data(format!("Unable to read [{}]: {}", CHANNEL_LIST_FILE, err))
+        })?;
 
         data.insert::<BotCardData>(Arc::new(card_data));
         data.insert::<BotCardIndex>(Arc::new(card_index));
+        data.insert::<BotCardRating>(Arc::new(crate::card_loader::load_card_rating()?));
 
         let mut initial_data: HashMap<String, String> = HashMap::new();
         initial_data.insert(CHANNEL_LIST_KEY.to_string(), contents);
@@ -640,15 +2631,38 @@ pub async fn init_client(context: &AppContext) -> Client {
         // end of pre-loaded data
 
         data.insert::<BotCache>(Arc::new(RwLock::new(initial_data)));
+        data.insert::<BotUserNames>(Arc::new(RwLock::new(HashMap::new())));
+        data.insert::<BotSimulations>(Arc::new(Mutex::new(TtlCache::new(SIMULATION_TTL))));
+        data.insert::<BotGuildConfigs>(Arc::new(RwLock::new(HashMap::new())));
+        data.insert::<BotCardChoices>(Arc::new(RwLock::new(TtlCache::new(CARD_CHOICE_TTL))));
+        data.insert::<BotReplayCursors>(Arc::new(RwLock::new(TtlCache::new(REPLAY_CURSOR_TTL))));
+        data.insert::<BotTeamRatings>(Arc::new(RwLock::new(TtlCache::new(TEAM_RATING_TTL))));
+        data.insert::<BotChannelCache>(Arc::new(RwLock::new(TtlCache::new(CHANNEL_INFO_TTL))));
+        data.insert::<BotMigrationRequests>(Arc::new(RwLock::new(TtlCache::new(
+            MIGRATION_REQUEST_TTL,
+        ))));
+        data.insert::<BotRateLimit>(Arc::new(Mutex::new(BotRateLimits {
+            user: RateLimiter::new(USER_RATE_LIMIT, USER_RATE_LIMIT_WINDOW),
+            channel: RateLimiter::new(CHANNEL_RATE_LIMIT, CHANNEL_RATE_LIMIT_WINDOW),
+        })));
+        data.insert::<BotDbPermits>(Arc::new(tokio::sync::Semaphore::new(
+            MAX_CONCURRENT_DB_COMMANDS,
+        )));
     }
 
-    client
+    Ok(client)
 }
 
 pub async fn main(context: &AppContext) {
-    let mut client = init_client(context).await;
+    let mut client = match init_client(context).await {
+        Ok(client) => client,
+        Err(err) => {
+            error!(kind = err.kind(), "Unable to start the bot: {}", err);
+            return;
+        }
+    };
 
     if let Err(why) = client.start().await {
-        println!("Client error: {:?}", why);
+        error!("Client error: {:?}", why);
     }
 }
@@ -1,3 +1,11 @@
+use tracing::warn;
+
+use crate::opt::*;
+
+// Discord rejects longer messages
+pub const MESSAGE_LIMIT: usize = 2000;
+const CODE_FENCE: &str = "```";
+
 pub struct BotReply {
     messages: Vec<String>,
 }
@@ -13,6 +21,13 @@ impl BotReply {
         self.messages.push(msg);
     }
 
+    // The user gets a short message, the details only go to the log
+    pub fn add_error(&mut self, action: &str, err: &Error) {
+        warn!(kind = err.kind(), "{}: {}", action, err);
+        self.messages
+            .push(format!("{}: {}", action, err.user_message()));
+    }
+
     pub fn add_boxed(&mut self, msg: String) {
         self.messages.push(format!("```\n{}\n```", msg));
     }
@@ -21,3 +36,103 @@ impl BotReply {
         self.messages.join("\n")
     }
 }
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+fn split_line(line: &str, max: usize) -> Vec<String> {
+    if char_len(line) <= max {
+        return vec![line.to_string()];
+    }
+    line.chars()
+        .collect::<Vec<char>>()
+        .chunks(max)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+// Splits on lines into messages of at most `limit` characters. A code block that is
+// cut in two is closed at the end of one message and opened again in the next
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    // Room for the fence that closes an open block
+    let reserve = CODE_FENCE.len() + 1;
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    let mut in_code = false;
+
+    for line in text
+        .lines()
+        .flat_map(|line| split_line(line, limit - 2 * reserve))
+    {
+        let is_fence = line.trim_start().starts_with(CODE_FENCE);
+        let needed = char_len(&current)
+            + 1
+            + char_len(&line)
+            + if in_code || is_fence { reserve } else { 0 };
+        if !current.is_empty() && needed > limit {
+            if in_code {
+                current.push('\n');
+                current.push_str(CODE_FENCE);
+            }
+            messages.push(std::mem::take(&mut current));
+            if in_code {
+                current.push_str(CODE_FENCE);
+            }
+        }
+
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+        if is_fence {
+            in_code = !in_code;
+        }
+    }
+
+    if !current.is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_message() {
+        assert_eq!(split_message("Pong!", MESSAGE_LIMIT), vec!["Pong!"]);
+        assert!(split_message("", MESSAGE_LIMIT).is_empty());
+    }
+
+    #[test]
+    fn test_split_code_block() {
+        let mut reply = BotReply::new();
+        reply.add("Deck".to_string());
+        reply.add_boxed(
+            (1..=48)
+                .map(|idx| format!("1 Card number {:02} (Set1 #{})", idx, idx))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        );
+
+        let messages = split_message(&reply.to_string(), 200);
+        assert!(messages.len() > 1);
+        for message in messages.iter() {
+            assert!(char_len(message) <= 200, "{}", message);
+            assert_eq!(message.matches(CODE_FENCE).count() % 2, 0, "{}", message);
+        }
+        assert!(messages[0].starts_with("Deck\n```"));
+        assert!(messages[1].starts_with("```\n1 Card number"));
+        assert!(messages.concat().contains("Card number 48"));
+    }
+
+    #[test]
+    fn test_split_long_line() {
+        let line = "x".repeat(450);
+        let messages = split_message(&line, 200);
+        assert!(messages.iter().all(|message| char_len(message) <= 200));
+        assert_eq!(messages.concat(), line);
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq)]
+pub enum RateLimit {
+    Allowed,
+    // `notify` is only set for the first rejected hit in a window so the bot
+    // does not answer every spammed message with a warning of its own
+    Limited { retry_after: Duration, notify: bool },
+}
+
+pub struct RateLimiter<K> {
+    limit: usize,
+    window: Duration,
+    hits: HashMap<K, VecDeque<Instant>>,
+    notified: HashSet<K>,
+}
+
+impl<K: Hash + Eq + Clone> RateLimiter<K> {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        RateLimiter {
+            limit,
+            window,
+            hits: HashMap::new(),
+            notified: HashSet::new(),
+        }
+    }
+
+    pub fn check(&mut self, key: &K, now: Instant) -> RateLimit {
+        let rate_limit = self.peek(key, now);
+        if rate_limit == RateLimit::Allowed {
+            self.record(key, now);
+        }
+        rate_limit
+    }
+
+    // Same as `check` without counting the hit, for a hit that another limit can still reject
+    pub fn peek(&mut self, key: &K, now: Instant) -> RateLimit {
+        let window = self.window;
+        let hits = self.hits.entry(key.clone()).or_default();
+        while let Some(hit) = hits.front() {
+            if now.duration_since(*hit) >= window {
+                hits.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if hits.len() < self.limit {
+            return RateLimit::Allowed;
+        }
+
+        let retry_after = hits
+            .front()
+            .map(|hit| window.saturating_sub(now.duration_since(*hit)))
+            .unwrap_or(window);
+        let notify = self.notified.insert(key.clone());
+
+        RateLimit::Limited {
+            retry_after,
+            notify,
+        }
+    }
+
+    pub fn record(&mut self, key: &K, now: Instant) {
+        self.hits.entry(key.clone()).or_default().push_back(now);
+        self.notified.remove(key);
+    }
+
+    // Drops keys that have not been seen for a full window
+    pub fn prune(&mut self, now: Instant) {
+        let window = self.window;
+        self.hits.retain(|_, hits| {
+            hits.back()
+                .map_or(false, |hit| now.duration_since(*hit) < window)
+        });
+        let hits = &self.hits;
+        self.notified.retain(|key| hits.contains_key(key));
+    }
+}
+
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: HashMap<K, (V, Instant)>,
+}
+
+impl<K: Hash + Eq, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        TtlCache {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &K, now: Instant) -> Option<V> {
+        self.entries
+            .get(key)
+            .filter(|(_, inserted)| now.duration_since(*inserted) < self.ttl)
+            .map(|(value, _)| value.clone())
+    }
+
+    // Expired entries are swept here, nothing else would ever drop them
+    pub fn insert(&mut self, key: K, value: V, now: Instant) {
+        let ttl = self.ttl;
+        self.entries
+            .retain(|_, (_, inserted)| now.duration_since(*inserted) < ttl);
+        self.entries.insert(key, (value, now));
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        self.entries.remove(key);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(2, Duration::from_secs(10));
+
+        assert_eq!(limiter.check(&1, now), RateLimit::Allowed);
+        assert_eq!(
+            limiter.check(&1, now + Duration::from_secs(2)),
+            RateLimit::Allowed
+        );
+        assert_eq!(
+            limiter.check(&1, now + Duration::from_secs(4)),
+            RateLimit::Limited {
+                retry_after: Duration::from_secs(6),
+                notify: true
+            }
+        );
+        assert_eq!(
+            limiter.check(&1, now + Duration::from_secs(5)),
+            RateLimit::Limited {
+                retry_after: Duration::from_secs(5),
+                notify: false
+            }
+        );
+        assert_eq!(limiter.check(&2, now), RateLimit::Allowed);
+        assert_eq!(
+            limiter.check(&1, now + Duration::from_secs(10)),
+            RateLimit::Allowed
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_prune() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(1, Duration::from_secs(10));
+
+        limiter.check(&1, now);
+        limiter.check(&1, now);
+        limiter.prune(now + Duration::from_secs(10));
+
+        assert!(limiter.hits.is_empty());
+        assert!(limiter.notified.is_empty());
+    }
+
+    #[test]
+    fn test_ttl_cache() {
+        let now = Instant::now();
+        let mut cache = TtlCache::new(Duration::from_secs(60));
+
+        cache.insert(1, true, now);
+        assert_eq!(cache.get(&1, now + Duration::from_secs(30)), Some(true));
+        assert_eq!(cache.get(&1, now + Duration::from_secs(60)), None);
+        assert_eq!(cache.get(&2, now), None);
+
+        cache.insert(2, false, now + Duration::from_secs(60));
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(cache.get(&2, now + Duration::from_secs(60)), Some(false));
+    }
+
+    #[test]
+    fn test_rate_limiter_peek() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(1, Duration::from_secs(10));
+
+        assert_eq!(limiter.peek(&1, now), RateLimit::Allowed);
+        assert_eq!(limiter.peek(&1, now), RateLimit::Allowed);
+        limiter.record(&1, now);
+        assert!(matches!(limiter.peek(&1, now), RateLimit::Limited { .. }));
+    }
+}
@@ -2,25 +2,33 @@ use crate::capture::screen::CaptureOpt;
 use crate::opt::*;
 
 use std::collections::HashSet;
+use std::path::Path;
 use std::{collections::HashMap, time};
 
 use indicium::simple::SearchIndex;
+use tracing::{debug, error, info, instrument, warn};
 
+use crate::app_config;
 use crate::app_context::*;
+use crate::card_matcher;
+use crate::deck_builder;
+use crate::deck_export::{self, DeckExportFormat};
+use crate::draft_replay;
+use crate::draft_signals;
+use crate::draft_simulator::DraftSimulator;
+use crate::metrics;
 use crate::models::draft_data::{DraftPick, DraftRecord};
+use crate::models::draft_game::DraftGame;
+use crate::pool_analysis;
+use crate::rating_validation;
 
 use super::*;
 use crate::models::card::*;
 
-mod card_matcher;
 mod image_uploader;
 mod ocr_engine;
 mod screen;
 
-const GAME_ID_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
-const GAME_ID_LENGTH: usize = 8;
-const CURRENT_GAME_ID_KEY: &str = "current_game_id";
-
 const LABEL_ACTION_SELECT: &str = "Select";
 const LABEL_ACTION_AUTO_MODE: &str = "Auto";
 const LABEL_ACTION_AUTO_MODE_ALL: &str = "All";
@@ -29,6 +37,15 @@ const LABEL_ACTION_AUTO_MODE_ONCE: &str = "Once";
 const LABEL_NEW_GAME: &str = "New Game";
 const LABEL_EXISTING_GAME: &str = "Existing Game";
 const LABEL_INPUT_GAME_ID: &str = "Input Game ID";
+const LABEL_RECENT_GAME: &str = "Recent Game";
+const LABEL_EXPORT_DECK: &str = "Export Deck";
+const LABEL_EXPORT_FORMAT: &str = "Export Format";
+const LABEL_BUILD_DECK: &str = "Build Deck";
+const LABEL_SIMULATE_DRAFT: &str = "Simulate Draft";
+const LABEL_SIMULATE_SET: &str = "Simulated Set";
+const LABEL_CARD_DATA_PATH: &str = "Card Export Path";
+const LABEL_UPDATE_CARD_DATA: &str = "Update Card Data";
+const LABEL_SCREEN_PROFILE: &str = "Screen Profile";
 
 const LABEL_CONFIRM_AUTO: &str = "Confirm Auto";
 const LABEL_CONFIRM_MANUAL: &str = "Confirm Manual";
@@ -40,16 +57,21 @@ const LABEL_MENU_SELECT_CARD: &str = "Select Card";
 const LABEL_MENU_ACTION: &str = "Action";
 
 const APP_NAME: &str = "Draft Claw";
-
-fn log(s: String) {
-    log_if(s.as_str(), DbgFlg::Capture);
-}
+const PICK_MODE_AUTO: &str = "auto";
+const PICK_MODE_MANUAL: &str = "manual";
 
 #[cfg(feature = "capture-interactive")]
 pub async fn main(context: &AppContext) {
-    let runtime_data = initialize_runtime_data();
+    let state = context.state();
+    let mut runtime_data = match initialize_runtime_data(state.screen_profile) {
+        Ok(runtime_data) => runtime_data,
+        Err(err) => {
+            error!("Unable to load the card data: {}", err);
+            return;
+        }
+    };
 
-    let game_menu = terminal_menu::menu(vec![
+    let mut menu_items = vec![
         terminal_menu::label(
             std::iter::repeat('-')
                 .take(APP_NAME.len())
@@ -62,29 +84,110 @@ pub async fn main(context: &AppContext) {
                 .collect::<String>(),
         ),
         terminal_menu::string(LABEL_INPUT_GAME_ID, "", true),
+    ];
+    if !state.recent_game_ids.is_empty() {
+        menu_items.push(terminal_menu::list(
+            LABEL_RECENT_GAME,
+            state.recent_game_ids.clone(),
+        ));
+    }
+    menu_items.extend(vec![
         terminal_menu::button(LABEL_EXISTING_GAME),
         terminal_menu::button(LABEL_NEW_GAME),
+        terminal_menu::list(LABEL_EXPORT_FORMAT, vec!["eternal", "text", "json"]),
+        terminal_menu::button(LABEL_EXPORT_DECK),
+        terminal_menu::button(LABEL_BUILD_DECK),
+        terminal_menu::string(LABEL_SIMULATE_SET, "", true),
+        terminal_menu::button(LABEL_SIMULATE_DRAFT),
+        terminal_menu::string(LABEL_CARD_DATA_PATH, "", true),
+        terminal_menu::button(LABEL_UPDATE_CARD_DATA),
+        // The saved profile comes first so it stays selected
+        terminal_menu::list(
+            LABEL_SCREEN_PROFILE,
+            std::iter::once(state.screen_profile)
+                .chain(
+                    ScreenProfile::ALL
+                        .into_iter()
+                        .filter(|profile| *profile != state.screen_profile),
+                )
+                .map(|profile| profile.name())
+                .collect::<Vec<&str>>(),
+        ),
         terminal_menu::back_button(LABEL_EXIT),
     ]);
+    let game_menu = terminal_menu::menu(menu_items);
 
-    let mut auto_mode_all: bool = false;
+    let mut auto_mode_all: bool = state.auto_mode_all;
+    // The same pick is captured on every pass until it is made
+    let mut analyzed_pick: Option<(String, String)> = None;
 
     terminal_menu::run(&game_menu);
     let mut game_id: String =
         match terminal_menu::mut_menu(&game_menu).selection_value(LABEL_INPUT_GAME_ID) {
             game_id if !game_id.is_empty() => game_id.to_string(),
-            _ => context.read_data(CURRENT_GAME_ID_KEY).unwrap_or_else(String::new),
+            _ if !state.recent_game_ids.is_empty() => terminal_menu::mut_menu(&game_menu)
+                .selection_value(LABEL_RECENT_GAME)
+                .to_string(),
+            _ => String::new(),
         };
 
+    if let Some(screen_profile) = ScreenProfile::from_name(
+        terminal_menu::mut_menu(&game_menu).selection_value(LABEL_SCREEN_PROFILE),
+    ) {
+        if screen_profile != state.screen_profile {
+            if let Err(err) = context.update_state(|state| state.screen_profile = screen_profile) {
+                warn!("Unable to save screen profile: {}", err);
+            }
+        }
+        runtime_data.screen_profile = screen_profile;
+    }
+
+    {
+        let menu_selection = terminal_menu::mut_menu(&game_menu);
+        if menu_selection.selected_item_name() == LABEL_EXPORT_DECK {
+            let format = menu_selection
+                .selection_value(LABEL_EXPORT_FORMAT)
+                .parse::<DeckExportFormat>()
+                .unwrap_or_default();
+            match export_deck(&runtime_data, &game_id, &format).await {
+                Ok(exported) => println!("{}", exported),
+                Err(err) => warn!("Unable to export deck: {}", err),
+            }
+            return;
+        }
+        if menu_selection.selected_item_name() == LABEL_SIMULATE_DRAFT {
+            let set_name = menu_selection.selection_value(LABEL_SIMULATE_SET);
+            if let Err(err) = simulate_draft(&runtime_data, set_name) {
+                warn!("Unable to simulate draft: {}", err);
+            }
+            return;
+        }
+        if menu_selection.selected_item_name() == LABEL_UPDATE_CARD_DATA {
+            let export_path = Path::new(menu_selection.selection_value(LABEL_CARD_DATA_PATH));
+            match card_loader::update_card_data(export_path, &runtime_data.card_ratings) {
+                Ok(update) => println!("{}", update.to_text(usize::MAX)),
+                Err(err) => warn!("Unable to update card data: {}", err),
+            }
+            return;
+        }
+        if menu_selection.selected_item_name() == LABEL_BUILD_DECK {
+            match build_deck(&runtime_data, &game_id).await {
+                Ok(suggestion) => println!("{}", suggestion),
+                Err(err) => warn!("Unable to build deck: {}", err),
+            }
+            return;
+        }
+    }
+
     loop {
         tokio::time::sleep(time::Duration::from_secs(1)).await;
         match screen::connect_eternal_screen() {
             Err(err) => {
-                log(format!("Unable to connect to screen: {}", err));
+                warn!("Unable to connect to screen: {}", err);
                 continue;
             }
             _ => {
-                log(format!("Connected to screen!"));
+                info!("Connected to screen!");
             }
         };
 
@@ -97,35 +200,39 @@ pub async fn main(context: &AppContext) {
                 LABEL_EXISTING_GAME => {
                     // game_id = "RI37NIhk".to_string();
                     if game_id.is_empty() {
-                        log("No existing game found!".to_string());
+                        warn!("No existing game found!");
                         continue;
                     }
+                    save_current_game(context, &game_id);
                 }
                 _ => {
-                    log("Exiting...".to_string());
+                    info!("Exiting...");
                     break;
                 }
             }
         }
-        log(format!("Game ID: {}", game_id));
+        info!("Game ID: {}", game_id);
 
         match db_access::get_draft_game(&game_id).await {
             // Insert if current game does not exist in the db
             Ok(result) if result.is_none() => {
-                db_access::insert_draft_game(&game_id).await.unwrap();
+                if let Err(err) = db_access::insert_draft_game(&game_id).await {
+                    warn!("Unable to insert draft game: {}", err);
+                    continue;
+                }
             }
             Ok(result) => {
-                log(format!(
+                info!(
                     "Game [{}] already exists! It belongs to [{}].",
                     game_id,
                     result
                         .unwrap()
                         .user_id
                         .unwrap_or("unregistered".to_string())
-                ));
+                );
             }
             Err(e) => {
-                log(format!("Unable to get draft game: {}", e));
+                warn!("Unable to get draft game: {}", e);
                 continue;
             }
         }
@@ -136,13 +243,21 @@ pub async fn main(context: &AppContext) {
         };
 
         let decklist = db_access::get_decklist(&game_id).await;
-        dbg!(&decklist);
+        debug!(?decklist, "Got decklist");
+
+        let pick = Some((game_id.to_string(), draft_record.pick.to_string()));
+        if analyzed_pick != pick {
+            print_draft_analysis(&runtime_data, &game_id).await;
+            analyzed_pick = pick;
+        }
 
         let input: Option<u8>;
+        let pick_mode: &str;
 
         if auto_mode_all {
             input = draft_record.selected_card;
-            log(format!("Auto selected: {:?}", input));
+            pick_mode = PICK_MODE_AUTO;
+            info!("Auto selected: {:?}", input);
         } else {
             let auto_selected_card = match draft_record.selected_card {
                 Some(idx) => draft_record.selection_vec[idx as usize].to_owned(),
@@ -195,7 +310,8 @@ pub async fn main(context: &AppContext) {
                             .split(":")
                             .next()
                             .map_or(None, |s| s.parse::<u8>().ok());
-                        log(format!("Manually selected: {:?}", input));
+                        pick_mode = PICK_MODE_MANUAL;
+                        info!("Manually selected: {:?}", input);
                     }
                     LABEL_CONFIRM_AUTO => {
                         auto_mode_all = match select_card_menu_instance
@@ -205,67 +321,87 @@ pub async fn main(context: &AppContext) {
                             LABEL_ACTION_AUTO_MODE_ALL => true,
                             _ => false,
                         };
+                        if let Err(err) =
+                            context.update_state(|state| state.auto_mode_all = auto_mode_all)
+                        {
+                            warn!("Unable to save auto mode: {}", err);
+                        }
 
                         input = draft_record.selected_card;
-                        log(format!("Auto selected: {:?}", input));
+                        pick_mode = PICK_MODE_AUTO;
+                        info!("Auto selected: {:?}", input);
                     }
                     LABEL_CONTINUE => {
                         continue;
                     }
                     _ => {
-                        log("Invalid menu option".to_string());
+                        warn!("Invalid menu option");
                         return;
                     }
                 }
             }
         }
 
-        act_on_input(input);
+        act_on_input(input, pick_mode, runtime_data.screen_profile);
     }
 }
 
 #[cfg(all(feature = "capture", not(feature = "capture-interactive")))]
 pub async fn main(context: &AppContext) {
-    let runtime_data = initialize_runtime_data();
+    let runtime_data = match initialize_runtime_data(context.state().screen_profile) {
+        Ok(runtime_data) => runtime_data,
+        Err(err) => {
+            error!("Unable to load the card data: {}", err);
+            return;
+        }
+    };
+
+    // TODO: add ability to select game id
+    let game_id = app_config::get().capture.game_id.to_string();
+    if game_id.is_empty() {
+        error!("capture.game_id is not configured");
+        return;
+    }
 
     loop {
         tokio::time::sleep(time::Duration::from_secs(1)).await;
         match screen::connect_eternal_screen() {
             Err(err) => {
-                log(format!("Unable to connect to screen: {}", err));
+                warn!("Unable to connect to screen: {}", err);
                 continue;
             }
             _ => {
-                log(format!("Connected to screen!"));
+                info!("Connected to screen!");
             }
         };
 
-        // TODO: add ability to select game id
-        let game_id = std::env::var("GAME_ID").expect("GAME_ID not set");
-
         let draft_record = match loop_capture(&runtime_data, &game_id).await {
             Ok(record) => record,
-            Err(_) => continue,
+            Err(err) if err.is_retryable() => continue,
+            // Nobody is watching, so a failure that repeats every time stops the capture
+            Err(err) => {
+                error!(kind = err.kind(), "Stopping the capture: {}", err);
+                return;
+            }
         };
 
         let input: Option<u8>;
         input = draft_record.selected_card;
-        act_on_input(input);
+        act_on_input(input, PICK_MODE_AUTO, runtime_data.screen_profile);
     }
 }
 
+#[instrument(skip(runtime_data), fields(pick))]
 async fn loop_capture(runtime_data: &RuntimeData, game_id: &str) -> Res<DraftRecord> {
     let draft_record: DraftRecord;
 
     match capture_draft_record(&runtime_data, &game_id) {
         Ok((mut record, image_path)) => {
+            tracing::Span::current().record("pick", record.pick.to_string().as_str());
+
             let overwrite = match db_access::get_draft_record(&game_id, &record.pick).await {
                 Ok(Some(record_in_db)) => {
-                    log(format!(
-                        "Record of {} in game {} exists in db",
-                        record.pick.to_string(),
-                        game_id
-                    ));
+                    debug!("Record exists in db");
 
                     if record_in_db.selected_card.is_some() {
                         record.selected_card = record_in_db.selected_card;
@@ -275,16 +411,16 @@ async fn loop_capture(runtime_data: &RuntimeData, game_id: &str) -> Res<DraftRec
                         || record_in_db.image_url.is_none()
                 }
                 _ => {
-                    log(format!(
+                    warn!(
                         "Unable to get existing draft record. Overwriting with new captured data."
-                    ));
+                    );
                     true
                 }
             };
 
             if overwrite {
                 let image_url = image_uploader::upload_image(&image_path).await?;
-                log(format!("Uploaded image to: {}", &image_url));
+                info!("Uploaded image to: {}", &image_url);
 
                 record.set_image_url(&image_url);
 
@@ -292,14 +428,14 @@ async fn loop_capture(runtime_data: &RuntimeData, game_id: &str) -> Res<DraftRec
                 db_access::upsert_draft_record(&record)
                     .await
                     .unwrap_or_else(|err| {
-                        log(format!("Unable to insert draft record: {}", err));
+                        warn!("Unable to insert draft record: {}", err);
                     });
             }
 
             draft_record = record.clone();
         }
         Err(e) => {
-            log(format!("Unable to capture draft record: {}", e));
+            warn!(kind = e.kind(), "Unable to capture draft record: {}", e);
             return Err(e);
         }
     }
@@ -307,34 +443,125 @@ async fn loop_capture(runtime_data: &RuntimeData, game_id: &str) -> Res<DraftRec
     Ok(draft_record)
 }
 
-fn act_on_input(input: Option<u8>) {
+fn act_on_input(input: Option<u8>, pick_mode: &str, screen_profile: ScreenProfile) {
     match input {
-        Some(card_index) => match screen::select_card(card_index) {
-            Ok(_) => {}
+        Some(card_index) => match screen::select_card(card_index, screen_profile) {
+            Ok(_) => metrics::count_pick(pick_mode, "selected"),
             Err(err) => {
-                log(format!("Unable to select card: {}", err));
+                metrics::count_pick(pick_mode, "failed");
+                warn!("Unable to select card: {}", err);
             }
         },
         None => {
-            log(format!("Invalid input {:?}", input));
+            metrics::count_pick(pick_mode, "no_card");
+            warn!("Invalid input {:?}", input);
         }
     }
 }
 
+fn save_current_game(context: &AppContext, game_id: &str) {
+    if let Err(err) = context.update_state(|state| state.set_current_game(game_id)) {
+        warn!("Unable to save current game: {}", err);
+    }
+}
+
 fn create_new_game(context: &AppContext) -> String {
-    let game_id = nanoid::nanoid!(
-        GAME_ID_LENGTH,
-        &GAME_ID_ALPHABET.to_string().chars().collect::<Vec<char>>()
-    );
-    context.write_data(CURRENT_GAME_ID_KEY, &game_id);
+    let game_id = DraftGame::generate_id();
+    save_current_game(context, &game_id);
 
     game_id
 }
 
-pub async fn upload_card_rating() {
-    let card_ratings = card_loader::get_card_rating_list();
+async fn get_deck_cards(runtime_data: &RuntimeData, game_id: &str) -> Res<Vec<Card>> {
+    let deck_list = db_access::get_decklist(game_id).await?;
+    card_loader::cards_by_names(&runtime_data.card_map, &deck_list)
+}
+
+pub async fn export_deck(
+    runtime_data: &RuntimeData,
+    game_id: &str,
+    format: &DeckExportFormat,
+) -> Res<String> {
+    let cards = get_deck_cards(runtime_data, game_id).await?;
+
+    deck_export::export_deck(&cards, format)
+}
+
+async fn print_draft_analysis(runtime_data: &RuntimeData, game_id: &str) {
+    if let Ok(cards) = get_deck_cards(runtime_data, game_id).await {
+        let analysis = pool_analysis::analyze_pool(&cards, &runtime_data.card_ratings);
+        println!("{}", analysis.to_text());
+    }
 
-    db_access::insert_card_rating(&card_ratings).await.unwrap();
+    if let Ok(records) = db_access::get_draft_records(game_id).await {
+        let signals = draft_signals::analyze_signals(
+            &records,
+            &runtime_data.card_map,
+            &runtime_data.card_ratings,
+        );
+        println!("{}", signals.to_text());
+    }
+}
+
+pub async fn build_deck(runtime_data: &RuntimeData, game_id: &str) -> Res<String> {
+    let cards = get_deck_cards(runtime_data, game_id).await?;
+
+    deck_builder::build_deck(&cards, &runtime_data.card_ratings)
+        .map(|suggestion| suggestion.to_text(&runtime_data.card_ratings))
+}
+
+// Drafts against bots on the terminal, nothing is written to the database
+pub fn simulate_draft(runtime_data: &RuntimeData, set_name: &str) -> Res<()> {
+    let cards = runtime_data
+        .card_map
+        .values()
+        .cloned()
+        .collect::<Vec<Card>>();
+    let seed = time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .map(|time| time.as_nanos() as u64)
+        .unwrap_or_default();
+    let mut simulator = DraftSimulator::new(
+        &DraftGame::generate_id(),
+        &cards,
+        set_name,
+        &runtime_data.card_ratings,
+        seed,
+    )?;
+
+    while let Some(record) = simulator.current_record() {
+        println!(
+            "{}",
+            draft_replay::pick_text(&record, &runtime_data.card_ratings)
+        );
+        println!("Pick a card (1-{}):", record.selection_vec.len());
+
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input).err_to(Error::Data)?;
+        let pick_idx = match input.trim().parse::<u8>() {
+            Ok(pick_num) if pick_num >= 1 => pick_num - 1,
+            _ => {
+                println!("Enter the number of the card to pick");
+                continue;
+            }
+        };
+
+        match simulator.pick(pick_idx) {
+            Ok(card) => println!("Picked {}\n", card.name),
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    let deck = deck_export::export_deck(simulator.human_pool(), &DeckExportFormat::Text)?;
+    println!("Drafted cards:\n{}", deck);
+
+    Ok(())
+}
+
+pub async fn upload_card_rating() -> Res<()> {
+    let card_ratings = card_loader::get_card_rating_list()?;
+
+    db_access::insert_card_rating(&card_ratings).await
 }
 
 pub struct RuntimeData {
@@ -342,10 +569,11 @@ pub struct RuntimeData {
     card_name_tokens: HashSet<String>,
     card_index: SearchIndex<String>,
     card_ratings: HashMap<String, String>,
+    screen_profile: ScreenProfile,
 }
 
-fn initialize_runtime_data() -> RuntimeData {
-    let cards = card_loader::load_card_data();
+fn initialize_runtime_data(screen_profile: ScreenProfile) -> Res<RuntimeData> {
+    let cards = card_loader::load_card_data()?;
 
     let card_index = cards.iter().fold(SearchIndex::default(), |mut acc, card| {
         acc.insert(&card.name, card);
@@ -364,14 +592,20 @@ fn initialize_runtime_data() -> RuntimeData {
         acc
     });
 
-    let card_ratings = card_loader::load_card_rating();
+    let card_ratings = card_loader::load_card_rating()?;
+
+    let rating_validation = rating_validation::check_card_ratings(&card_map)?;
+    if !rating_validation.is_ok() {
+        warn!("{}", rating_validation.to_text());
+    }
 
-    RuntimeData {
+    Ok(RuntimeData {
         card_map,
         card_name_tokens,
         card_index,
         card_ratings,
-    }
+        screen_profile,
+    })
 }
 
 struct ScreenMatchedData {
@@ -383,16 +617,19 @@ struct ScreenMatchedData {
 }
 
 fn get_draft_selection_text(data: &RuntimeData) -> Res<ScreenMatchedData> {
-    let capture_opt = CaptureOpt::default();
+    let capture_opt = CaptureOpt {
+        screen_profile: data.screen_profile,
+        ..CaptureOpt::default()
+    };
     let screen_data = screen::capture_raw_text_on_screen(&capture_opt)?;
 
     let pick_number = screen_data
         .pick_num
         .split_whitespace()
         .nth(1)
-        .ok_or("unable to capture pick number")?
+        .ok_or(Error::Ocr("unable to capture pick number".to_string()))?
         .parse::<u8>()
-        .map_err(|err| err.to_string())?;
+        .err_to(Error::Ocr)?;
 
     let expected_count = DraftPick::new(pick_number).get_expected_card_selection_count() as usize;
 
@@ -404,6 +641,7 @@ fn get_draft_selection_text(data: &RuntimeData) -> Res<ScreenMatchedData> {
         .collect::<Vec<&str>>();
     let matched_card_names =
         card_matcher::find_card_name_matches(&data.card_index, &data.card_name_tokens, &card_texts);
+    metrics::count_card_matches(matched_card_names.len(), card_texts.len());
 
     let matched_cards = matched_card_names
         .iter()
@@ -413,14 +651,14 @@ fn get_draft_selection_text(data: &RuntimeData) -> Res<ScreenMatchedData> {
 
     if capture_opt.ocr_pick {
         if expected_count != matched_cards.len() {
-            return Err(format!(
+            return Err(Error::Ocr(format!(
                 "Expected {} cards, but found {} cards",
                 expected_count,
                 matched_cards.len()
-            ));
+            )));
         }
     }
-    log(format!("Found {} cards on screen.", matched_cards.len()));
+    info!("Found {} cards on screen.", matched_cards.len());
 
     let mut draft_selection_text = String::new();
     let mut draft_selection_vec = Vec::new();
@@ -470,7 +708,7 @@ fn get_draft_selection_text(data: &RuntimeData) -> Res<ScreenMatchedData> {
         let deck_text = format!("{}x {:30}", card_row.1, card_text);
         deck.push(deck_text);
     }
-    dbg!(&deck);
+    debug!(?deck, "Matched deck");
 
     Ok(ScreenMatchedData {
         pick_num: pick_number,
@@ -485,21 +723,21 @@ pub fn capture_draft_record(
     runtime_data: &RuntimeData,
     game_id: &str,
 ) -> Res<(DraftRecord, String)> {
-    log("Capturing draft record...".to_string());
+    info!("Capturing draft record...");
 
     let screen_matched_data = get_draft_selection_text(&runtime_data)?;
 
-    log(format!("Pick number: {}", screen_matched_data.pick_num));
-    log(format!(
+    info!("Pick number: {}", screen_matched_data.pick_num);
+    debug!(
         "Draft selection text:\n{}",
-        screen_matched_data.selection_text,
-    ));
+        screen_matched_data.selection_text
+    );
 
     if screen_matched_data.selection_text.is_empty() {
-        return Err(format!(
+        return Err(Error::Ocr(format!(
             "Unable to capture draft record for pick {}",
             screen_matched_data.pick_num
-        ));
+        )));
     }
 
     let mut draft_record = DraftRecord::new(
@@ -524,13 +762,13 @@ pub fn capture_draft_record(
     Ok((draft_record, screen_matched_data.image_path))
 }
 
-pub fn load_card_hashmap_by_name() -> HashMap<String, Card> {
-    let cards = card_loader::load_card_data();
+pub fn load_card_hashmap_by_name() -> Res<HashMap<String, Card>> {
+    let cards = card_loader::load_card_data()?;
     let mut card_hashmap = HashMap::new();
 
     for card in cards {
         card_hashmap.insert(card.name.clone(), card);
     }
 
-    card_hashmap
+    Ok(card_hashmap)
 }
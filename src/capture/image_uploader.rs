@@ -1,15 +1,21 @@
+use crate::app_config;
+use crate::metrics;
 use crate::opt::*;
 use imgurs::ImgurClient;
 
-const IMGUR_CLIENT_ID: &str = "IMGUR_CLIENT_ID";
-
 pub async fn upload_image(path: &str) -> Res<String> {
-    let client_id = std::env::var(IMGUR_CLIENT_ID).expect("Expect IMGUR client id");
+    let result = upload(path).await;
+    metrics::count_upload(&result);
+    result
+}
+
+async fn upload(path: &str) -> Res<String> {
+    let client_id = app_config::get().imgur.client_id.to_string();
 
     let client = ImgurClient::new(&client_id);
-    let upload_info = client.upload_image(path).await.err_to_str()?;
+    let upload_info = client.upload_image(path).await.err_to(Error::Upload)?;
     if !upload_info.success {
-        return Err("Unable to upload image".into());
+        return Err(Error::Upload("Unable to upload image".to_string()));
     }
 
     Ok(upload_info.data.link)
@@ -1,7 +1,11 @@
+use crate::app_config;
+use crate::app_context::ScreenProfile;
+use crate::metrics;
 use crate::opt::*;
 
 use lazy_static::lazy_static;
 use leptess::LepTess;
+use tracing::debug;
 
 use std::{
     fs::{self, File},
@@ -9,17 +13,10 @@ use std::{
     process::{Command, Stdio},
 };
 
-const RUNTIME_PATH: &str = "./";
 // const ETERNAL_WINDOW_NAME: &str = "Eternal Card Game";
 const ETERNAL_WINDOW_NAME: &str = "Android Emulator";
 const ETERNAL_SCREEN_FILE_NAME: &str = "game.png";
 const ETERNAL_SCREEN_PROCESSED_FILE_NAME: &str = "game_processed.png";
-const TESS_DATA: &str = "./resource/tessdata";
-
-// const ANDROID_ADB_PATH: &str = "/Users/tuephan/Library/Android/sdk/platform-tools/adb";
-const ANDROID_ADB_PATH: &str = "adb";
-
-const ANDROID_HOST_ENV_KEY: &str = "ANDROID_HOST";
 
 pub struct ScreenRect {
     pub x: i32,
@@ -39,92 +36,106 @@ impl ScreenRect {
     }
 }
 
-pub fn get_eternal_screen_path() -> Result<String, String> {
-    fs::create_dir_all(RUNTIME_PATH).map_err(|err| err.to_string())?;
+pub fn get_eternal_screen_path() -> Res<String> {
+    let runtime_path = &app_config::get().capture.runtime_path;
+    fs::create_dir_all(runtime_path).err_to(Error::Config)?;
 
-    let path = PathBuf::from(RUNTIME_PATH)
+    let path = PathBuf::from(runtime_path)
         .join(ETERNAL_SCREEN_FILE_NAME)
         .to_string_lossy()
         .to_string();
     Ok(path)
 }
 
-pub fn get_eternal_screen_processed_path() -> Result<String, String> {
-    fs::create_dir_all(RUNTIME_PATH).map_err(|err| err.to_string())?;
+pub fn get_eternal_screen_processed_path() -> Res<String> {
+    let runtime_path = &app_config::get().capture.runtime_path;
+    fs::create_dir_all(runtime_path).err_to(Error::Config)?;
 
-    let path = PathBuf::from(RUNTIME_PATH)
+    let path = PathBuf::from(runtime_path)
         .join(ETERNAL_SCREEN_PROCESSED_FILE_NAME)
         .to_string_lossy()
         .to_string();
     Ok(path)
 }
 
-fn capture_game_window_adb(output_path: &str) -> Result<(), String> {
+fn capture_game_window_adb(output_path: &str) -> Res<()> {
     let mut file = File::create(output_path)
-        .map_err(|err| format!("Failed to create output file: {}", err))?;
+        .map_err(|err| Error::Device(format!("Failed to create output file: {}", err)))?;
 
-    let mut child = Command::new(ANDROID_ADB_PATH)
+    let mut child = Command::new(&app_config::get().capture.adb_path)
         .arg("exec-out")
         .arg("screencap")
         .arg("-p")
         .stdout(Stdio::piped())
         .spawn()
-        .map_err(|err| format!("Capture game window from ADB failure: {}", err))?;
+        .map_err(|err| Error::Device(format!("Capture game window from ADB failure: {}", err)))?;
 
     if let Some(mut stdout) = child.stdout.take() {
-        std::io::copy(&mut stdout, &mut file)
-            .map_err(|err| format!("Failed to write screencap to output file: {}", err))?;
+        std::io::copy(&mut stdout, &mut file).map_err(|err| {
+            Error::Device(format!("Failed to write screencap to output file: {}", err))
+        })?;
     }
 
     let status = child
         .wait()
-        .map_err(|err| format!("Failed to wait for adb process: {}", err))?;
+        .map_err(|err| Error::Device(format!("Failed to wait for adb process: {}", err)))?;
 
     if status.success() {
         Ok(())
     } else {
-        Err("adb screencap failed".to_string())
+        Err(Error::Device("adb screencap failed".to_string()))
     }
 }
-// 2560 x 1600
-// lazy_static! {
-//     pub static ref CARD_POSITIONS: Vec<ScreenRect> = vec![
-//         ScreenRect::new( 438, 367, 239, 23),
-//         ScreenRect::new( 769, 367, 239, 23),
-//         ScreenRect::new(1100, 367, 239, 23),
-//         ScreenRect::new(1430, 367, 239, 23),
-//         ScreenRect::new( 438, 836, 239, 23),
-//         ScreenRect::new( 769, 836, 239, 23),
-//         ScreenRect::new(1100, 836, 239, 23),
-//         ScreenRect::new(1430, 836, 239, 23),
-//         ScreenRect::new( 438,1303, 239, 23),
-//         ScreenRect::new( 769,1303, 239, 23),
-//         ScreenRect::new(1100,1303, 239, 23),
-//         ScreenRect::new(1430,1303, 239, 23),
-//     ];
-//     pub static ref DECK_POSITIONS: Vec<(ScreenRect, ScreenRect)> = vec![
-//     ];
-//     pub static ref PICK_NUM_POSITION: ScreenRect = ScreenRect::new(1419, 1495, 277, 43);
-// }
-
-// 1920 x 1080
+pub struct ScreenLayout {
+    pub cards: Vec<ScreenRect>,
+    pub deck: Vec<(ScreenRect, ScreenRect)>,
+    pub pick_num: ScreenRect,
+}
+
 lazy_static! {
-    pub static ref CARD_POSITIONS: Vec<ScreenRect> = vec![
-        ScreenRect::new(393, 250, 160, 14),
-        ScreenRect::new(615, 250, 160, 14),
-        ScreenRect::new(839, 250, 160, 14),
-        ScreenRect::new(1062, 250, 160, 14),
-        ScreenRect::new(393, 565, 160, 14),
-        ScreenRect::new(615, 565, 160, 14),
-        ScreenRect::new(839, 565, 160, 14),
-        ScreenRect::new(1062, 565, 160, 14),
-        ScreenRect::new(393, 880, 160, 14),
-        ScreenRect::new(615, 880, 160, 14),
-        ScreenRect::new(839, 880, 160, 14),
-        ScreenRect::new(1062, 880, 160, 14),
-    ];
-    pub static ref DECK_POSITIONS: Vec<(ScreenRect, ScreenRect)> = vec![];
-    pub static ref PICK_NUM_POSITION: ScreenRect = ScreenRect::new(1055, 1008, 185, 31);
+    static ref HD_LAYOUT: ScreenLayout = ScreenLayout {
+        cards: vec![
+            ScreenRect::new(393, 250, 160, 14),
+            ScreenRect::new(615, 250, 160, 14),
+            ScreenRect::new(839, 250, 160, 14),
+            ScreenRect::new(1062, 250, 160, 14),
+            ScreenRect::new(393, 565, 160, 14),
+            ScreenRect::new(615, 565, 160, 14),
+            ScreenRect::new(839, 565, 160, 14),
+            ScreenRect::new(1062, 565, 160, 14),
+            ScreenRect::new(393, 880, 160, 14),
+            ScreenRect::new(615, 880, 160, 14),
+            ScreenRect::new(839, 880, 160, 14),
+            ScreenRect::new(1062, 880, 160, 14),
+        ],
+        deck: vec![],
+        pick_num: ScreenRect::new(1055, 1008, 185, 31),
+    };
+    static ref WQXGA_LAYOUT: ScreenLayout = ScreenLayout {
+        cards: vec![
+            ScreenRect::new(438, 367, 239, 23),
+            ScreenRect::new(769, 367, 239, 23),
+            ScreenRect::new(1100, 367, 239, 23),
+            ScreenRect::new(1430, 367, 239, 23),
+            ScreenRect::new(438, 836, 239, 23),
+            ScreenRect::new(769, 836, 239, 23),
+            ScreenRect::new(1100, 836, 239, 23),
+            ScreenRect::new(1430, 836, 239, 23),
+            ScreenRect::new(438, 1303, 239, 23),
+            ScreenRect::new(769, 1303, 239, 23),
+            ScreenRect::new(1100, 1303, 239, 23),
+            ScreenRect::new(1430, 1303, 239, 23),
+        ],
+        deck: vec![],
+        pick_num: ScreenRect::new(1419, 1495, 277, 43),
+    };
+}
+
+pub fn screen_layout(profile: ScreenProfile) -> &'static ScreenLayout {
+    match profile {
+        ScreenProfile::Hd => &HD_LAYOUT,
+        ScreenProfile::Wqxga => &WQXGA_LAYOUT,
+    }
 }
 
 pub struct ScreenData {
@@ -137,6 +148,7 @@ pub struct CaptureOpt {
     pub with_tess_data: bool,
     pub ocr_pick: bool,
     pub ocr_deck: bool,
+    pub screen_profile: ScreenProfile,
 }
 
 impl CaptureOpt {
@@ -145,53 +157,61 @@ impl CaptureOpt {
             with_tess_data: true,
             ocr_pick: true,
             ocr_deck: true,
+            screen_profile: ScreenProfile::default(),
         }
     }
 }
 
-fn capture_raw_text_from_image(image_path: &str, opt: &CaptureOpt) -> Result<ScreenData, String> {
+fn capture_raw_text_from_image(image_path: &str, opt: &CaptureOpt) -> Res<ScreenData> {
     let tess_data = if opt.with_tess_data {
-        Some(TESS_DATA)
+        Some(app_config::get().capture.tess_data.as_str())
     } else {
         None
     };
 
-    let mut lt = LepTess::new(tess_data, "eng").expect("tesseract init failed");
-    lt.set_image(image_path).expect("set image failed");
+    let mut lt = LepTess::new(tess_data, "eng")
+        .map_err(|err| Error::Ocr(format!("Tesseract init failed: {}", err)))?;
+    lt.set_image(image_path)
+        .map_err(|err| Error::Ocr(format!("Unable to read {}: {}", image_path, err)))?;
 
     let mut captured_card_vec = Vec::new();
     let mut captured_deck_vec = Vec::new();
-    let (screen_width, screen_height) = lt.get_image_dimensions().unwrap();
+    let (screen_width, screen_height) = lt.get_image_dimensions().ok_or(Error::Ocr(format!(
+        "No image dimensions for {}",
+        image_path
+    )))?;
+
+    debug!(screen_width, screen_height, "Read screen dimensions");
 
-    dbg!((screen_width, screen_height));
+    let layout = screen_layout(opt.screen_profile);
 
     if opt.ocr_pick {
-        for rect in CARD_POSITIONS.iter() {
+        for rect in layout.cards.iter() {
             lt.set_rectangle(rect.x, rect.y, rect.width, rect.height);
-            let text = lt.get_utf8_text().expect("get text failed");
+            let text = lt.get_utf8_text().err_to(Error::Ocr)?;
             captured_card_vec.push(text);
         }
-        dbg!(&captured_card_vec);
+        debug!(?captured_card_vec, "Captured card texts");
     }
 
     lt.set_rectangle(
-        PICK_NUM_POSITION.x,
-        PICK_NUM_POSITION.y,
-        PICK_NUM_POSITION.width,
-        PICK_NUM_POSITION.height,
+        layout.pick_num.x,
+        layout.pick_num.y,
+        layout.pick_num.width,
+        layout.pick_num.height,
     );
-    let pic_number_text = lt.get_utf8_text().expect("get text failed");
-    dbg!(&pic_number_text);
+    let pic_number_text = lt.get_utf8_text().err_to(Error::Ocr)?;
+    debug!(pic_number_text, "Captured pick number text");
 
-    for rect in DECK_POSITIONS.iter() {
+    for rect in layout.deck.iter() {
         lt.set_rectangle(rect.0.x, rect.0.y, rect.0.width, rect.0.height);
-        let text = lt.get_utf8_text().expect("get text failed");
+        let text = lt.get_utf8_text().err_to(Error::Ocr)?;
 
         lt.set_rectangle(rect.1.x, rect.1.y, rect.1.width, rect.1.height);
-        let count = lt.get_utf8_text().expect("get text failed");
+        let count = lt.get_utf8_text().err_to(Error::Ocr)?;
         captured_deck_vec.push((text, count));
     }
-    dbg!(&captured_deck_vec);
+    debug!(?captured_deck_vec, "Captured deck texts");
 
     Ok(ScreenData {
         pick_num: pic_number_text,
@@ -200,44 +220,42 @@ fn capture_raw_text_from_image(image_path: &str, opt: &CaptureOpt) -> Result<Scr
     })
 }
 
-pub fn capture_raw_text_on_screen(opt: &CaptureOpt) -> Result<ScreenData, String> {
+pub fn capture_raw_text_on_screen(opt: &CaptureOpt) -> Res<ScreenData> {
     let screenshot_path = get_eternal_screen_path()?;
     let process_screenshot_path = get_eternal_screen_processed_path()?;
 
     capture_game_window_adb(&screenshot_path)?;
 
     super::ocr_engine::process_image(&screenshot_path, &process_screenshot_path)
-        .map_err(|err| err.to_string())?;
-    capture_raw_text_from_image(&process_screenshot_path, opt)
+        .err_to(Error::Ocr)?;
+    metrics::time_ocr(|| capture_raw_text_from_image(&process_screenshot_path, opt))
 }
 
-fn get_card_position(card_index: u8) -> (i32, i32) {
-    (
-        CARD_POSITIONS[card_index as usize].x,
-        CARD_POSITIONS[card_index as usize].y,
-    )
+fn get_card_position(card_index: u8, profile: ScreenProfile) -> (i32, i32) {
+    let rect = &screen_layout(profile).cards[card_index as usize];
+    (rect.x, rect.y)
 }
 
-pub fn select_card(card_index: u8) -> Result<(), String> {
-    let (x, y) = get_card_position(card_index);
+pub fn select_card(card_index: u8, profile: ScreenProfile) -> Res<()> {
+    let (x, y) = get_card_position(card_index, profile);
 
-    Command::new(ANDROID_ADB_PATH)
+    Command::new(&app_config::get().capture.adb_path)
         .arg("shell")
         .arg("input")
         .arg("tap")
         .arg(x.to_string())
         .arg(y.to_string())
         .output()
-        .map_err(|err| err.to_string())
+        .err_to(Error::Device)
         .map(|out| {
-            dbg!(&out);
+            debug!(?out, "Selected card");
         })
 }
 
 pub fn connect_eternal_screen() -> Res<()> {
-    let android_host = std::env::var(ANDROID_HOST_ENV_KEY).expect("Android host variable not set");
+    let android_host = &app_config::get().capture.android_host;
 
-    // Command::new(ANDROID_ADB_PATH)
+    // Command::new(&app_config::get().capture.adb_path)
     //     .arg("connect")
     //     .arg(android_host)
     //     .output()
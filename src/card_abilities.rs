@@ -0,0 +1,326 @@
+use std::str::FromStr;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use strum_macros::{Display, EnumString};
+
+lazy_static! {
+    // A line that opens with keywords, e.g. "<b>Flying</b>, <b>Aegis</b>" or "<b>Summon</b>: ..."
+    static ref KEYWORD_LINE_REGEX: Regex =
+        Regex::new(r"^((?:<b>[^<]+</b>(?:,\s*)?)+)\s*(?:$|[:.])").unwrap();
+    static ref BOLD_REGEX: Regex = Regex::new(r"<b>([^<]+)</b>").unwrap();
+    static ref DAMAGE_REGEX: Regex =
+        Regex::new(r"(?i)\bdeal (\d+) damage(?: to ([^.;]+))?").unwrap();
+    static ref KILL_REGEX: Regex = Regex::new(r"(?i)\bkill ([^.;]+)").unwrap();
+    static ref DRAW_REGEX: Regex =
+        Regex::new(r"(?i)\bdraw (a|an|one|two|three|four|five|\d+) cards?\b").unwrap();
+    static ref TOKEN_REGEX: Regex = Regex::new(
+        r"(?i)\bplay (a|an|one|two|three|four|five|\d+) (?:(\d+)/(\d+) )?<b>([^<]+)</b>"
+    )
+    .unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum Keyword {
+    Flying,
+    Overwhelm,
+    Quickdraw,
+    Aegis,
+    Deadly,
+    Lifesteal,
+    Charge,
+    Endurance,
+    Killer,
+    Warp,
+    Reckless,
+    Berserk,
+    Stealth,
+    Unblockable,
+    Regen,
+    Revenge,
+    Scout,
+    Echo,
+    Destiny,
+    Warcry,
+    Summon,
+    Ultimate,
+    Entomb,
+    Empower,
+    Infiltrate,
+    Spellcraft,
+    Amplify,
+    Tribute,
+    Valor,
+    Pledge,
+    Voidbound,
+    Onslaught,
+    Ambush,
+    Taunt,
+    #[strum(serialize = "Double Damage")]
+    DoubleDamage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalKind {
+    Kill,
+    Damage(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Removal {
+    pub kind: RemovalKind,
+    // Hits every unit, or every enemy unit, instead of a single target
+    pub sweeper: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenGeneration {
+    pub count: u32,
+    pub name: String,
+    pub stats: Option<(i32, i32)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CardAbilities {
+    pub keywords: Vec<Keyword>,
+    // Largest amount of direct damage, whatever the target
+    pub damage: Option<u32>,
+    pub removal: Option<Removal>,
+    pub draw: u32,
+    pub tokens: Vec<TokenGeneration>,
+}
+
+fn parse_count(text: &str) -> Option<u32> {
+    match text.to_lowercase().as_str() {
+        "a" | "an" | "one" => Some(1),
+        "two" => Some(2),
+        "three" => Some(3),
+        "four" => Some(4),
+        "five" => Some(5),
+        number => number.parse::<u32>().ok(),
+    }
+}
+
+// "Amplify 2" and "Spellcraft 3" carry an amount that is not part of the keyword
+fn parse_keyword(text: &str) -> Option<Keyword> {
+    let text = text.trim();
+    let name = text
+        .rsplit_once(' ')
+        .filter(|(_, amount)| amount.chars().all(|c| c.is_ascii_digit()))
+        .map_or(text, |(name, _)| name);
+    Keyword::from_str(name).ok()
+}
+
+// Only keywords that open a line belong to the card, the same words further in
+// are usually granted to other units
+fn parse_keywords(lines: &[&str]) -> Vec<Keyword> {
+    let mut keywords = Vec::new();
+    for line in lines.iter() {
+        let leading = match KEYWORD_LINE_REGEX.captures(line) {
+            Some(captures) => captures[1].to_string(),
+            None => continue,
+        };
+        for captures in BOLD_REGEX.captures_iter(&leading) {
+            if let Some(keyword) = parse_keyword(&captures[1]) {
+                if !keywords.contains(&keyword) {
+                    keywords.push(keyword);
+                }
+            }
+        }
+    }
+    keywords
+}
+
+fn targets_units(target: &str) -> bool {
+    // "Deal 2 damage." and "to an enemy" can hit a unit as well
+    let target = target.to_lowercase();
+    target.contains("unit") || !(target.contains("player") || target.contains("opponent"))
+}
+
+fn is_sweeper(target: &str) -> bool {
+    let target = target.to_lowercase();
+    target.starts_with("each") || target.starts_with("all")
+}
+
+fn best_removal(current: Option<Removal>, removal: Removal) -> Option<Removal> {
+    let rank = |removal: &Removal| match removal.kind {
+        RemovalKind::Kill => (u32::MAX, removal.sweeper),
+        RemovalKind::Damage(amount) => (amount, removal.sweeper),
+    };
+    match current {
+        Some(current) if rank(&current) >= rank(&removal) => Some(current),
+        _ => Some(removal),
+    }
+}
+
+pub fn parse_card_text(card_text: &str) -> CardAbilities {
+    let lines = card_text
+        .split(';')
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>();
+    let plain = BOLD_REGEX.replace_all(card_text, "$1").to_string();
+
+    let mut abilities = CardAbilities {
+        keywords: parse_keywords(&lines),
+        ..Default::default()
+    };
+
+    for captures in DAMAGE_REGEX.captures_iter(&plain) {
+        let amount = match captures[1].parse::<u32>() {
+            Ok(amount) => amount,
+            Err(_) => continue,
+        };
+        abilities.damage = abilities.damage.max(Some(amount));
+
+        let target = captures.get(2).map_or("", |target| target.as_str().trim());
+        if targets_units(target) {
+            abilities.removal = best_removal(
+                abilities.removal,
+                Removal {
+                    kind: RemovalKind::Damage(amount),
+                    sweeper: is_sweeper(target),
+                },
+            );
+        }
+    }
+
+    for captures in KILL_REGEX.captures_iter(&plain) {
+        let target = captures[1].trim();
+        if target.to_lowercase().contains("unit") {
+            abilities.removal = best_removal(
+                abilities.removal,
+                Removal {
+                    kind: RemovalKind::Kill,
+                    sweeper: is_sweeper(target),
+                },
+            );
+        }
+    }
+
+    abilities.draw = DRAW_REGEX
+        .captures_iter(&plain)
+        .filter_map(|captures| parse_count(&captures[1]))
+        .sum();
+
+    abilities.tokens = TOKEN_REGEX
+        .captures_iter(card_text)
+        .filter_map(|captures| {
+            let stats = match (captures.get(2), captures.get(3)) {
+                (Some(attack), Some(health)) => {
+                    Some((attack.as_str().parse().ok()?, health.as_str().parse().ok()?))
+                }
+                _ => None,
+            };
+            Some(TokenGeneration {
+                count: parse_count(&captures[1])?,
+                name: captures[4].trim().to_string(),
+                stats,
+            })
+        })
+        .collect();
+
+    abilities
+}
+
+impl CardAbilities {
+    pub fn has_keyword(&self, keyword: &Keyword) -> bool {
+        self.keywords.contains(keyword)
+    }
+
+    pub fn is_removal(&self) -> bool {
+        self.removal.is_some()
+    }
+
+    pub fn makes_tokens(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keywords() {
+        let abilities = parse_card_text(
+            "<b>Flying</b>, <b>Amplify 2</b>;<b>Summon</b>: Give a unit <b>Overwhelm</b>.",
+        );
+        assert_eq!(
+            abilities.keywords,
+            vec![Keyword::Flying, Keyword::Amplify, Keyword::Summon]
+        );
+        assert!(!abilities.has_keyword(&Keyword::Overwhelm));
+
+        let abilities = parse_card_text("<b>Double Damage</b>;<b>Scout</b>.");
+        assert_eq!(
+            abilities.keywords,
+            vec![Keyword::DoubleDamage, Keyword::Scout]
+        );
+
+        assert!(parse_card_text("<b>Silence</b> a unit.")
+            .keywords
+            .is_empty());
+        assert_eq!(parse_card_text("No card text"), CardAbilities::default());
+    }
+
+    #[test]
+    fn test_damage_and_removal() {
+        let torch = parse_card_text("Deal 2 damage to a unit or player.");
+        assert_eq!(torch.damage, Some(2));
+        assert_eq!(
+            torch.removal,
+            Some(Removal {
+                kind: RemovalKind::Damage(2),
+                sweeper: false
+            })
+        );
+
+        let face = parse_card_text("<b>Entomb</b>: Deal 3 damage to the enemy player.");
+        assert_eq!(face.damage, Some(3));
+        assert!(!face.is_removal());
+
+        let opponent = parse_card_text("Deal 3 damage to your opponent.");
+        assert_eq!(opponent.damage, Some(3));
+        assert!(!opponent.is_removal());
+        assert!(parse_card_text("Deal 1 damage to an opponent's unit.").is_removal());
+
+        let sweeper = parse_card_text("Deal 2 damage to each unit.");
+        assert!(sweeper.removal.unwrap().sweeper);
+
+        let kill =
+            parse_card_text("Kill an exhausted enemy unit and deal 2 damage to the enemy player.");
+        assert_eq!(kill.removal.unwrap().kind, RemovalKind::Kill);
+        assert_eq!(kill.damage, Some(2));
+
+        assert!(!parse_card_text("Kill an enemy attachment.").is_removal());
+        assert!(parse_card_text("Kill all units.").removal.unwrap().sweeper);
+    }
+
+    #[test]
+    fn test_draw_and_tokens() {
+        assert_eq!(parse_card_text("<b>Summon</b>: Draw a card.").draw, 1);
+        assert_eq!(
+            parse_card_text("Draw two cards.;<b>Empower</b>: draw a card.").draw,
+            3
+        );
+        assert_eq!(
+            parse_card_text("Draw a <b>Fire Sigil</b> from your deck.").draw,
+            0
+        );
+
+        let tokens = parse_card_text("<b>Entomb</b>: Play two 1/1 <b>Grenadin</b>.").tokens;
+        assert_eq!(
+            tokens,
+            vec![TokenGeneration {
+                count: 2,
+                name: "Grenadin".to_string(),
+                stats: Some((1, 1)),
+            }]
+        );
+        let infernus = parse_card_text("At the start of your turn, play an <b>Infernus</b>.");
+        assert_eq!(infernus.tokens[0].stats, None);
+        assert!(infernus.makes_tokens());
+    }
+}
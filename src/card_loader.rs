@@ -1,68 +1,485 @@
 use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::collections::{HashMap, HashSet};
 use std::io::{BufReader, BufRead};
 
+use itertools::Itertools;
+
+use crate::app_config;
 use crate::models::card::Card;
 use crate::models::card_rating::CardRating;
+use crate::opt::*;
 
-const CARD_DATA_PATH: &str = "./resource/eternal-cards.json";
+// Written next to the card data first so a failed update leaves it intact
+const CARD_DATA_TEMP_SUFFIX: &str = ".tmp";
 
-const CARD_RATING_PATH: &str = "./resource/card_rating.txt";
 const CARD_RATING_FORMAT: &str = "14.0";
 
-pub fn load_card_data() -> Vec<Card> {
-    let card_data = std::fs::read_to_string(CARD_DATA_PATH).expect("failed to read card data");
-    let cards: Vec<Card> = serde_json::from_str(&card_data).expect("failed to parse card data");
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardChange {
+    pub name: String,
+    pub fields: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardDataDiff {
+    pub added: Vec<String>,
+    pub changed: Vec<CardChange>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CardDataUpdate {
+    pub cards: HashMap<String, Card>,
+    pub diff: CardDataDiff,
+    // Rating entries whose name is no longer a card
+    pub stale_ratings: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+pub fn load_card_data() -> Res<Vec<Card>> {
+    let card_data_path = &app_config::get().resources.card_data_path;
+    let card_data = std::fs::read_to_string(card_data_path)
+        .map_err(|err| Error::Data(format!("Unable to read [{}]: {}", card_data_path, err)))?;
+
+    parse_card_data(&card_data)
+}
+
+// Checks an export against the `Card` schema, the error points at the offending line
+pub fn parse_card_data(card_data: &str) -> Res<Vec<Card>> {
+    let cards: Vec<Card> = serde_json::from_str(card_data)
+        .map_err(|err| Error::Data(format!("Card data does not match the card format: {}", err)))?;
+    if cards.is_empty() {
+        return Err(Error::Data("Card data has no cards".to_string()));
+    }
+
+    Ok(cards)
+}
+
+// Problems that do not stop the data from loading but are worth a look
+pub fn validate_cards(cards: &[Card]) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for (name, count) in cards.iter().map(|card| card.name.as_str()).counts() {
+        if name.trim().is_empty() {
+            warnings.push(format!("{} cards have no name", count));
+        } else if count > 1 {
+            warnings.push(format!(
+                "[{}] appears {} times, only the last is kept",
+                name, count
+            ));
+        }
+    }
+    for card in cards.iter() {
+        let unknown = card.influence.unknown();
+        if !unknown.is_empty() {
+            warnings.push(format!(
+                "[{}] has unknown influence {}",
+                card.name,
+                unknown.join(", ")
+            ));
+        }
+    }
+
+    warnings.sort();
+    warnings
+}
+
+fn changed_fields(old: &Card, new: &Card) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if old.cost != new.cost {
+        fields.push("cost");
+    }
+    if old.influence != new.influence {
+        fields.push("influence");
+    }
+    if (old.attack, old.health) != (new.attack, new.health) {
+        fields.push("stats");
+    }
+    if old.card_text != new.card_text {
+        fields.push("text");
+    }
+    if old.rarity != new.rarity {
+        fields.push("rarity");
+    }
+    if old.card_type.to_text() != new.card_type.to_text() {
+        fields.push("type");
+    }
+    if old.set_name != new.set_name || old.set_number != new.set_number {
+        fields.push("set");
+    }
+    if old.deck_buildable != new.deck_buildable {
+        fields.push("deck buildable");
+    }
+    fields
+}
+
+pub fn diff_cards(old: &HashMap<String, Card>, new: &HashMap<String, Card>) -> CardDataDiff {
+    let added = new
+        .keys()
+        .filter(|name| !old.contains_key(*name))
+        .cloned()
+        .sorted()
+        .collect();
+    let removed = old
+        .keys()
+        .filter(|name| !new.contains_key(*name))
+        .cloned()
+        .sorted()
+        .collect();
+    let changed = new
+        .iter()
+        .filter_map(|(name, card)| {
+            let fields = changed_fields(old.get(name)?, card);
+            if fields.is_empty() {
+                None
+            } else {
+                Some(CardChange {
+                    name: name.to_string(),
+                    fields,
+                })
+            }
+        })
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect();
+
+    CardDataDiff {
+        added,
+        changed,
+        removed,
+    }
+}
+
+pub fn stale_ratings(
+    cards: &HashMap<String, Card>,
+    ratings: &HashMap<String, String>,
+) -> Vec<String> {
+    ratings
+        .keys()
+        .filter(|name| !cards.contains_key(*name))
+        .cloned()
+        .sorted()
+        .collect()
+}
+
+// A file name inside the import directory. Anything that would leave it, through
+// a path or a symlink, is refused without saying whether it exists
+pub fn import_path(import_dir: &str, file_name: &str) -> Res<PathBuf> {
+    if import_dir.is_empty() {
+        return Err(Error::Config(
+            "resources.card_import_dir is not configured".to_string(),
+        ));
+    }
+    let refused = || Error::Data(format!("Unable to import [{}]", file_name));
+    if Path::new(file_name).file_name() != Some(file_name.as_ref()) {
+        return Err(refused());
+    }
+
+    let import_dir = Path::new(import_dir).canonicalize().err_to(Error::Config)?;
+    let path = import_dir
+        .join(file_name)
+        .canonicalize()
+        .map_err(|_| refused())?;
+    if path.parent() != Some(import_dir.as_path()) {
+        return Err(refused());
+    }
+
+    Ok(path)
+}
+
+// Validates an export and diffs it against the current data. The export only
+// replaces the bundled card data once it parsed, and the swap is a single rename
+pub fn update_card_data(
+    export_path: &Path,
+    ratings: &HashMap<String, String>,
+) -> Res<CardDataUpdate> {
+    let card_data = std::fs::read_to_string(export_path).map_err(|err| {
+        Error::Data(format!(
+            "Unable to read [{}]: {}",
+            export_path.display(),
+            err
+        ))
+    })?;
+    let new_cards = parse_card_data(&card_data)?;
+    let warnings = validate_cards(&new_cards);
+    let cards = cards_by_name(new_cards);
 
+    let card_data_path = &app_config::get().resources.card_data_path;
+    let old_cards = std::fs::read_to_string(card_data_path)
+        .err_to(Error::Data)
+        .and_then(|card_data| parse_card_data(&card_data))
+        .map(cards_by_name)
+        .unwrap_or_default();
+    let diff = diff_cards(&old_cards, &cards);
+
+    let temp_path = format!("{}{}", card_data_path, CARD_DATA_TEMP_SUFFIX);
+    std::fs::write(&temp_path, &card_data).err_to(Error::Data)?;
+    std::fs::rename(&temp_path, card_data_path).err_to(Error::Data)?;
+
+    Ok(CardDataUpdate {
+        stale_ratings: stale_ratings(&cards, ratings),
+        cards,
+        diff,
+        warnings,
+    })
+}
+
+impl CardDataUpdate {
+    pub fn to_text(&self, limit: usize) -> String {
+        let names = |names: &[String]| {
+            let mut text = names.iter().take(limit).join(", ");
+            if names.len() > limit {
+                text.push_str(&format!(" and {} more", names.len() - limit));
+            }
+            text
+        };
+
+        let mut lines = vec![format!(
+            "{} cards: {} added, {} changed, {} removed",
+            self.cards.len(),
+            self.diff.added.len(),
+            self.diff.changed.len(),
+            self.diff.removed.len()
+        )];
+        if !self.diff.added.is_empty() {
+            lines.push(format!("Added: {}", names(&self.diff.added)));
+        }
+        if !self.diff.changed.is_empty() {
+            let changed = self
+                .diff
+                .changed
+                .iter()
+                .map(|change| format!("{} ({})", change.name, change.fields.join(", ")))
+                .collect::<Vec<String>>();
+            lines.push(format!("Changed: {}", names(&changed)));
+        }
+        if !self.diff.removed.is_empty() {
+            lines.push(format!("Removed: {}", names(&self.diff.removed)));
+        }
+        if !self.stale_ratings.is_empty() {
+            lines.push(format!(
+                "Ratings without a card: {}",
+                names(&self.stale_ratings)
+            ));
+        }
+        for warning in self.warnings.iter().take(limit) {
+            lines.push(format!("Warning: {}", warning));
+        }
+        lines.join("\n")
+    }
+}
+
+fn cards_by_name(cards: Vec<Card>) -> HashMap<String, Card> {
     cards
+        .into_iter()
+        .map(|card| (card.name.to_string(), card))
+        .collect()
+}
+
+// Exact lookup of stored names, e.g. a decklist. An unknown name is an error
+// rather than a guess at the closest card
+pub fn cards_by_names(cards: &HashMap<String, Card>, names: &[String]) -> Res<Vec<Card>> {
+    names
+        .iter()
+        .map(|name| {
+            cards
+                .get(name)
+                .cloned()
+                .ok_or(Error::Data(format!("Card {} not found in data", name)))
+        })
+        .collect()
 }
 
-pub fn load_card_hashmap_by_name() -> HashMap<String, Card> {
-    let cards = load_card_data();
+pub fn load_card_hashmap_by_name() -> Res<HashMap<String, Card>> {
+    let cards = load_card_data()?;
     let mut card_hashmap = HashMap::new();
 
     for card in cards {
         card_hashmap.insert(card.name.clone(), card);
     }
 
-    card_hashmap
+    Ok(card_hashmap)
 }
 
-pub fn load_card_rating() -> HashMap<String, String> {
-    let mut card_ratings: HashMap<String, String> = HashMap::new();
+#[derive(Debug, Clone, PartialEq)]
+pub struct RatingEntry {
+    // Line in the rating file, the header is line 1
+    pub line: usize,
+    pub rating: String,
+    pub name: String,
+}
 
-    let file = File::open(CARD_RATING_PATH).unwrap();
+pub fn load_card_rating_entries() -> Res<Vec<RatingEntry>> {
+    let card_rating_path = &app_config::get().resources.card_rating_path;
+    let file = File::open(card_rating_path)
+        .map_err(|err| Error::Data(format!("Unable to read [{}]: {}", card_rating_path, err)))?;
     let reader = BufReader::new(file);
 
     let rating_remap: HashMap<&str, &str> =
         HashMap::from([("4 deliveries", "D+"), ("10 cylices", "D")]);
 
-    reader.lines().skip(1).for_each(|line| {
-        let line = line.unwrap();
+    let mut entries = Vec::new();
+    for (idx, line) in reader.lines().enumerate().skip(1) {
+        let line = line.err_to(Error::Data)?;
         let mut iter = line.split("\t");
-        let rating = iter.next().unwrap();
+        let rating = iter.next().unwrap_or_default();
 
         while let Some(name) = iter.next() {
             if name.is_empty() {
                 continue;
             }
             let rating = rating_remap.get(rating).unwrap_or(&rating);
-            card_ratings.insert(name.to_string(), rating.to_string());
+            entries.push(RatingEntry {
+                line: idx + 1,
+                rating: rating.to_string(),
+                name: name.to_string(),
+            });
         }
-    });
+    }
 
-    let all_ratings = card_ratings.values().collect::<HashSet<_>>();
+    Ok(entries)
+}
 
-    card_ratings
+pub fn load_card_rating() -> Res<HashMap<String, String>> {
+    // Later entries win, duplicates are reported by `rating_validation`
+    Ok(load_card_rating_entries()?
+        .into_iter()
+        .map(|entry| (entry.name, entry.rating))
+        .collect())
 }
 
-pub fn get_card_rating_list() -> Vec<CardRating> {
-    load_card_rating()
+pub fn get_card_rating_list() -> Res<Vec<CardRating>> {
+    Ok(load_card_rating()?
         .iter()
         .map(|(name, rating)| CardRating {
             format: CARD_RATING_FORMAT.to_string(),
             name: name.to_string(),
             rating: rating.to_string(),
         })
-        .collect()
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card_json(name: &str, cost: u32, card_text: &str) -> String {
+        format!(
+            r#"{{"SetNumber":1,"Name":"{}","CardText":"{}","Cost":{},"Influence":"{{F}}","Attack":1,"Health":1,"Rarity":"Common","Type":"Unit","ImageUrl":"","DetailsUrl":"","DeckBuildable":true,"SetName":"Test"}}"#,
+            name, card_text, cost
+        )
+    }
+
+    fn cards(json: &[String]) -> HashMap<String, Card> {
+        cards_by_name(parse_card_data(&format!("[{}]", json.join(","))).unwrap())
+    }
+
+    #[test]
+    fn test_import_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let import_dir = dir.path().join("import");
+        std::fs::create_dir(&import_dir).unwrap();
+        std::fs::write(import_dir.join("cards.json"), "[]").unwrap();
+        std::fs::write(dir.path().join("secret.json"), "[]").unwrap();
+        let import_dir = import_dir.to_str().unwrap();
+
+        assert_eq!(
+            import_path(import_dir, "cards.json").unwrap(),
+            Path::new(import_dir)
+                .canonicalize()
+                .unwrap()
+                .join("cards.json")
+        );
+        assert!(import_path("", "cards.json").is_err());
+        assert!(import_path(import_dir, "").is_err());
+        assert!(import_path(import_dir, "missing.json").is_err());
+        assert!(import_path(import_dir, "../secret.json").is_err());
+        assert!(import_path(import_dir, dir.path().join("secret.json").to_str().unwrap()).is_err());
+
+        // A missing file is refused like any other, without the os error
+        assert!(import_path(import_dir, "missing.json")
+            .unwrap_err()
+            .to_string()
+            .ends_with("Unable to import [missing.json]"));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(
+                dir.path().join("secret.json"),
+                Path::new(import_dir).join("link.json"),
+            )
+            .unwrap();
+            assert!(import_path(import_dir, "link.json").is_err());
+        }
+    }
+
+    #[test]
+    fn test_cards_by_names() {
+        let cards = cards(&[card_json("Torch", 1, ""), card_json("Torch Bearer", 2, "")]);
+        let names = vec!["Torch".to_string(), "Torch".to_string()];
+
+        let found = cards_by_names(&cards, &names).unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|card| card.name == "Torch"));
+        assert!(cards_by_names(&cards, &["Torc".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_card_data() {
+        assert!(parse_card_data(&format!("[{}]", card_json("Torch", 1, ""))).is_ok());
+        assert!(parse_card_data("[]").is_err());
+        assert!(parse_card_data(r#"[{"Name":"Torch"}]"#).is_err());
+        assert!(parse_card_data(r#"[{"Name":"#).is_err());
+    }
+
+    #[test]
+    fn test_diff_cards() {
+        let old = cards(&[
+            card_json("Torch", 1, "Deal 2 damage."),
+            card_json("Oni Ronin", 2, ""),
+            card_json("Harsh Rule", 9, ""),
+        ]);
+        let new = cards(&[
+            card_json("Torch", 1, "Deal 3 damage."),
+            card_json("Oni Ronin", 2, ""),
+            card_json("Vara", 4, ""),
+        ]);
+
+        let diff = diff_cards(&old, &new);
+        assert_eq!(diff.added, vec!["Vara"]);
+        assert_eq!(diff.removed, vec!["Harsh Rule"]);
+        assert_eq!(
+            diff.changed,
+            vec![CardChange {
+                name: "Torch".to_string(),
+                fields: vec!["text"],
+            }]
+        );
+
+        let ratings = HashMap::from([
+            ("Torch".to_string(), "B".to_string()),
+            ("Harsh Rule".to_string(), "A".to_string()),
+        ]);
+        assert_eq!(stale_ratings(&new, &ratings), vec!["Harsh Rule"]);
+    }
+
+    #[test]
+    fn test_validate_cards() {
+        let json = format!(
+            "[{},{}]",
+            card_json("Torch", 1, ""),
+            card_json("Torch", 2, "")
+        );
+        let warnings = validate_cards(&parse_card_data(&json).unwrap());
+        assert_eq!(
+            warnings,
+            vec!["[Torch] appears 2 times, only the last is kept"]
+        );
+    }
+
+    #[test]
+    fn test_bundled_card_data() {
+        let cards = load_card_data().unwrap();
+        assert!(validate_cards(&cards).is_empty());
+    }
 }
@@ -1,7 +1,9 @@
 use std::collections::HashSet;
 
 use indicium::simple::SearchIndex;
+use itertools::Itertools;
 use strsim::levenshtein;
+use tracing::debug;
 
 fn preprocess_text(text: &str) -> String {
     text.chars()
@@ -25,7 +27,7 @@ fn correct_word(word: &str, dictionary: &HashSet<String>) -> String {
 fn find_card(text: &str, card_index: &SearchIndex<String>) -> Option<String> {
     let find_result = card_index.search(&text);
 
-    dbg!(&find_result);
+    debug!(?find_result, "Searched card index");
 
     match find_result.len() {
         multiple if multiple > 1 => {
@@ -52,7 +54,7 @@ pub fn find_card_name_matches(
         .filter_map(|text| {
             let text = preprocess_text(text);
 
-            dbg!(&text);
+            debug!(text, "Matching card text");
 
             find_card(&text, card_index).or_else(|| {
                 let corrected_text: Vec<String> = text
@@ -62,7 +64,7 @@ pub fn find_card_name_matches(
 
                 let corrected_text = corrected_text.join(" ");
 
-                dbg!(&corrected_text);
+                debug!(corrected_text, "Matching corrected card text");
 
                 find_card(corrected_text.as_str(), card_index)
             })
@@ -70,6 +72,24 @@ pub fn find_card_name_matches(
         .collect::<Vec<String>>()
 }
 
+// Closest names by edit distance, for text that is not close enough to match a card
+pub fn suggest_card_names<'a>(
+    text: &str,
+    card_names: impl Iterator<Item = &'a String>,
+    limit: usize,
+) -> Vec<String> {
+    let text = text.to_lowercase();
+    let max_distance = (text.len() / 3).max(2);
+
+    card_names
+        .map(|name| (levenshtein(&text, &name.to_lowercase()), name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .sorted_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(b.1)))
+        .take(limit)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
 mod test {
     use super::*;
 
@@ -106,4 +126,23 @@ mod test {
             vec![ALPHA, OMEGA]
         );
     }
+
+    #[test]
+    fn test_suggest_card_names() {
+        let card_names = vec![
+            "Torch".to_string(),
+            "Touch of Death".to_string(),
+            "Harsh Rule".to_string(),
+        ];
+
+        assert_eq!(
+            suggest_card_names("Trch", card_names.iter(), 3),
+            vec!["Torch"]
+        );
+        assert_eq!(
+            suggest_card_names("harsh rules", card_names.iter(), 3),
+            vec!["Harsh Rule"]
+        );
+        assert!(suggest_card_names("Vara", card_names.iter(), 3).is_empty());
+    }
 }
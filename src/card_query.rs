@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::card_abilities::{CardAbilities, Keyword};
+use crate::models::card::*;
+use crate::models::card_rating::grade_score;
+use crate::opt::*;
+
+lazy_static! {
+    static ref FILTER_REGEX: Regex =
+        Regex::new(r"^(?i)(cost|attack|health|influence|type|set|rarity|rating|text|keyword|has|name)(<=|>=|!=|<|>|=|:)(.+)$")
+            .unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Has,
+}
+
+impl CompareOp {
+    fn parse(s: &str) -> Self {
+        match s {
+            "=" => CompareOp::Eq,
+            "!=" => CompareOp::Ne,
+            "<" => CompareOp::Lt,
+            "<=" => CompareOp::Le,
+            ">" => CompareOp::Gt,
+            ">=" => CompareOp::Ge,
+            _ => CompareOp::Has,
+        }
+    }
+
+    fn compare<T: PartialOrd>(&self, left: T, right: T) -> bool {
+        match self {
+            CompareOp::Eq | CompareOp::Has => left == right,
+            CompareOp::Ne => left != right,
+            CompareOp::Lt => left < right,
+            CompareOp::Le => left <= right,
+            CompareOp::Gt => left > right,
+            CompareOp::Ge => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ability {
+    Removal,
+    Sweeper,
+    Damage,
+    Draw,
+    Tokens,
+}
+
+impl Ability {
+    fn parse(value: &str) -> Res<Self> {
+        let ability = match value.to_lowercase().as_str() {
+            "removal" => Ability::Removal,
+            "sweeper" => Ability::Sweeper,
+            "damage" => Ability::Damage,
+            "draw" => Ability::Draw,
+            "tokens" | "token" => Ability::Tokens,
+            _ => return Err(Error::Data(format!("Unknown ability [{}]", value))),
+        };
+        Ok(ability)
+    }
+
+    fn matches(&self, abilities: &CardAbilities) -> bool {
+        match self {
+            Ability::Removal => abilities.is_removal(),
+            Ability::Sweeper => abilities.removal.map_or(false, |removal| removal.sweeper),
+            Ability::Damage => abilities.damage.is_some(),
+            Ability::Draw => abilities.draw > 0,
+            Ability::Tokens => abilities.makes_tokens(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CardFilter {
+    Cost(CompareOp, u32),
+    Attack(CompareOp, i32),
+    Health(CompareOp, i32),
+    Influence(CompareOp, Vec<Influence>),
+    Type(CardTypeEnum),
+    Fast,
+    Set(String),
+    Rarity(CompareOp, CardRarity),
+    Rating(CompareOp, i32),
+    Text(String),
+    Keyword(Keyword),
+    Has(Ability),
+    Name(String),
+}
+
+impl CardFilter {
+    fn matches(&self, card: &Card, rating: Option<&String>) -> bool {
+        match self {
+            CardFilter::Cost(op, cost) => op.compare(card.cost, *cost),
+            CardFilter::Attack(op, attack) => op.compare(card.attack, *attack),
+            CardFilter::Health(op, health) => op.compare(card.health, *health),
+            CardFilter::Influence(op, influences) => {
+                let card_influences = card.influence.influences();
+                let within = card_influences.iter().all(|i| influences.contains(i));
+                match op {
+                    CompareOp::Eq => {
+                        within && influences.iter().all(|i| card_influences.contains(i))
+                    }
+                    CompareOp::Ne => !within,
+                    _ => within,
+                }
+            }
+            CardFilter::Type(card_type) => card.card_type.is(card_type),
+            CardFilter::Fast => card.card_type.is_fast(),
+            CardFilter::Set(set_name) => card.set_name.to_lowercase().contains(set_name),
+            CardFilter::Rarity(CompareOp::Eq | CompareOp::Has, rarity) => card.rarity == *rarity,
+            CardFilter::Rarity(CompareOp::Ne, rarity) => card.rarity != *rarity,
+            CardFilter::Rarity(op, rarity) => match (card.rarity.rank(), rarity.rank()) {
+                (Some(card_rank), Some(rank)) => op.compare(card_rank, rank),
+                _ => false,
+            },
+            CardFilter::Rating(op, score) => rating
+                .and_then(|rating| grade_score(rating))
+                .map_or(false, |card_score| op.compare(card_score, *score)),
+            CardFilter::Text(text) => card.plain_text().to_lowercase().contains(text),
+            CardFilter::Keyword(keyword) => card.abilities().has_keyword(keyword),
+            CardFilter::Has(ability) => ability.matches(card.abilities()),
+            CardFilter::Name(name) => card.name.to_lowercase().contains(name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CardQuery {
+    filters: Vec<CardFilter>,
+}
+
+impl CardQuery {
+    pub fn is_filter_query(input: &str) -> bool {
+        shlex::split(input)
+            .unwrap_or_default()
+            .iter()
+            .any(|token| FILTER_REGEX.is_match(token))
+    }
+
+    pub fn parse(input: &str) -> Res<CardQuery> {
+        let tokens =
+            shlex::split(input).ok_or(Error::Data(format!("Unable to parse query [{}]", input)))?;
+
+        let mut filters = Vec::new();
+        for token in tokens.iter() {
+            let captures = match FILTER_REGEX.captures(token) {
+                Some(captures) => captures,
+                None => {
+                    filters.push(CardFilter::Name(token.to_lowercase()));
+                    continue;
+                }
+            };
+
+            let key = captures[1].to_lowercase();
+            let op = CompareOp::parse(&captures[2]);
+            let value = captures[3].trim();
+
+            let filter = match key.as_str() {
+                "cost" => CardFilter::Cost(op, parse_number(&key, value)?),
+                "attack" => CardFilter::Attack(op, parse_number(&key, value)?),
+                "health" => CardFilter::Health(op, parse_number(&key, value)?),
+                "influence" => CardFilter::Influence(
+                    op,
+                    value
+                        .chars()
+                        .map(|c| {
+                            Influence::from_str(&c.to_ascii_uppercase().to_string())
+                                .map_err(|_| Error::Data(format!("Unknown influence [{}]", c)))
+                        })
+                        .collect::<Res<Vec<Influence>>>()?,
+                ),
+                "type" if value.eq_ignore_ascii_case("fast") => CardFilter::Fast,
+                "type" => CardFilter::Type(
+                    CardTypeEnum::from_str(value)
+                        .map_err(|_| Error::Data(format!("Unknown card type [{}]", value)))?,
+                ),
+                "set" => CardFilter::Set(value.to_lowercase()),
+                "rarity" => CardFilter::Rarity(op.clone(), parse_rarity(&op, value)?),
+                "rating" => CardFilter::Rating(
+                    op,
+                    grade_score(value).ok_or(Error::Data(format!("Unknown rating [{}]", value)))?,
+                ),
+                "text" => CardFilter::Text(value.to_lowercase()),
+                "keyword" => CardFilter::Keyword(
+                    Keyword::from_str(value)
+                        .map_err(|_| Error::Data(format!("Unknown keyword [{}]", value)))?,
+                ),
+                "has" => CardFilter::Has(Ability::parse(value)?),
+                _ => CardFilter::Name(value.to_lowercase()),
+            };
+            filters.push(filter);
+        }
+
+        Ok(CardQuery { filters })
+    }
+
+    pub fn matches(&self, card: &Card, rating: Option<&String>) -> bool {
+        self.filters
+            .iter()
+            .all(|filter| filter.matches(card, rating))
+    }
+
+    pub fn run<'a>(
+        &self,
+        cards: impl Iterator<Item = &'a Card>,
+        ratings: &HashMap<String, String>,
+    ) -> Vec<&'a Card> {
+        let mut found: Vec<&Card> = cards
+            .filter(|card| card.deck_buildable)
+            .filter(|card| self.matches(card, ratings.get(&card.name)))
+            .collect();
+        found.sort_by(|a, b| a.cost.cmp(&b.cost).then(a.name.cmp(&b.name)));
+        found
+    }
+}
+
+fn parse_number<T: FromStr>(key: &str, value: &str) -> Res<T> {
+    value
+        .parse::<T>()
+        .map_err(|_| Error::Data(format!("Expected a number for [{}], got [{}]", key, value)))
+}
+
+fn parse_rarity(op: &CompareOp, value: &str) -> Res<CardRarity> {
+    let rarity = match value.to_lowercase().as_str() {
+        "l" | "legendary" => CardRarity::Legendary,
+        "r" | "rare" => CardRarity::Rare,
+        "u" | "uncommon" => CardRarity::Uncommon,
+        "c" | "common" => CardRarity::Common,
+        "p" | "promo" => CardRarity::Promo,
+        _ => return Err(Error::Data(format!("Unknown rarity [{}]", value))),
+    };
+    let is_range = !matches!(op, CompareOp::Eq | CompareOp::Ne | CompareOp::Has);
+    if is_range && rarity.rank().is_none() {
+        return Err(Error::Data(format!(
+            "Rarity [{}] can only be compared with = or !=",
+            value
+        )));
+    }
+    Ok(rarity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_cards::test_card;
+
+    fn test_cards() -> Vec<Card> {
+        vec![
+            test_card("Torch")
+                .text("Deal 2 damage to a unit or player.")
+                .cost(1)
+                .stats(0, 0)
+                .card_type("Fast Spell")
+                .set_name("The Empty Throne")
+                .build(),
+            test_card("Rakano Outlaw")
+                .text("<b>Berserk</b>;<b>Summon</b>: Draw a card.")
+                .cost(3)
+                .influence("{F}{J}")
+                .stats(3, 3)
+                .rarity("Uncommon")
+                .set_name("The Empty Throne")
+                .build(),
+            test_card("Vara, Fiery Dancer")
+                .text("<b>Quickdraw</b>")
+                .cost(4)
+                .influence("{F}{F}{J}")
+                .stats(4, 4)
+                .rarity("Legendary")
+                .set_name("The Fall of Argenport")
+                .build(),
+            test_card("Sigil")
+                .cost(0)
+                .influence("")
+                .stats(0, 0)
+                .rarity("None")
+                .card_type("Power")
+                .deck_buildable(false)
+                .set_name("The Fall of Argenport")
+                .build(),
+        ]
+    }
+
+    fn run_query(query: &str) -> Vec<String> {
+        let cards = test_cards();
+        let ratings = HashMap::from([
+            ("Torch".to_string(), "B+".to_string()),
+            ("Rakano Outlaw".to_string(), "C".to_string()),
+        ]);
+        CardQuery::parse(query)
+            .unwrap()
+            .run(cards.iter(), &ratings)
+            .iter()
+            .map(|card| card.name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_is_filter_query() {
+        assert!(CardQuery::is_filter_query("cost<=3"));
+        assert!(CardQuery::is_filter_query("torch type:spell"));
+        assert!(!CardQuery::is_filter_query("Vara, Fiery Dancer"));
+    }
+
+    #[test]
+    fn test_numeric_filters() {
+        assert_eq!(run_query("cost<=3"), vec!["Torch", "Rakano Outlaw"]);
+        assert_eq!(run_query("cost>3"), vec!["Vara, Fiery Dancer"]);
+        assert_eq!(run_query("attack=3"), vec!["Rakano Outlaw"]);
+    }
+
+    #[test]
+    fn test_influence_filter() {
+        assert_eq!(run_query("influence:F"), vec!["Torch"]);
+        assert_eq!(
+            run_query("influence:fj"),
+            vec!["Torch", "Rakano Outlaw", "Vara, Fiery Dancer"]
+        );
+        assert_eq!(
+            run_query("influence=FJ"),
+            vec!["Rakano Outlaw", "Vara, Fiery Dancer"]
+        );
+    }
+
+    #[test]
+    fn test_type_set_and_rarity_filters() {
+        assert_eq!(
+            run_query("type:unit"),
+            vec!["Rakano Outlaw", "Vara, Fiery Dancer"]
+        );
+        assert_eq!(run_query("type:fast"), vec!["Torch"]);
+        assert_eq!(
+            run_query(r#"set:"fall of argenport""#),
+            vec!["Vara, Fiery Dancer"]
+        );
+        assert_eq!(
+            run_query("rarity>=U"),
+            vec!["Rakano Outlaw", "Vara, Fiery Dancer"]
+        );
+    }
+
+    #[test]
+    fn test_rarity_range_skips_promo() {
+        let mut cards = test_cards();
+        cards[0].rarity = CardRarity::Promo;
+        let ratings = HashMap::new();
+        let names = |query: &str| {
+            CardQuery::parse(query)
+                .unwrap()
+                .run(cards.iter(), &ratings)
+                .iter()
+                .map(|card| card.name.to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(names("rarity<=C"), Vec::<String>::new());
+        assert_eq!(names("rarity<=U"), vec!["Rakano Outlaw"]);
+        assert_eq!(names("rarity:P"), vec!["Torch"]);
+        assert!(CardQuery::parse("rarity<=P").is_err());
+    }
+
+    #[test]
+    fn test_rating_and_text_filters() {
+        assert_eq!(run_query("rating>=B"), vec!["Torch"]);
+        assert_eq!(run_query("rating<B"), vec!["Rakano Outlaw"]);
+        assert_eq!(run_query("text:damage"), vec!["Torch"]);
+        assert_eq!(run_query("outlaw cost:3"), vec!["Rakano Outlaw"]);
+    }
+
+    #[test]
+    fn test_ability_filters() {
+        assert_eq!(run_query("keyword:berserk"), vec!["Rakano Outlaw"]);
+        assert_eq!(run_query("keyword:quickdraw"), vec!["Vara, Fiery Dancer"]);
+        assert_eq!(run_query("has:removal"), vec!["Torch"]);
+        assert_eq!(run_query("has:draw cost<=3"), vec!["Rakano Outlaw"]);
+        assert!(run_query("has:tokens").is_empty());
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        assert!(CardQuery::parse("cost<=abc").is_err());
+        assert!(CardQuery::parse("type:dragon").is_err());
+        assert!(CardQuery::parse("rating>=Z").is_err());
+        assert!(CardQuery::parse("keyword:teleport").is_err());
+        assert!(CardQuery::parse("has:everything").is_err());
+    }
+}
@@ -0,0 +1,418 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::app_config::{self, ConfigMode};
+use crate::card_loader;
+use crate::card_query::CardQuery;
+use crate::db_access;
+use crate::deck_export::{self, DeckExportFormat};
+use crate::draft_replay::{self, DraftReplay};
+use crate::draft_signals;
+use crate::opt::*;
+use crate::pool_analysis;
+use crate::rating_validation;
+
+#[derive(Debug, Parser)]
+#[command(name = "draft-claw", about = "Eternal draft companion and Discord bot")]
+pub struct Cli {
+    /// Config file to use instead of config.toml in the config directory
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Override a config value, e.g. --set db.host=example.com
+    #[arg(
+        long = "set",
+        global = true,
+        value_name = "KEY=VALUE",
+        value_parser = app_config::parse_override
+    )]
+    pub overrides: Vec<(String, String)>,
+
+    /// Runs the bot when it is compiled in, the capture otherwise
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum Command {
+    /// Run the Discord bot
+    Bot,
+    /// Capture draft picks from the game screen
+    Capture,
+    /// Show the picks of a recorded draft
+    Replay {
+        game_id: String,
+        /// Show the cards of a single pick instead of the summary
+        #[arg(long)]
+        pick: Option<u8>,
+    },
+    /// Manage the card rating table
+    #[command(subcommand)]
+    Ratings(RatingsCommand),
+    /// Look up cards
+    #[command(subcommand)]
+    Cards(CardsCommand),
+    /// Inspect recorded games
+    #[command(subcommand)]
+    Game(GameCommand),
+    /// Export drafted cards
+    #[command(subcommand)]
+    Export(ExportCommand),
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum RatingsCommand {
+    /// Upload the rating table to the database
+    Import,
+    /// Check the rating table against the card data
+    Check,
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum CardsCommand {
+    /// Search by name or filters, e.g. cost<=2 influence:F has:removal
+    Search {
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum GameCommand {
+    /// Show a game with its picks, pool and signals
+    Show { game_id: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum ExportCommand {
+    /// Export the deck list of a game
+    Deck {
+        game_id: String,
+        /// eternal, text or json
+        #[arg(long, default_value = "eternal")]
+        format: DeckExportFormat,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration with secrets masked
+    Show,
+}
+
+impl Cli {
+    pub fn config_args(&self) -> app_config::ConfigArgs {
+        app_config::ConfigArgs {
+            path: self.config.clone(),
+            overrides: self.overrides.clone(),
+        }
+    }
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        if cfg!(feature = "bot") {
+            Command::Bot
+        } else {
+            Command::Capture
+        }
+    }
+}
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Bot => "bot",
+            Command::Capture => "capture",
+            Command::Replay { .. } => "replay",
+            Command::Ratings(RatingsCommand::Import) => "ratings import",
+            Command::Ratings(RatingsCommand::Check) => "ratings check",
+            Command::Cards(_) => "cards search",
+            Command::Game(_) => "game show",
+            Command::Export(_) => "export deck",
+            Command::Config(_) => "config show",
+        }
+    }
+
+    pub fn config_mode(&self) -> ConfigMode {
+        match self {
+            Command::Bot => ConfigMode::Bot,
+            Command::Capture => ConfigMode::Capture,
+            Command::Replay { .. }
+            | Command::Ratings(RatingsCommand::Import)
+            | Command::Game(_)
+            | Command::Export(_) => ConfigMode::Database,
+            Command::Ratings(RatingsCommand::Check) | Command::Cards(_) | Command::Config(_) => {
+                ConfigMode::Offline
+            }
+        }
+    }
+
+    // `config show` lists the issues itself, so a broken config can still be inspected
+    pub fn validates_config(&self) -> bool {
+        !matches!(self, Command::Config(_))
+    }
+
+    // Checked before anything is loaded so a missing feature is the only error shown
+    pub fn check_feature(&self) -> Res<()> {
+        let (feature, compiled) = match self {
+            Command::Bot => ("bot", cfg!(feature = "bot")),
+            Command::Capture => ("capture", cfg!(feature = "capture")),
+            _ => return Ok(()),
+        };
+        if compiled {
+            Ok(())
+        } else {
+            Err(Error::Data(format!(
+                "The {} command is not available in this build, rebuild with --features {}",
+                self.name(),
+                feature
+            )))
+        }
+    }
+}
+
+async fn replay(game_id: &str, pick: Option<u8>) -> Res<String> {
+    let replay = DraftReplay::new(game_id, db_access::get_draft_records(game_id).await?);
+    if replay.is_empty() {
+        return Err(Error::Data(format!(
+            "No picks recorded for game [{}]",
+            game_id
+        )));
+    }
+
+    match pick {
+        Some(pick_id) => {
+            let record = replay
+                .record(pick_id)
+                .ok_or(Error::Data(format!("Pick {} was not recorded", pick_id)))?;
+            Ok(draft_replay::pick_text(
+                record,
+                &card_loader::load_card_rating()?,
+            ))
+        }
+        None => Ok(replay.summary()),
+    }
+}
+
+async fn import_ratings() -> Res<String> {
+    let validation =
+        rating_validation::check_card_ratings(&card_loader::load_card_hashmap_by_name()?)?;
+
+    let card_ratings = card_loader::get_card_rating_list()?;
+    db_access::upsert_card_ratings(&card_ratings).await?;
+
+    let imported = format!("Imported {} card ratings", card_ratings.len());
+    if validation.is_ok() {
+        Ok(imported)
+    } else {
+        Ok(format!("{}\n{}", validation.to_text(), imported))
+    }
+}
+
+fn check_ratings() -> Res<String> {
+    let validation =
+        rating_validation::check_card_ratings(&card_loader::load_card_hashmap_by_name()?)?;
+    if validation.is_ok() {
+        Ok(validation.to_text())
+    } else {
+        Err(Error::Data(validation.to_text()))
+    }
+}
+
+pub fn search_cards(query: &str) -> Res<String> {
+    let cards = card_loader::load_card_hashmap_by_name()?;
+    let ratings = card_loader::load_card_rating()?;
+    let found = CardQuery::parse(query)?.run(cards.values(), &ratings);
+
+    let mut lines = found
+        .iter()
+        .map(|card| {
+            format!(
+                "{:>2} {:<6} [{:<2}] {}",
+                card.cost,
+                card.influence.to_text(),
+                ratings
+                    .get(&card.name)
+                    .map_or("NA", |rating| rating.as_str()),
+                card.name
+            )
+        })
+        .collect::<Vec<String>>();
+    lines.push(format!("{} cards found", found.len()));
+    Ok(lines.join("\n"))
+}
+
+async fn show_game(game_id: &str) -> Res<String> {
+    let game = db_access::get_draft_game(game_id)
+        .await?
+        .ok_or(Error::Data(format!("Game [{}] not found", game_id)))?;
+    let cards = card_loader::load_card_hashmap_by_name()?;
+    let ratings = card_loader::load_card_rating()?;
+
+    let mut lines = vec![
+        format!("Game {}", game.game_id),
+        format!("Started {}", game.time),
+        format!(
+            "Owner {}",
+            game.user_id.as_deref().unwrap_or("unregistered")
+        ),
+        format!("Vote mode {}", game.vote_mode),
+    ];
+    if let Some(thread_id) = game.thread_id.as_ref() {
+        lines.push(format!("Thread {}", thread_id));
+    }
+
+    let records = db_access::get_draft_records(game_id).await?;
+    if records.is_empty() {
+        lines.push("No picks recorded".to_string());
+        return Ok(lines.join("\n"));
+    }
+
+    lines.push(String::new());
+    lines.push(DraftReplay::new(game_id, records.clone()).summary());
+
+    let pool = card_loader::cards_by_names(&cards, &db_access::get_decklist(game_id).await?)?;
+    lines.push(String::new());
+    lines.push(pool_analysis::analyze_pool(&pool, &ratings).to_text());
+
+    lines.push(String::new());
+    lines.push(draft_signals::analyze_signals(&records, &cards, &ratings).to_text());
+
+    Ok(lines.join("\n"))
+}
+
+async fn export_deck(game_id: &str, format: &DeckExportFormat) -> Res<String> {
+    let cards = card_loader::load_card_hashmap_by_name()?;
+    let pool = card_loader::cards_by_names(&cards, &db_access::get_decklist(game_id).await?)?;
+    deck_export::export_deck(&pool, format)
+}
+
+fn show_config(config_args: &app_config::ConfigArgs) -> Res<String> {
+    let (path, _) = app_config::config_path(config_args)?;
+    let config = app_config::get();
+
+    let mut lines = vec![
+        format!("# Config file: {}", path.display()),
+        config.to_text(),
+    ];
+    for (name, mode) in [("bot", ConfigMode::Bot), ("capture", ConfigMode::Capture)] {
+        let issues = config.issues(mode);
+        if issues.is_empty() {
+            lines.push(format!("# {}: ok", name));
+        } else {
+            lines.push(format!("# {}: {}", name, issues.join(", ")));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+// Commands that run to completion and print their result, the bot and the
+// capture loop are started by main
+pub async fn run(command: &Command, config_args: &app_config::ConfigArgs) -> Res<String> {
+    match command {
+        Command::Bot | Command::Capture => {
+            command.check_feature()?;
+            Err(Error::Data(format!(
+                "The {} command is started by main",
+                command.name()
+            )))
+        }
+        Command::Replay { game_id, pick } => replay(game_id, *pick).await,
+        Command::Ratings(RatingsCommand::Import) => import_ratings().await,
+        Command::Ratings(RatingsCommand::Check) => check_ratings(),
+        Command::Cards(CardsCommand::Search { query }) => search_cards(&query.join(" ")),
+        Command::Game(GameCommand::Show { game_id }) => show_game(game_id).await,
+        Command::Export(ExportCommand::Deck { game_id, format }) => {
+            export_deck(game_id, format).await
+        }
+        Command::Config(ConfigCommand::Show) => show_config(config_args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("draft-claw").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse(&[]).unwrap().command, None);
+        assert_eq!(parse(&["bot"]).unwrap().command, Some(Command::Bot));
+        assert_eq!(
+            parse(&["replay", "abc", "--pick", "3"]).unwrap().command,
+            Some(Command::Replay {
+                game_id: "abc".to_string(),
+                pick: Some(3)
+            })
+        );
+        assert_eq!(
+            parse(&["cards", "search", "cost<=2", "has:removal"])
+                .unwrap()
+                .command,
+            Some(Command::Cards(CardsCommand::Search {
+                query: vec!["cost<=2".to_string(), "has:removal".to_string()]
+            }))
+        );
+        assert_eq!(
+            parse(&["export", "deck", "abc", "--format", "json"])
+                .unwrap()
+                .command,
+            Some(Command::Export(ExportCommand::Deck {
+                game_id: "abc".to_string(),
+                format: DeckExportFormat::Json
+            }))
+        );
+
+        assert!(parse(&["export", "deck", "abc", "--format", "mtga"]).is_err());
+        assert!(parse(&["cards", "search"]).is_err());
+        assert!(parse(&["ratings"]).is_err());
+    }
+
+    #[test]
+    fn test_global_config_args() {
+        let cli = parse(&["ratings", "check", "--set", "db.host=example.com"]).unwrap();
+        assert_eq!(cli.command, Some(Command::Ratings(RatingsCommand::Check)));
+        assert_eq!(
+            cli.config_args().overrides,
+            vec![("db.host".to_string(), "example.com".to_string())]
+        );
+        assert!(parse(&["--set", "db.host", "bot"]).is_err());
+    }
+
+    #[test]
+    fn test_feature_check() {
+        assert_eq!(Command::Bot.check_feature().is_ok(), cfg!(feature = "bot"));
+        assert_eq!(
+            Command::Capture.check_feature().is_ok(),
+            cfg!(feature = "capture")
+        );
+        assert!(Command::Config(ConfigCommand::Show).check_feature().is_ok());
+        assert_eq!(
+            Command::Cards(CardsCommand::Search { query: vec![] }).config_mode(),
+            ConfigMode::Offline
+        );
+        assert!(!Command::Config(ConfigCommand::Show).validates_config());
+        assert!(Command::Ratings(RatingsCommand::Check).validates_config());
+    }
+
+    #[test]
+    fn test_search_cards() {
+        let text = search_cards("name:torch").unwrap();
+        assert!(text.contains("Torch"));
+        assert!(search_cards("cost:abc").is_err());
+    }
+}
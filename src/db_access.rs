@@ -1,87 +1,169 @@
+use crate::app_config;
+use crate::metrics;
 use crate::models::card_rating::*;
 use crate::models::draft_data::*;
 use crate::models::draft_game::*;
+use crate::models::draft_result::*;
+use crate::models::guild_config::*;
 use crate::opt::*;
 
-use std::collections::HashMap;
-use std::env;
+use std::collections::{HashMap, HashSet};
 
 use surrealdb::Surreal;
 // use surrealdb::engine::remote::ws::{Wss, Client};
 use surrealdb::engine::remote::http::{Client, Https};
 use surrealdb::opt::auth::Root;
+use tracing::{debug, info};
 
 const DRAFT_RECORD_TABLE: &str = "draft_record";
 const CARD_RATING_TABLE: &str = "card_rating";
+const TEAM_RATING_TABLE: &str = "team_card_rating";
 const DRAFT_GAME_TABLE: &str = "draft_game";
 const DRAFT_VOTE_TABLE: &str = "draft_vote";
-
-fn log(s: String) {
-    log_if(s.as_str(), DbgFlg::Db);
-}
+const GUILD_CONFIG_TABLE: &str = "guild_config";
+const DRAFT_RESULT_TABLE: &str = "draft_result";
 
 async fn get_db() -> Res<Surreal<Client>> {
-    let host = env::var("SURREAL_DB_HOST").expect("SURREAL_DB_HOST not set");
-    let user = env::var("SURREAL_DB_USER").expect("SURREAL_DB_USER not set");
-    let pass = env::var("SURREAL_DB_PASS").expect("SURREAL_DB_PASS not set");
+    let config = &app_config::get().db;
+    if config.host.is_empty() {
+        return Err(Error::Db("db.host is not configured".to_string()));
+    }
 
-    log(format!("Connecting to {}", host));
-    let db = Surreal::new::<Https>(host).await.err_to_str()?;
-    db.use_ns("dc").use_db("dc").await.err_to_str()?;
+    debug!("Connecting to {}", config.host);
+    let db = Surreal::new::<Https>(config.host.as_str())
+        .await
+        .err_to(Error::Db)?;
+    db.use_ns("dc").use_db("dc").await.err_to(Error::Db)?;
 
     db.signin(Root {
-        username: &user,
-        password: &pass,
+        username: &config.user,
+        password: &config.pass,
     })
     .await
-    .err_to_str()?;
+    .err_to(Error::Db)?;
 
     Ok(db)
 }
 
 pub async fn upsert_draft_record(draft_record: &DraftRecord) -> Res<()> {
+    let call = metrics::db_call("upsert_draft_record");
     let db = get_db().await?;
 
     let db_record: DraftRecord = db
         .update((DRAFT_RECORD_TABLE, draft_record.get_id()))
         .content(draft_record)
         .await
-        .err_to_str()?;
+        .err_to(Error::Db)?;
 
-    log(format!("Upserted draft record: {:?}", db_record));
-    Ok(())
+    debug!("Upserted draft record: {:?}", db_record);
+    call.finish(Ok(()))
 }
 
 pub async fn get_last_draft_record(game_id: &str) -> Res<Option<DraftRecord>> {
+    let call = metrics::db_call("get_last_draft_record");
     let db = get_db().await?;
 
     let query = format!(
-        "SELECT * FROM {} WHERE game_id = '{}' ORDER BY pick.pick_id DESC LIMIT 1",
-        DRAFT_RECORD_TABLE, game_id
+        "SELECT * FROM {} WHERE game_id = $game_id ORDER BY pick.pick_id DESC LIMIT 1",
+        DRAFT_RECORD_TABLE
     );
     let mut result = db
         .query(query)
         .bind(("table", DRAFT_RECORD_TABLE))
+        .bind(("game_id", game_id.to_string()))
         .await
-        .err_to_str()?;
-    let result_item: Option<DraftRecord> = result.take(0).err_to_str()?;
+        .err_to(Error::Db)?;
+    let result_item: Option<DraftRecord> = result.take(0).err_to(Error::Db)?;
 
-    log(format!("Got last draft record: {:?}", result_item));
-    Ok(result_item)
+    debug!("Got last draft record: {:?}", result_item);
+    call.finish(Ok(result_item))
 }
 
 pub async fn get_draft_record(game_id: &str, pick: &DraftPick) -> Res<Option<DraftRecord>> {
+    let call = metrics::db_call("get_draft_record");
     let db = get_db().await?;
 
     let record: Option<DraftRecord> = db
         .select((DRAFT_RECORD_TABLE, DraftRecord::generate_id(game_id, pick)))
         .await
-        .err_to_str()?;
+        .err_to(Error::Db)?;
+
+    call.finish(Ok(record))
+}
+
+pub async fn get_draft_records(game_id: &str) -> Res<Vec<DraftRecord>> {
+    let call = metrics::db_call("get_draft_records");
+    let db = get_db().await?;
+
+    let query = format!(
+        "SELECT * FROM {} WHERE game_id = $game_id ORDER BY pick.pick_id ASC",
+        DRAFT_RECORD_TABLE
+    );
+    let mut result = db
+        .query(query)
+        .bind(("table", DRAFT_RECORD_TABLE))
+        .bind(("game_id", game_id.to_string()))
+        .await
+        .err_to(Error::Db)?;
+    let records: Vec<DraftRecord> = result.take(0).err_to(Error::Db)?;
+
+    debug!("Got {} draft records for {}", records.len(), game_id);
+    call.finish(Ok(records))
+}
+
+pub async fn get_taken_draft_records() -> Res<Vec<DraftRecord>> {
+    let call = metrics::db_call("get_taken_draft_records");
+    let db = get_db().await?;
+
+    let query = format!(
+        "SELECT * FROM {} WHERE selected_card != NONE",
+        DRAFT_RECORD_TABLE
+    );
+    let mut result = db
+        .query(query)
+        .bind(("table", DRAFT_RECORD_TABLE))
+        .await
+        .err_to(Error::Db)?;
+    let records: Vec<DraftRecord> = result.take(0).err_to(Error::Db)?;
+
+    debug!("Got {} taken draft records", records.len());
+    call.finish(Ok(records))
+}
+
+// Number of taken picks of each game, games without any are left out
+pub async fn get_taken_pick_counts(game_ids: &[String]) -> Res<HashMap<String, usize>> {
+    let call = metrics::db_call("get_taken_pick_counts");
+    let db = get_db().await?;
+
+    let query = format!(
+        "SELECT game_id, count() AS picks FROM {} \
+        WHERE game_id INSIDE $game_ids AND selected_card != NONE GROUP BY game_id",
+        DRAFT_RECORD_TABLE
+    );
+
+    #[derive(serde::Deserialize)]
+    struct PickCountQueryResult {
+        game_id: String,
+        picks: usize,
+    }
+
+    let result: Vec<PickCountQueryResult> = db
+        .query(query)
+        .bind(("game_ids", game_ids.to_vec()))
+        .await
+        .err_to(Error::Db)?
+        .take(0)
+        .err_to(Error::Db)?;
 
-    Ok(record)
+    debug!("Got taken pick counts of {} games", result.len());
+    call.finish(Ok(result
+        .into_iter()
+        .map(|count| (count.game_id, count.picks))
+        .collect()))
 }
 
 pub async fn insert_card_rating(card_ratings: &Vec<CardRating>) -> Res<()> {
+    let call = metrics::db_call("insert_card_rating");
     let db = get_db().await?;
 
     for rating in card_ratings {
@@ -89,120 +171,395 @@ pub async fn insert_card_rating(card_ratings: &Vec<CardRating>) -> Res<()> {
             .create((CARD_RATING_TABLE, rating.name.to_string()))
             .content(rating)
             .await
-            .err_to_str()?;
+            .err_to(Error::Db)?;
+    }
+
+    call.finish(Ok(()))
+}
+
+// One request for the whole list, every rating is a statement of the transaction
+async fn update_card_ratings(
+    db: &Surreal<Client>,
+    table: &str,
+    card_ratings: &[CardRating],
+) -> Res<()> {
+    let mut statements = vec!["BEGIN TRANSACTION;".to_string()];
+    for idx in 0..card_ratings.len() {
+        statements.push(format!(
+            "UPDATE type::thing($table, $ratings[{0}].name) CONTENT $ratings[{0}];",
+            idx
+        ));
     }
+    statements.push("COMMIT TRANSACTION;".to_string());
 
+    db.query(statements.join("\n"))
+        .bind(("table", table.to_string()))
+        .bind(("ratings", card_ratings))
+        .await
+        .err_to(Error::Db)?;
     Ok(())
 }
 
+pub async fn upsert_card_ratings(card_ratings: &[CardRating]) -> Res<()> {
+    let call = metrics::db_call("upsert_card_ratings");
+    let db = get_db().await?;
+
+    update_card_ratings(&db, CARD_RATING_TABLE, card_ratings).await?;
+
+    debug!("Upserted {} card ratings", card_ratings.len());
+    call.finish(Ok(()))
+}
+
+// Team ratings keep their own table, the published list is keyed by the card name alone
+pub async fn upsert_team_ratings(card_ratings: &[CardRating]) -> Res<()> {
+    let call = metrics::db_call("upsert_team_ratings");
+    let db = get_db().await?;
+
+    update_card_ratings(&db, TEAM_RATING_TABLE, card_ratings).await?;
+
+    debug!("Upserted {} team ratings", card_ratings.len());
+    call.finish(Ok(()))
+}
+
+pub async fn get_team_rating(name: &str) -> Res<Option<CardRating>> {
+    let call = metrics::db_call("get_team_rating");
+    let db = get_db().await?;
+
+    let rating: Option<CardRating> = db
+        .select((TEAM_RATING_TABLE, name.to_string()))
+        .await
+        .err_to(Error::Db)?;
+
+    debug!("Got team rating: {:?}", rating);
+    call.finish(Ok(rating))
+}
+
 pub async fn get_draft_game(game_id: &str) -> Res<Option<DraftGame>> {
+    let call = metrics::db_call("get_draft_game");
     let db = get_db().await?;
 
-    let draft_game = db.select((DRAFT_GAME_TABLE, game_id)).await.err_to_str()?;
+    let draft_game = db
+        .select((DRAFT_GAME_TABLE, game_id))
+        .await
+        .err_to(Error::Db)?;
 
-    log(format!("Got draft game: {:?}", draft_game));
-    Ok(draft_game)
+    debug!("Got draft game: {:?}", draft_game);
+    call.finish(Ok(draft_game))
 }
 
 pub async fn get_last_draft_game_by_user(user_id: &str) -> Res<Option<DraftGame>> {
+    let call = metrics::db_call("get_last_draft_game_by_user");
+    let db = get_db().await?;
+
+    let query = format!(
+        "SELECT * FROM {} WHERE user_id = $user_id ORDER BY time DESC LIMIT 1",
+        DRAFT_GAME_TABLE
+    );
+
+    let mut result = db
+        .query(query)
+        .bind(("table", DRAFT_GAME_TABLE))
+        .bind(("user_id", user_id.to_string()))
+        .await
+        .err_to(Error::Db)?;
+
+    let result_out = result.take(0).err_to(Error::Db);
+
+    debug!("Got last draft game: {:?}", result_out);
+    call.finish(result_out)
+}
+
+pub async fn get_draft_games_by_user(user_id: &str, limit: usize) -> Res<Vec<DraftGame>> {
+    let call = metrics::db_call("get_draft_games_by_user");
+    let db = get_db().await?;
+
+    let query = format!(
+        "SELECT * FROM {} WHERE user_id = $user_id AND simulated != true \
+        ORDER BY time DESC LIMIT {}",
+        DRAFT_GAME_TABLE, limit
+    );
+
+    let mut result = db
+        .query(query)
+        .bind(("table", DRAFT_GAME_TABLE))
+        .bind(("user_id", user_id.to_string()))
+        .await
+        .err_to(Error::Db)?;
+
+    let games: Vec<DraftGame> = result.take(0).err_to(Error::Db)?;
+
+    debug!("Got {} draft games for {}", games.len(), user_id);
+    call.finish(Ok(games))
+}
+
+pub async fn get_simulated_game_ids() -> Res<HashSet<String>> {
+    let call = metrics::db_call("get_simulated_game_ids");
+    let db = get_db().await?;
+
+    let query = format!(
+        "SELECT VALUE game_id FROM {} WHERE simulated = true",
+        DRAFT_GAME_TABLE
+    );
+    let game_ids: Vec<String> = db
+        .query(query)
+        .await
+        .err_to(Error::Db)?
+        .take(0)
+        .err_to(Error::Db)?;
+
+    debug!("Got {} simulated games", game_ids.len());
+    call.finish(Ok(game_ids.into_iter().collect()))
+}
+
+pub async fn get_draft_game_by_thread(thread_id: &str) -> Res<Option<DraftGame>> {
+    let call = metrics::db_call("get_draft_game_by_thread");
     let db = get_db().await?;
 
     let query = format!(
-        "SELECT * FROM {} WHERE user_id = '{}' ORDER BY time DESC LIMIT 1",
-        DRAFT_GAME_TABLE, user_id
+        "SELECT * FROM {} WHERE thread_id = $thread_id LIMIT 1",
+        DRAFT_GAME_TABLE
     );
 
     let mut result = db
         .query(query)
         .bind(("table", DRAFT_GAME_TABLE))
+        .bind(("thread_id", thread_id.to_string()))
         .await
-        .err_to_str()?;
+        .err_to(Error::Db)?;
 
-    let result_out = result.take(0).err_to_str();
+    let result_out = result.take(0).err_to(Error::Db);
 
-    log(format!("Got last draft game: {:?}", result_out));
-    result_out
+    debug!("Got draft game by thread: {:?}", result_out);
+    call.finish(result_out)
 }
 
 pub async fn upsert_draft_game(draft_game: &DraftGame) -> Res<()> {
+    let call = metrics::db_call("upsert_draft_game");
     let db = get_db().await?;
 
     let db_record: DraftGame = db
         .update((DRAFT_GAME_TABLE, draft_game.game_id.to_string()))
         .content(draft_game)
         .await
-        .err_to_str()?;
+        .err_to(Error::Db)?;
 
-    log(format!("Upserted draft game: {:?}", db_record));
-    Ok(())
+    debug!("Upserted draft game: {:?}", db_record);
+    call.finish(Ok(()))
 }
 
 pub async fn insert_draft_game(game_id: &str) -> Res<DraftGame> {
+    let call = metrics::db_call("insert_draft_game");
     let db = get_db().await?;
 
-    let draft_game: Option<DraftGame> =
-        db.select((DRAFT_GAME_TABLE, game_id)).await.err_to_str()?;
+    let draft_game: Option<DraftGame> = db
+        .select((DRAFT_GAME_TABLE, game_id))
+        .await
+        .err_to(Error::Db)?;
     match draft_game {
         Some(game) => {
-            log(format!("Found existing game: {:?}", game));
-            return Ok(game);
+            debug!("Found existing game: {:?}", game);
+            return call.finish(Ok(game));
         }
         None => {
-            let create_game_query = format!(
-                r#"
-                CREATE {}:{} CONTENT {{
-                    'game_id': '{}',
+            let create_game_query = r#"
+                CREATE type::thing($table, $game_id) CONTENT {
+                    'game_id': $game_id,
                     'time': time::now(),
-                }}"#,
-                DRAFT_GAME_TABLE, game_id, game_id
-            );
-            db.query(create_game_query).await.err_to_str()?;
-
-            let new_game = db.select((DRAFT_GAME_TABLE, game_id)).await.err_to_str()?;
-            return Ok(new_game);
+                }"#;
+            db.query(create_game_query)
+                .bind(("table", DRAFT_GAME_TABLE))
+                .bind(("game_id", game_id.to_string()))
+                .await
+                .err_to(Error::Db)?;
+
+            let new_game = db
+                .select((DRAFT_GAME_TABLE, game_id))
+                .await
+                .err_to(Error::Db)?;
+            return call.finish(Ok(new_game));
         }
     }
 }
 
 pub async fn upsert_draft_vote(draft_vote: &DraftVote) -> Res<()> {
+    let call = metrics::db_call("upsert_draft_vote");
     let db = get_db().await?;
 
     let db_record: DraftVote = db
         .update((DRAFT_VOTE_TABLE, draft_vote.get_id()))
         .content(draft_vote)
         .await
-        .err_to_str()?;
+        .err_to(Error::Db)?;
 
-    log(format!("Upserted draft vote: {:?}", db_record));
-    Ok(())
+    debug!("Upserted draft vote: {:?}", db_record);
+    call.finish(Ok(()))
 }
 
-pub async fn get_highest_voted_pick(game_id: &str, draft_pick: &DraftPick) -> Res<Option<u8>> {
-    let db = get_db().await.err_to_str()?;
+pub async fn get_draft_vote(
+    game_id: &str,
+    user_id: &str,
+    draft_pick: &DraftPick,
+) -> Res<Option<DraftVote>> {
+    let call = metrics::db_call("get_draft_vote");
+    let db = get_db().await?;
+
+    let id = vec![
+        game_id.to_string(),
+        user_id.to_string(),
+        draft_pick.pick_id.to_string(),
+    ];
+    let draft_vote: Option<DraftVote> =
+        db.select((DRAFT_VOTE_TABLE, id)).await.err_to(Error::Db)?;
+
+    debug!("Got draft vote: {:?}", draft_vote);
+    call.finish(Ok(draft_vote))
+}
+
+pub async fn get_draft_votes(game_id: &str, draft_pick: &DraftPick) -> Res<Vec<DraftVote>> {
+    let call = metrics::db_call("get_draft_votes");
+    let db = get_db().await?;
 
     let query = format!(
-        "SELECT vote_idx, count() FROM {} WHERE game_id = '{}' AND draft_pick.pick_id = {} GROUP BY vote_idx ORDER BY count DESC LIMIT 1",
-        DRAFT_VOTE_TABLE, game_id, draft_pick.pick_id
+        "SELECT * FROM {} WHERE game_id = $game_id AND draft_pick.pick_id = {}",
+        DRAFT_VOTE_TABLE, draft_pick.pick_id
     );
 
-    let result: Res<Option<u8>> = db
+    let result: Vec<DraftVote> = db
+        .query(query)
+        .bind(("table", DRAFT_VOTE_TABLE))
+        .bind(("game_id", game_id.to_string()))
+        .await
+        .err_to(Error::Db)?
+        .take(0)
+        .err_to(Error::Db)?;
+
+    debug!("Got {} draft votes", result.len());
+    call.finish(Ok(result))
+}
+
+pub async fn get_all_draft_votes() -> Res<Vec<DraftVote>> {
+    let call = metrics::db_call("get_all_draft_votes");
+    let db = get_db().await?;
+
+    let query = format!("SELECT * FROM {}", DRAFT_VOTE_TABLE);
+    let mut result = db
         .query(query)
         .bind(("table", DRAFT_VOTE_TABLE))
         .await
-        .err_to_str()?
-        .take("vote_idx")
-        .err_to_str();
+        .err_to(Error::Db)?;
+    let votes: Vec<DraftVote> = result.take(0).err_to(Error::Db)?;
+
+    debug!("Got {} draft votes", votes.len());
+    call.finish(Ok(votes))
+}
+
+pub async fn delete_draft_vote(draft_vote: &DraftVote) -> Res<()> {
+    let call = metrics::db_call("delete_draft_vote");
+    let db = get_db().await?;
+
+    let db_record: Option<DraftVote> = db
+        .delete((DRAFT_VOTE_TABLE, draft_vote.get_id()))
+        .await
+        .err_to(Error::Db)?;
+
+    debug!("Deleted draft vote: {:?}", db_record);
+    call.finish(Ok(()))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserMigration {
+    pub games: usize,
+    pub votes: usize,
+    // Votes already cast under the new id, the old vote is left where it is
+    pub skipped_votes: usize,
+}
+
+// Games and votes used to be keyed by the Discord display name. Move every record
+// of `old_user_id` over to `new_user_id`.
+pub async fn migrate_user_id(old_user_id: &str, new_user_id: &str) -> Res<UserMigration> {
+    let call = metrics::db_call("migrate_user_id");
+    let db = get_db().await?;
+
+    let query = format!("SELECT * FROM {} WHERE user_id = $old", DRAFT_VOTE_TABLE);
+    let votes: Vec<DraftVote> = db
+        .query(query)
+        .bind(("old", old_user_id.to_string()))
+        .await
+        .err_to(Error::Db)?
+        .take(0)
+        .err_to(Error::Db)?;
+
+    let mut migration = UserMigration::default();
+    let mut old_vote_ids = Vec::new();
+    let mut vote_ids = Vec::new();
+    let mut migrated_votes = Vec::new();
+    for vote in votes.iter() {
+        let mut migrated_vote = vote.clone();
+        migrated_vote.user_id = new_user_id.to_string();
+
+        let existing: Option<DraftVote> = db
+            .select((DRAFT_VOTE_TABLE, migrated_vote.get_id()))
+            .await
+            .err_to(Error::Db)?;
+        if existing.is_some() {
+            migration.skipped_votes += 1;
+            continue;
+        }
+
+        old_vote_ids.push(vote.get_id());
+        vote_ids.push(migrated_vote.get_id());
+        migrated_votes.push(migrated_vote);
+    }
+
+    // Games and votes move together, a failed statement leaves all of them with the old user
+    let mut statements = vec![
+        "BEGIN TRANSACTION;".to_string(),
+        format!(
+            "UPDATE {} SET user_id = $new WHERE user_id = $old;",
+            DRAFT_GAME_TABLE
+        ),
+    ];
+    for idx in 0..migrated_votes.len() {
+        statements.push(format!(
+            "UPDATE type::thing($vote_table, $vote_ids[{0}]) CONTENT $votes[{0}];",
+            idx
+        ));
+        statements.push(format!(
+            "DELETE type::thing($vote_table, $old_vote_ids[{}]);",
+            idx
+        ));
+    }
+    statements.push("COMMIT TRANSACTION;".to_string());
+
+    let games: Vec<DraftGame> = db
+        .query(statements.join("\n"))
+        .bind(("new", new_user_id.to_string()))
+        .bind(("old", old_user_id.to_string()))
+        .bind(("vote_table", DRAFT_VOTE_TABLE))
+        .bind(("votes", &migrated_votes))
+        .bind(("vote_ids", &vote_ids))
+        .bind(("old_vote_ids", &old_vote_ids))
+        .await
+        .err_to(Error::Db)?
+        .take(0)
+        .err_to(Error::Db)?;
+    migration.games = games.len();
+    migration.votes = migrated_votes.len();
 
-    log(format!("Got highest voted pick: {:?}", result));
-    result
+    info!(
+        "Migrated user {} to {}: {:?}",
+        old_user_id, new_user_id, migration
+    );
+    call.finish(Ok(migration))
 }
 
 pub async fn get_decklist(game_id: &str) -> Res<Vec<String>> {
+    let call = metrics::db_call("get_decklist");
     let db = get_db().await?;
 
     let query = format!(
-        "select selected_card, selection_vec from draft_record where game_id = '{}'",
-        game_id
+        "select selected_card, selection_vec from {} where game_id = $game_id",
+        DRAFT_RECORD_TABLE
     );
 
     #[derive(serde::Deserialize)]
@@ -214,10 +571,11 @@ pub async fn get_decklist(game_id: &str) -> Res<Vec<String>> {
     let result: Vec<DecklistQueryResult> = db
         .query(query)
         .bind(("table", DRAFT_RECORD_TABLE))
+        .bind(("game_id", game_id.to_string()))
         .await
-        .err_to_str()?
+        .err_to(Error::Db)?
         .take(0)
-        .err_to_str()?;
+        .err_to(Error::Db)?;
 
     let mapped_res = result
         .iter()
@@ -231,5 +589,75 @@ pub async fn get_decklist(game_id: &str) -> Res<Vec<String>> {
         })
         .collect();
 
-    Ok(mapped_res)
+    call.finish(Ok(mapped_res))
+}
+
+pub async fn get_guild_config(guild_id: &str) -> Res<Option<GuildConfig>> {
+    let call = metrics::db_call("get_guild_config");
+    let db = get_db().await?;
+
+    let guild_config = db
+        .select((GUILD_CONFIG_TABLE, guild_id))
+        .await
+        .err_to(Error::Db)?;
+
+    debug!("Got guild config: {:?}", guild_config);
+    call.finish(Ok(guild_config))
+}
+
+pub async fn upsert_guild_config(guild_config: &GuildConfig) -> Res<()> {
+    let call = metrics::db_call("upsert_guild_config");
+    let db = get_db().await?;
+
+    let db_record: GuildConfig = db
+        .update((GUILD_CONFIG_TABLE, guild_config.guild_id.to_string()))
+        .content(guild_config)
+        .await
+        .err_to(Error::Db)?;
+
+    debug!("Upserted guild config: {:?}", db_record);
+    call.finish(Ok(()))
+}
+
+pub async fn get_draft_result(game_id: &str) -> Res<Option<DraftResult>> {
+    let call = metrics::db_call("get_draft_result");
+    let db = get_db().await?;
+
+    let draft_result = db
+        .select((DRAFT_RESULT_TABLE, game_id))
+        .await
+        .err_to(Error::Db)?;
+
+    debug!("Got draft result: {:?}", draft_result);
+    call.finish(Ok(draft_result))
+}
+
+pub async fn get_draft_results() -> Res<Vec<DraftResult>> {
+    let call = metrics::db_call("get_draft_results");
+    let db = get_db().await?;
+
+    let query = format!("SELECT * FROM {}", DRAFT_RESULT_TABLE);
+    let mut result = db
+        .query(query)
+        .bind(("table", DRAFT_RESULT_TABLE))
+        .await
+        .err_to(Error::Db)?;
+    let results: Vec<DraftResult> = result.take(0).err_to(Error::Db)?;
+
+    debug!("Got {} draft results", results.len());
+    call.finish(Ok(results))
+}
+
+pub async fn upsert_draft_result(draft_result: &DraftResult) -> Res<()> {
+    let call = metrics::db_call("upsert_draft_result");
+    let db = get_db().await?;
+
+    let db_record: DraftResult = db
+        .update((DRAFT_RESULT_TABLE, draft_result.game_id.to_string()))
+        .content(draft_result)
+        .await
+        .err_to(Error::Db)?;
+
+    debug!("Upserted draft result: {:?}", db_record);
+    call.finish(Ok(()))
 }
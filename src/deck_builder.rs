@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::models::card::*;
+use crate::models::card_rating::{grade_score, UNRATED_GRADE};
+use crate::opt::*;
+
+pub const DECK_SIZE: usize = 45;
+pub const NON_POWER_TARGET: usize = 28;
+
+// A third faction has to be worth more than this many grade steps over the best pair
+const THIRD_FACTION_PENALTY: i32 = 15;
+const FIXING_BONUS: i32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct DeckSuggestion {
+    pub factions: Vec<Influence>,
+    pub main_deck: Vec<Card>,
+    pub power_cards: Vec<Card>,
+    pub sigils: Vec<(Influence, usize)>,
+    pub cuts: Vec<(Card, String)>,
+}
+
+impl DeckSuggestion {
+    pub fn power_count(&self) -> usize {
+        self.power_cards.len() + self.sigils.iter().map(|(_, count)| count).sum::<usize>()
+    }
+
+    pub fn to_text(&self, ratings: &HashMap<String, String>) -> String {
+        let mut lines = vec![format!(
+            "Factions: {}",
+            self.factions.iter().map(|f| f.faction_name()).join(" / ")
+        )];
+
+        lines.push(String::new());
+        lines.push(format!("Main deck ({})", self.main_deck.len()));
+        for card in self.main_deck.iter() {
+            lines.push(format!(
+                "[{:<2}] {}",
+                ratings.get(&card.name).map(|r| r.as_str()).unwrap_or("NA"),
+                card.to_text(CARD_TO_TEXT_OPT_NO_RARITY)
+            ));
+        }
+
+        lines.push(String::new());
+        lines.push(format!("Power ({})", self.power_count()));
+        for card in self.power_cards.iter() {
+            lines.push(format!("1x {}", card.name));
+        }
+        for (faction, count) in self.sigils.iter() {
+            lines.push(format!("{}x {} Sigil", count, faction.faction_name()));
+        }
+
+        if !self.cuts.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("Cuts ({})", self.cuts.len()));
+            for (card, reason) in self.cuts.iter() {
+                lines.push(format!("{:30} {}", card.name, reason));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+fn card_score(card: &Card, ratings: &HashMap<String, String>) -> i32 {
+    ratings
+        .get(&card.name)
+        .and_then(|rating| grade_score(rating))
+        .or_else(|| grade_score(UNRATED_GRADE))
+        .unwrap_or(0)
+}
+
+fn is_power(card: &Card) -> bool {
+    card.card_type.is(&CardTypeEnum::Power)
+}
+
+fn is_castable(card: &Card, factions: &[Influence]) -> bool {
+    card.influence.is_payable_with(factions)
+}
+
+fn produces_any(card: &Card, factions: &[Influence]) -> bool {
+    card.influence
+        .factions()
+        .iter()
+        .any(|faction| factions.contains(faction))
+}
+
+fn faction_combinations() -> Vec<Vec<Influence>> {
+    FACTIONS
+        .iter()
+        .cloned()
+        .combinations(2)
+        .chain(FACTIONS.iter().cloned().combinations(3))
+        .collect()
+}
+
+fn score_factions(
+    factions: &[Influence],
+    non_power: &[&Card],
+    power: &[&Card],
+    ratings: &HashMap<String, String>,
+) -> i32 {
+    let card_total: i32 = non_power
+        .iter()
+        .filter(|card| is_castable(card, factions))
+        .map(|card| card_score(card, ratings))
+        .sorted_by(|a, b| b.cmp(a))
+        .take(NON_POWER_TARGET)
+        .sum();
+
+    if factions.len() < 3 {
+        return card_total;
+    }
+
+    let fixing = power
+        .iter()
+        .filter(|card| {
+            card.influence
+                .factions()
+                .iter()
+                .filter(|faction| factions.contains(faction))
+                .count()
+                >= 2
+        })
+        .count() as i32;
+
+    card_total - (THIRD_FACTION_PENALTY - fixing * FIXING_BONUS).max(0)
+}
+
+// Splits `total` sigils across factions in proportion to the influence the main deck asks for
+fn allocate_sigils(
+    factions: &[Influence],
+    main_deck: &[Card],
+    total: usize,
+) -> Vec<(Influence, usize)> {
+    let weights = factions
+        .iter()
+        .map(|faction| {
+            let weight: usize = main_deck
+                .iter()
+                .map(|card| card.influence.count_of(faction) as usize)
+                .sum();
+            (*faction, weight.max(1))
+        })
+        .collect::<Vec<(Influence, usize)>>();
+    let weight_total: usize = weights.iter().map(|(_, weight)| weight).sum();
+
+    let mut sigils = weights
+        .iter()
+        .map(|(faction, weight)| {
+            (
+                *faction,
+                total * weight / weight_total,
+                total * weight % weight_total,
+            )
+        })
+        .collect::<Vec<(Influence, usize, usize)>>();
+
+    let mut remaining = total - sigils.iter().map(|(_, count, _)| count).sum::<usize>();
+    let order = sigils
+        .iter()
+        .enumerate()
+        .sorted_by(|a, b| b.1 .2.cmp(&a.1 .2).then(a.0.cmp(&b.0)))
+        .map(|(idx, _)| idx)
+        .collect::<Vec<usize>>();
+    for idx in order {
+        if remaining == 0 {
+            break;
+        }
+        sigils[idx].1 += 1;
+        remaining -= 1;
+    }
+
+    sigils
+        .into_iter()
+        .map(|(faction, count, _)| (faction, count))
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+pub fn build_deck(pool: &[Card], ratings: &HashMap<String, String>) -> Res<DeckSuggestion> {
+    let non_power = pool
+        .iter()
+        .filter(|card| !is_power(card))
+        .collect::<Vec<&Card>>();
+    let power = pool
+        .iter()
+        .filter(|card| is_power(card))
+        .collect::<Vec<&Card>>();
+
+    if non_power.is_empty() {
+        return Err(Error::Data("No cards drafted yet".to_string()));
+    }
+
+    let factions = faction_combinations()
+        .into_iter()
+        .map(|factions| {
+            let score = score_factions(&factions, &non_power, &power, ratings);
+            (factions, score)
+        })
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.len().cmp(&a.0.len())))
+        .map(|(factions, _)| factions)
+        .ok_or(Error::Data("No faction combinations".to_string()))?;
+
+    let mut castable = non_power
+        .iter()
+        .filter(|card| is_castable(card, &factions))
+        .sorted_by(|a, b| {
+            card_score(b, ratings)
+                .cmp(&card_score(a, ratings))
+                .then(a.cost.cmp(&b.cost))
+        })
+        .cloned()
+        .cloned()
+        .collect::<Vec<Card>>();
+    // Padding a short pool with sigils would not be a real deck
+    if castable.len() < NON_POWER_TARGET {
+        return Err(Error::Data(format!(
+            "The pool is too small for a deck, {} of {} cards castable in {}",
+            castable.len(),
+            NON_POWER_TARGET,
+            factions
+                .iter()
+                .map(|faction| faction.faction_name())
+                .join(", ")
+        )));
+    }
+    let cut_for_rating = castable.split_off(NON_POWER_TARGET);
+    let main_deck = castable
+        .into_iter()
+        .sorted_by_key(|card| (card.cost, card.influence.to_text(), card.name.to_string()))
+        .collect::<Vec<Card>>();
+
+    let power_count = DECK_SIZE - main_deck.len();
+    let (power_cards, cut_power): (Vec<&Card>, Vec<&Card>) =
+        power.iter().partition(|card| produces_any(card, &factions));
+    let mut power_cards = power_cards.into_iter().cloned().collect::<Vec<Card>>();
+    let cut_extra_power = if power_cards.len() > power_count {
+        power_cards.split_off(power_count)
+    } else {
+        Vec::new()
+    };
+    let sigils = allocate_sigils(&factions, &main_deck, power_count - power_cards.len());
+
+    let cutoff = main_deck
+        .iter()
+        .map(|card| card_score(card, ratings))
+        .min()
+        .unwrap_or(0);
+    let cutoff_grade = main_deck
+        .iter()
+        .find(|card| card_score(card, ratings) == cutoff)
+        .and_then(|card| ratings.get(&card.name))
+        .map(|r| r.as_str())
+        .unwrap_or(UNRATED_GRADE);
+
+    let mut cuts = Vec::new();
+    for card in non_power
+        .iter()
+        .filter(|card| !is_castable(card, &factions))
+    {
+        let missing = card
+            .influence
+            .factions()
+            .iter()
+            .filter(|faction| !factions.contains(faction))
+            .map(|faction| faction.faction_name())
+            .join(", ");
+        cuts.push(((*card).clone(), format!("off-faction, needs {}", missing)));
+    }
+    for card in cut_for_rating.into_iter() {
+        let grade = ratings.get(&card.name).cloned().unwrap_or("NA".to_string());
+        cuts.push((
+            card,
+            format!("rated {}, deck cutoff is {}", grade, cutoff_grade),
+        ));
+    }
+    for card in cut_power.into_iter() {
+        cuts.push((card.clone(), "power for other factions".to_string()));
+    }
+    for card in cut_extra_power.into_iter() {
+        cuts.push((card, "too many power cards".to_string()));
+    }
+
+    Ok(DeckSuggestion {
+        factions,
+        main_deck,
+        power_cards,
+        sigils,
+        cuts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_cards::card;
+
+    fn test_pool() -> (Vec<Card>, HashMap<String, String>) {
+        let mut pool = Vec::new();
+        let mut ratings = HashMap::new();
+        for i in 0..15 {
+            pool.push(card(&format!("Fire {}", i), i % 5 + 1, "{F}", "Unit"));
+            ratings.insert(format!("Fire {}", i), "B".to_string());
+            pool.push(card(&format!("Justice {}", i), i % 5 + 1, "{J}{J}", "Unit"));
+            ratings.insert(format!("Justice {}", i), "B-".to_string());
+        }
+        for i in 0..5 {
+            pool.push(card(&format!("Shadow {}", i), 2, "{S}", "Spell"));
+            ratings.insert(format!("Shadow {}", i), "C".to_string());
+        }
+        pool.push(card("Seat of Glory", 0, "{F}{J}", "Power"));
+        pool.push(card("Seat of Cunning", 0, "{P}{S}", "Power"));
+        (pool, ratings)
+    }
+
+    #[test]
+    fn test_build_deck() {
+        let (pool, ratings) = test_pool();
+        let deck = build_deck(&pool, &ratings).unwrap();
+
+        assert_eq!(deck.factions, vec![Influence::Fire, Influence::Justice]);
+        assert_eq!(deck.main_deck.len(), NON_POWER_TARGET);
+        assert_eq!(deck.main_deck.len() + deck.power_count(), DECK_SIZE);
+        assert_eq!(deck.power_cards.len(), 1);
+        assert_eq!(deck.power_cards[0].name, "Seat of Glory");
+
+        // Justice cards need two pips each, so Justice gets more sigils
+        let sigils: HashMap<Influence, usize> = deck.sigils.iter().cloned().collect();
+        assert!(sigils[&Influence::Justice] > sigils[&Influence::Fire]);
+
+        let cut_names = deck
+            .cuts
+            .iter()
+            .map(|(card, _)| card.name.as_str())
+            .collect::<Vec<&str>>();
+        assert!(cut_names.contains(&"Shadow 0"));
+        assert!(cut_names.contains(&"Seat of Cunning"));
+        assert_eq!(deck.cuts.len(), 2 + 5 + 1);
+    }
+
+    #[test]
+    fn test_build_deck_small_pool() {
+        let pool = vec![card("Torch", 1, "{F}", "Fast Spell")];
+        let err = build_deck(&pool, &HashMap::new()).unwrap_err();
+
+        assert!(err.to_string().contains("too small"));
+    }
+
+    #[test]
+    fn test_build_deck_empty_pool() {
+        assert!(build_deck(&[], &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_allocate_sigils() {
+        let main_deck = vec![
+            card("A", 1, "{F}", "Unit"),
+            card("B", 1, "{T}{T}{T}", "Unit"),
+        ];
+        let sigils = allocate_sigils(&[Influence::Fire, Influence::Time], &main_deck, 16);
+        assert_eq!(sigils, vec![(Influence::Fire, 4), (Influence::Time, 12)]);
+    }
+}
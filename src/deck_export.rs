@@ -0,0 +1,171 @@
+use itertools::Itertools;
+use serde::Serialize;
+use strum_macros::{Display, EnumString};
+
+use crate::models::card::*;
+use crate::opt::*;
+
+#[derive(Debug, Clone, PartialEq, Default, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum DeckExportFormat {
+    #[default]
+    #[strum(serialize = "eternal")]
+    Eternal,
+    #[strum(serialize = "text")]
+    Text,
+    #[strum(serialize = "json")]
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct DeckExportEntry {
+    count: usize,
+    name: String,
+    set_number: u32,
+    eternal_id: Option<u32>,
+    cost: u32,
+    influence: String,
+    card_type: String,
+    rarity: String,
+}
+
+fn group_cards(cards: &[Card]) -> Vec<(usize, &Card)> {
+    cards
+        .iter()
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .group_by(|card| card.name.to_string())
+        .into_iter()
+        .map(|(_, group)| {
+            let group = group.collect::<Vec<&Card>>();
+            (group.len(), group[0])
+        })
+        .sorted_by_key(|(_, card)| {
+            (
+                card.card_type.is(&CardTypeEnum::Power),
+                card.cost,
+                card.influence.to_text(),
+                card.name.to_string(),
+            )
+        })
+        .collect()
+}
+
+// Eternal imports one "<count> <name> (Set<set> #<id>)" line per card
+pub fn to_eternal_line(count: usize, card: &Card) -> String {
+    match card.eternal_id {
+        Some(eternal_id) => format!(
+            "{} {} (Set{} #{})",
+            count, card.name, card.set_number, eternal_id
+        ),
+        None => format!("{} {}", count, card.name),
+    }
+}
+
+// Reads a deck pasted from the Eternal client back into one card name per copy
+pub fn parse_eternal_deck(text: &str) -> Res<Vec<String>> {
+    let mut names = Vec::new();
+    for line in text.lines().map(|line| line.trim()) {
+        // The client separates the market with a line of dashes
+        if line.is_empty() || line.starts_with('-') {
+            continue;
+        }
+
+        let (count, rest) = line
+            .split_once(' ')
+            .ok_or(Error::Data(format!("Unable to parse deck line [{}]", line)))?;
+        let count = count
+            .parse::<usize>()
+            .map_err(|_| Error::Data(format!("Expected a card count in [{}]", line)))?;
+        let name = match rest.rfind(" (Set") {
+            Some(idx) => &rest[..idx],
+            None => rest,
+        };
+
+        names.extend(std::iter::repeat(name.trim().to_string()).take(count));
+    }
+    Ok(names)
+}
+
+pub fn export_deck(cards: &[Card], format: &DeckExportFormat) -> Res<String> {
+    let grouped = group_cards(cards);
+
+    match format {
+        DeckExportFormat::Eternal => Ok(grouped
+            .iter()
+            .map(|(count, card)| to_eternal_line(*count, card))
+            .join("\n")),
+        DeckExportFormat::Text => Ok(grouped
+            .iter()
+            .map(|(count, card)| format!("{}x {}", count, card.to_text(CARD_TO_TEXT_OPT_NO_RARITY)))
+            .join("\n")),
+        DeckExportFormat::Json => {
+            let entries = grouped
+                .iter()
+                .map(|(count, card)| DeckExportEntry {
+                    count: *count,
+                    name: card.name.to_string(),
+                    set_number: card.set_number,
+                    eternal_id: card.eternal_id,
+                    cost: card.cost,
+                    influence: card.influence.to_text(),
+                    card_type: card.card_type.to_text(),
+                    rarity: card.rarity.to_string(),
+                })
+                .collect::<Vec<DeckExportEntry>>();
+            serde_json::to_string_pretty(&entries).err_to(Error::Data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_cards() -> Vec<Card> {
+        serde_json::from_str(
+            r#"[
+            {"SetNumber":1,"EternalID":5,"Name":"Torch","CardText":"","Cost":1,"Influence":"{F}","Attack":0,"Health":0,"Rarity":"Common","Type":"Fast Spell","ImageUrl":"","DetailsUrl":"","DeckBuildable":true,"SetName":"The Empty Throne"},
+            {"SetNumber":1,"EternalID":1,"Name":"Fire Sigil","CardText":"","Cost":0,"Influence":"","Attack":0,"Health":0,"Rarity":"Common","Type":"Power","ImageUrl":"","DetailsUrl":"","DeckBuildable":true,"SetName":"The Empty Throne"},
+            {"SetNumber":3,"EternalID":12,"Name":"Oni Ronin","CardText":"","Cost":1,"Influence":"{F}","Attack":1,"Health":1,"Rarity":"Uncommon","Type":"Unit","ImageUrl":"","DetailsUrl":"","DeckBuildable":true,"SetName":"The Dusk Road"},
+            {"SetNumber":1,"EternalID":5,"Name":"Torch","CardText":"","Cost":1,"Influence":"{F}","Attack":0,"Health":0,"Rarity":"Common","Type":"Fast Spell","ImageUrl":"","DetailsUrl":"","DeckBuildable":true,"SetName":"The Empty Throne"}
+        ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_export_eternal() {
+        let exported = export_deck(&test_cards(), &DeckExportFormat::Eternal).unwrap();
+        assert_eq!(
+            exported,
+            "1 Oni Ronin (Set3 #12)\n2 Torch (Set1 #5)\n1 Fire Sigil (Set1 #1)"
+        );
+    }
+
+    #[test]
+    fn test_parse_eternal_deck() {
+        let names = parse_eternal_deck("2 Torch (Set1 #5)\n1 Vara, Fiery Dancer\n\n---MARKET---\n")
+            .unwrap();
+        assert_eq!(names, vec!["Torch", "Torch", "Vara, Fiery Dancer"]);
+        assert!(parse_eternal_deck("Torch").is_err());
+    }
+
+    #[test]
+    fn test_export_json() {
+        let exported = export_deck(&test_cards(), &DeckExportFormat::Json).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&exported).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 3);
+        assert_eq!(parsed[1]["name"], "Torch");
+        assert_eq!(parsed[1]["count"], 2);
+        assert_eq!(parsed[1]["eternal_id"], 5);
+    }
+
+    #[test]
+    fn test_export_format_from_str() {
+        assert_eq!(
+            "JSON".parse::<DeckExportFormat>().unwrap(),
+            DeckExportFormat::Json
+        );
+        assert!("xml".parse::<DeckExportFormat>().is_err());
+    }
+}
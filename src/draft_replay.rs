@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::models::draft_data::DraftRecord;
+
+#[derive(Debug, Clone)]
+pub struct DraftReplay {
+    pub game_id: String,
+    records: Vec<DraftRecord>,
+}
+
+pub fn taken_card(record: &DraftRecord) -> Option<&str> {
+    record
+        .selected_card
+        .and_then(|idx| record.selection_vec.get(idx as usize))
+        .map(|name| name.as_str())
+        .filter(|name| !name.is_empty())
+}
+
+pub fn passed_cards(record: &DraftRecord) -> Vec<&str> {
+    record
+        .selection_vec
+        .iter()
+        .enumerate()
+        .filter(|(idx, name)| !name.is_empty() && record.selected_card != Some(*idx as u8))
+        .map(|(_, name)| name.as_str())
+        .collect()
+}
+
+pub fn pick_text(record: &DraftRecord, ratings: &HashMap<String, String>) -> String {
+    let mut lines = vec![format!("Pick {}", record.pick.to_string())];
+
+    for (idx, name) in record.selection_vec.iter().enumerate() {
+        if name.is_empty() {
+            continue;
+        }
+        let marker = if record.selected_card == Some(idx as u8) {
+            ">"
+        } else {
+            " "
+        };
+        lines.push(format!(
+            "{} {:>2}. [{:<2}] {}",
+            marker,
+            idx + 1,
+            ratings.get(name).map(|r| r.as_str()).unwrap_or("NA"),
+            name
+        ));
+    }
+
+    if taken_card(record).is_none() {
+        lines.push("No card taken".to_string());
+    }
+
+    lines.join("\n")
+}
+
+impl DraftReplay {
+    pub fn new(game_id: &str, records: Vec<DraftRecord>) -> Self {
+        DraftReplay {
+            game_id: game_id.to_string(),
+            records: records
+                .into_iter()
+                .sorted_by_key(|record| record.pick.pick_id)
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn record(&self, pick_id: u8) -> Option<&DraftRecord> {
+        self.records
+            .iter()
+            .find(|record| record.pick.pick_id == pick_id)
+    }
+
+    pub fn first_pick(&self) -> Option<u8> {
+        self.records.first().map(|record| record.pick.pick_id)
+    }
+
+    // Picks that were never captured are skipped when stepping through the draft
+    pub fn next_pick(&self, pick_id: u8) -> Option<u8> {
+        self.records
+            .iter()
+            .map(|record| record.pick.pick_id)
+            .find(|id| *id > pick_id)
+    }
+
+    pub fn prev_pick(&self, pick_id: u8) -> Option<u8> {
+        self.records
+            .iter()
+            .rev()
+            .map(|record| record.pick.pick_id)
+            .find(|id| *id < pick_id)
+    }
+
+    pub fn summary(&self) -> String {
+        self.records
+            .iter()
+            .map(|record| {
+                format!(
+                    "{:<6} {}",
+                    record.pick.pick_str,
+                    taken_card(record).unwrap_or("-")
+                )
+            })
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::draft_data::DraftPick;
+
+    fn record(pick_id: u8, selections: &[&str], selected: Option<u8>) -> DraftRecord {
+        let mut record = DraftRecord::new("game".to_string(), DraftPick::new(pick_id));
+        record.set_selection_vec(selections);
+        record.selected_card = selected;
+        record
+    }
+
+    fn test_replay() -> DraftReplay {
+        DraftReplay::new(
+            "game",
+            vec![
+                record(3, &["Torch", "", "Harsh Rule"], None),
+                record(
+                    1,
+                    &["Torch", "Vara, Fiery Dancer", "Rakano Outlaw"],
+                    Some(1),
+                ),
+                record(2, &["Torch", "Harsh Rule"], Some(0)),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_taken_and_passed() {
+        let replay = test_replay();
+        let first = replay.record(1).unwrap();
+
+        assert_eq!(taken_card(first), Some("Vara, Fiery Dancer"));
+        assert_eq!(passed_cards(first), vec!["Torch", "Rakano Outlaw"]);
+
+        let untaken = replay.record(3).unwrap();
+        assert_eq!(taken_card(untaken), None);
+        assert_eq!(passed_cards(untaken), vec!["Torch", "Harsh Rule"]);
+    }
+
+    #[test]
+    fn test_stepping() {
+        let replay = DraftReplay::new(
+            "game",
+            vec![
+                record(1, &["Torch"], Some(0)),
+                record(4, &["Torch"], Some(0)),
+            ],
+        );
+
+        assert_eq!(replay.first_pick(), Some(1));
+        assert_eq!(replay.next_pick(1), Some(4));
+        assert_eq!(replay.next_pick(4), None);
+        assert_eq!(replay.prev_pick(4), Some(1));
+        assert_eq!(replay.prev_pick(1), None);
+    }
+
+    #[test]
+    fn test_pick_text() {
+        let replay = test_replay();
+        let ratings = HashMap::from([("Vara, Fiery Dancer".to_string(), "A".to_string())]);
+
+        let text = pick_text(replay.record(1).unwrap(), &ratings);
+        assert_eq!(
+            text,
+            "Pick 1 (p1p1)\n   1. [NA] Torch\n>  2. [A ] Vara, Fiery Dancer\n   3. [NA] Rakano Outlaw"
+        );
+        assert!(pick_text(replay.record(3).unwrap(), &ratings).ends_with("No card taken"));
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = test_replay().summary();
+        let lines = summary.lines().collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "p1p1   Vara, Fiery Dancer");
+        assert_eq!(lines[2], "p1p3   -");
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::models::card::*;
+use crate::models::card_rating::grade_score;
+use crate::models::draft_data::{DraftRecord, PACK_SIZE};
+
+// Only playables tell us something, filler is left in every pack
+pub const SIGNAL_MIN_GRADE: &str = "B-";
+// A faction is flowing when it scores this much above the average faction
+const FLOWING_RATIO: f32 = 1.5;
+// Below this there is too little evidence to call anything open
+const FLOWING_MIN_SCORE: f32 = 1.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FactionSignal {
+    pub faction: Influence,
+    pub score: f32,
+    pub flowing: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackSignals {
+    pub pack: u8,
+    // Picks that carried a signal, the first pick of a pack never does
+    pub picks: usize,
+    pub factions: Vec<FactionSignal>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DraftSignals {
+    pub packs: Vec<PackSignals>,
+    pub overall: Vec<FactionSignal>,
+}
+
+fn pack_of(record: &DraftRecord) -> u8 {
+    (record.pick.pick_id - 1) / PACK_SIZE as u8 + 1
+}
+
+fn position_in_pack(record: &DraftRecord) -> usize {
+    (record.pick.pick_id as usize - 1) % PACK_SIZE + 1
+}
+
+// Strength of every faction in what the earlier drafters left. A good card is
+// worth more the later it is still there, on the first pick nothing was passed yet
+fn pick_scores(
+    record: &DraftRecord,
+    cards: &HashMap<String, Card>,
+    ratings: &HashMap<String, String>,
+    min_score: i32,
+) -> [f32; FACTIONS.len()] {
+    let weight = (position_in_pack(record) - 1) as f32 / (PACK_SIZE - 1) as f32;
+    let mut scores = [0.0; FACTIONS.len()];
+
+    for card in record
+        .selection_vec
+        .iter()
+        .filter_map(|name| cards.get(name))
+    {
+        let score = match ratings
+            .get(&card.name)
+            .and_then(|rating| grade_score(rating))
+        {
+            Some(score) if score >= min_score => score,
+            _ => continue,
+        };
+        // Gold cards signal for both of their factions
+        for faction in card.influence.factions() {
+            if let Some(idx) = FACTIONS.iter().position(|f| *f == faction) {
+                scores[idx] += (score - min_score + 1) as f32 * weight;
+            }
+        }
+    }
+
+    scores
+}
+
+fn faction_signals(totals: &[f32; FACTIONS.len()], picks: usize) -> Vec<FactionSignal> {
+    let scores = totals
+        .iter()
+        .map(|total| if picks > 0 { total / picks as f32 } else { 0.0 })
+        .collect::<Vec<f32>>();
+    let average = scores.iter().sum::<f32>() / scores.len() as f32;
+
+    FACTIONS
+        .iter()
+        .zip(scores)
+        .map(|(faction, score)| FactionSignal {
+            faction: *faction,
+            score,
+            flowing: score >= FLOWING_MIN_SCORE && score >= average * FLOWING_RATIO,
+        })
+        .sorted_by(|a, b| b.score.total_cmp(&a.score))
+        .collect()
+}
+
+fn signals_text(signals: &[FactionSignal]) -> String {
+    signals
+        .iter()
+        .map(|signal| {
+            format!(
+                "{} {:.1}{}",
+                signal.faction.faction_name(),
+                signal.score,
+                if signal.flowing { "*" } else { "" }
+            )
+        })
+        .join(", ")
+}
+
+impl DraftSignals {
+    pub fn flowing(&self) -> Vec<Influence> {
+        self.overall
+            .iter()
+            .filter(|signal| signal.flowing)
+            .map(|signal| signal.faction)
+            .collect()
+    }
+
+    pub fn summary(&self) -> String {
+        let flowing = self.flowing();
+        if flowing.is_empty() {
+            "Signals: no faction stands out yet".to_string()
+        } else {
+            format!(
+                "Signals: {} {} open",
+                flowing
+                    .iter()
+                    .map(|faction| faction.faction_name())
+                    .join(", "),
+                if flowing.len() == 1 { "looks" } else { "look" }
+            )
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![self.summary()];
+        for pack in self.packs.iter() {
+            lines.push(format!(
+                "Pack {} ({} picks): {}",
+                pack.pack,
+                pack.picks,
+                signals_text(&pack.factions)
+            ));
+        }
+        lines.push(format!("Overall: {}", signals_text(&self.overall)));
+        lines.push(format!(
+            "Average strength of {} or better cards still in the pack, weighted by pick. * flowing",
+            SIGNAL_MIN_GRADE
+        ));
+        lines.join("\n")
+    }
+}
+
+pub fn analyze_signals(
+    records: &[DraftRecord],
+    cards: &HashMap<String, Card>,
+    ratings: &HashMap<String, String>,
+) -> DraftSignals {
+    let min_score = grade_score(SIGNAL_MIN_GRADE).unwrap_or(0);
+    let mut overall = [0.0; FACTIONS.len()];
+    let mut overall_picks = 0;
+
+    let packs = records
+        .iter()
+        .filter(|record| position_in_pack(record) > 1)
+        .sorted_by_key(|record| record.pick.pick_id)
+        .group_by(|record| pack_of(record))
+        .into_iter()
+        .map(|(pack, records)| {
+            let mut totals = [0.0; FACTIONS.len()];
+            let mut picks = 0;
+            for record in records {
+                let scores = pick_scores(record, cards, ratings, min_score);
+                for (total, score) in totals.iter_mut().zip(scores.iter()) {
+                    *total += score;
+                }
+                picks += 1;
+            }
+
+            for (total, score) in overall.iter_mut().zip(totals.iter()) {
+                *total += score;
+            }
+            overall_picks += picks;
+
+            PackSignals {
+                pack,
+                picks,
+                factions: faction_signals(&totals, picks),
+            }
+        })
+        .collect::<Vec<PackSignals>>();
+
+    DraftSignals {
+        packs,
+        overall: faction_signals(&overall, overall_picks),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::draft_data::DraftPick;
+    use crate::test_cards::test_card;
+
+    fn test_cards() -> HashMap<String, Card> {
+        [
+            test_card("Torch").influence("{F}").build(),
+            test_card("Oni Ronin").influence("{F}").build(),
+            test_card("Harsh Rule").influence("{J}{J}").build(),
+            test_card("Vara").influence("{F}{J}").build(),
+            test_card("Filler").influence("{S}").build(),
+        ]
+        .into_iter()
+        .map(|card| (card.name.to_string(), card))
+        .collect()
+    }
+
+    fn test_ratings() -> HashMap<String, String> {
+        HashMap::from([
+            ("Torch".to_string(), "A".to_string()),
+            ("Oni Ronin".to_string(), "B".to_string()),
+            ("Harsh Rule".to_string(), "B-".to_string()),
+            ("Vara".to_string(), "B".to_string()),
+            ("Filler".to_string(), "C".to_string()),
+        ])
+    }
+
+    fn record(pick_id: u8, selections: &[&str]) -> DraftRecord {
+        let mut record = DraftRecord::new("game".to_string(), DraftPick::new(pick_id));
+        record.set_selection_vec(selections);
+        record
+    }
+
+    fn score_of(signals: &[FactionSignal], faction: Influence) -> f32 {
+        signals
+            .iter()
+            .find(|signal| signal.faction == faction)
+            .map(|signal| signal.score)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_first_pick_carries_no_signal() {
+        let records = vec![record(1, &["Torch", "Oni Ronin", "Filler"])];
+        let signals = analyze_signals(&records, &test_cards(), &test_ratings());
+
+        assert!(signals.packs.is_empty());
+        assert!(signals.flowing().is_empty());
+        assert_eq!(signals.summary(), "Signals: no faction stands out yet");
+    }
+
+    #[test]
+    fn test_late_fire_is_flowing() {
+        let records = vec![
+            record(12, &["Torch"]),
+            record(6, &["Oni Ronin", "Harsh Rule", "Filler"]),
+        ];
+        let signals = analyze_signals(&records, &test_cards(), &test_ratings());
+
+        assert_eq!(signals.packs.len(), 1);
+        assert_eq!(signals.packs[0].picks, 2);
+        // Torch at pick 12 is worth its full 5, Oni Ronin at pick 6 counts 2 * 5/11
+        let fire = score_of(&signals.overall, Influence::Fire);
+        assert!((fire - (5.0 + 2.0 * 5.0 / 11.0) / 2.0).abs() < 0.001);
+        assert_eq!(score_of(&signals.overall, Influence::Shadow), 0.0);
+
+        assert_eq!(signals.flowing(), vec![Influence::Fire]);
+        assert_eq!(signals.summary(), "Signals: Fire looks open");
+    }
+
+    #[test]
+    fn test_gold_cards_and_packs() {
+        let records = vec![record(3, &["Vara"]), record(15, &["Harsh Rule"])];
+        let signals = analyze_signals(&records, &test_cards(), &test_ratings());
+
+        assert_eq!(
+            signals
+                .packs
+                .iter()
+                .map(|pack| pack.pack)
+                .collect::<Vec<u8>>(),
+            vec![1, 2]
+        );
+        let first = &signals.packs[0].factions;
+        assert_eq!(
+            score_of(first, Influence::Fire),
+            score_of(first, Influence::Justice)
+        );
+        assert_eq!(score_of(&signals.packs[1].factions, Influence::Fire), 0.0);
+        assert!(signals.to_text().contains("Pack 2 (1 picks)"));
+    }
+}
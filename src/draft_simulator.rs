@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::models::card::*;
+use crate::models::card_rating::{grade_score, UNRATED_GRADE};
+use crate::models::draft_data::{DraftPick, DraftRecord, DRAFT_PICK_COUNT, PACK_SIZE};
+use crate::opt::*;
+use crate::pack_generator::{PackGenerator, PackLayout};
+
+// Every seat opens a 12 card pack per round, so a pod of 12 empties the packs
+pub const POD_SIZE: usize = 12;
+pub const HUMAN_SEAT: usize = 0;
+
+// Bots stay open for their first picks, then lean into their two main factions
+const BOT_COMMIT_AFTER: usize = 8;
+const BOT_FACTION_BONUS: i32 = 3;
+
+fn card_score(card: &Card, ratings: &HashMap<String, String>) -> i32 {
+    ratings
+        .get(&card.name)
+        .and_then(|rating| grade_score(rating))
+        .or_else(|| grade_score(UNRATED_GRADE))
+        .unwrap_or(0)
+}
+
+// Picks the best rated card, favoring the factions the bot already has most pips in
+pub fn bot_pick(pool: &[Card], pack: &[Card], ratings: &HashMap<String, String>) -> usize {
+    let main_factions = if pool.len() >= BOT_COMMIT_AFTER {
+        FACTIONS
+            .iter()
+            .map(|faction| {
+                let pips: usize = pool
+                    .iter()
+                    .map(|card| card.influence.count_of(faction) as usize)
+                    .sum();
+                (*faction, pips)
+            })
+            .sorted_by(|a, b| b.1.cmp(&a.1))
+            .take(2)
+            .map(|(faction, _)| faction)
+            .collect::<Vec<Influence>>()
+    } else {
+        Vec::new()
+    };
+
+    pack.iter()
+        .enumerate()
+        .max_by_key(|(idx, card)| {
+            let on_faction = !main_factions.is_empty()
+                && card
+                    .influence
+                    .factions()
+                    .iter()
+                    .all(|faction| main_factions.contains(faction));
+            let bonus = if on_faction { BOT_FACTION_BONUS } else { 0 };
+            // Ties go to the card shown first
+            (card_score(card, ratings) + bonus, -(*idx as i32))
+        })
+        .map(|(idx, _)| idx)
+        .unwrap_or(0)
+}
+
+pub struct DraftSimulator {
+    pub game_id: String,
+    generator: PackGenerator,
+    ratings: HashMap<String, String>,
+    packs: Vec<Vec<Card>>,
+    pools: Vec<Vec<Card>>,
+    pick_id: u8,
+}
+
+impl DraftSimulator {
+    pub fn new(
+        game_id: &str,
+        cards: &[Card],
+        set_name: &str,
+        ratings: &HashMap<String, String>,
+        seed: u64,
+    ) -> Res<Self> {
+        let generator = PackGenerator::new(cards, set_name, PackLayout::default(), seed)?;
+        DraftSimulator::with_generator(game_id, generator, ratings)
+    }
+
+    pub fn with_generator(
+        game_id: &str,
+        generator: PackGenerator,
+        ratings: &HashMap<String, String>,
+    ) -> Res<Self> {
+        if generator.layout().pack_size() != PACK_SIZE {
+            return Err(Error::Data(format!(
+                "Simulated drafts need {} card packs, the layout has {}",
+                PACK_SIZE,
+                generator.layout().pack_size()
+            )));
+        }
+
+        let mut simulator = DraftSimulator {
+            game_id: game_id.to_string(),
+            generator,
+            ratings: ratings.clone(),
+            packs: Vec::new(),
+            pools: vec![Vec::new(); POD_SIZE],
+            pick_id: 1,
+        };
+        simulator.open_packs();
+
+        Ok(simulator)
+    }
+
+    fn open_packs(&mut self) {
+        let generator = &mut self.generator;
+        self.packs = (0..POD_SIZE).map(|_| generator.generate()).collect();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pick_id > DRAFT_PICK_COUNT
+    }
+
+    pub fn current_pick(&self) -> Option<DraftPick> {
+        if self.is_finished() {
+            None
+        } else {
+            Some(DraftPick::new(self.pick_id))
+        }
+    }
+
+    pub fn current_pack(&self) -> &[Card] {
+        self.packs
+            .get(HUMAN_SEAT)
+            .map(|pack| pack.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn human_pool(&self) -> &[Card] {
+        &self.pools[HUMAN_SEAT]
+    }
+
+    pub fn bot_pools(&self) -> &[Vec<Card>] {
+        &self.pools[HUMAN_SEAT + 1..]
+    }
+
+    // The human seat is shown as a regular draft record so the pick and vote commands work unchanged
+    pub fn current_record(&self) -> Option<DraftRecord> {
+        let pick = self.current_pick()?;
+        let names = self
+            .current_pack()
+            .iter()
+            .map(|card| card.name.as_str())
+            .collect::<Vec<&str>>();
+        let deck = self
+            .human_pool()
+            .iter()
+            .map(|card| card.name.as_str())
+            .collect::<Vec<&str>>();
+
+        let mut record = DraftRecord::new(self.game_id.to_string(), pick);
+        record.set_selection_vec(&names);
+        record.set_selection_text(&names.join("\n"));
+        record.set_decklist_text(&deck);
+        Some(record)
+    }
+
+    pub fn pick(&mut self, pick_idx: u8) -> Res<Card> {
+        if self.is_finished() {
+            return Err(Error::Data("The draft is already over".to_string()));
+        }
+        if pick_idx as usize >= self.current_pack().len() {
+            return Err(Error::Data(format!(
+                "Pick {} is out of range, the pack has {} cards",
+                pick_idx + 1,
+                self.current_pack().len()
+            )));
+        }
+
+        let picked = self.packs[HUMAN_SEAT].remove(pick_idx as usize);
+        self.pools[HUMAN_SEAT].push(picked.clone());
+
+        for seat in (0..POD_SIZE).filter(|seat| *seat != HUMAN_SEAT) {
+            if self.packs[seat].is_empty() {
+                continue;
+            }
+            let idx = bot_pick(&self.pools[seat], &self.packs[seat], &self.ratings);
+            let card = self.packs[seat].remove(idx);
+            self.pools[seat].push(card);
+        }
+
+        self.pick_id += 1;
+        if self.is_finished() {
+            return Ok(picked);
+        }
+
+        if self.packs.iter().all(|pack| pack.is_empty()) {
+            self.open_packs();
+        } else {
+            self.pass_packs();
+        }
+
+        Ok(picked)
+    }
+
+    // Packs go left in the first and third round and right in the others
+    fn pass_packs(&mut self) {
+        let round = (self.pick_id as usize - 1) / PACK_SIZE;
+        if round % 2 == 0 {
+            self.packs.rotate_right(1);
+        } else {
+            self.packs.rotate_left(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_cards::test_card;
+
+    fn test_cards() -> Vec<Card> {
+        let mut cards = Vec::new();
+        for (rarity, count) in [
+            ("Common", 20),
+            ("Uncommon", 10),
+            ("Rare", 5),
+            ("Legendary", 2),
+        ] {
+            for i in 0..count {
+                let influence = ["{F}", "{T}", "{J}", "{P}", "{S}"][i % 5];
+                cards.push(
+                    test_card(&format!("{} {}", rarity, i))
+                        .influence(influence)
+                        .rarity(rarity)
+                        .build(),
+                );
+            }
+        }
+        cards.push(test_card("Fire Sigil").influence("").rarity("None").build());
+        cards
+    }
+
+    #[test]
+    fn test_bot_pick() {
+        let ratings = HashMap::from([
+            ("A".to_string(), "B".to_string()),
+            ("B".to_string(), "B-".to_string()),
+        ]);
+        let pack = vec![
+            test_card("A").influence("{S}").build(),
+            test_card("B").influence("{F}").build(),
+        ];
+
+        assert_eq!(bot_pick(&[], &pack, &ratings), 0);
+
+        let pool = vec![test_card("Fire").influence("{F}{T}").build(); BOT_COMMIT_AFTER];
+        assert_eq!(bot_pick(&pool, &pack, &ratings), 1);
+    }
+
+    #[test]
+    fn test_full_draft() {
+        let cards = test_cards();
+        let mut simulator = DraftSimulator::new("sim", &cards, "test", &HashMap::new(), 7).unwrap();
+
+        let first = simulator.current_record().unwrap();
+        assert_eq!(first.pick.pick_str, "p1p1");
+        assert_eq!(first.selection_vec.len(), PACK_SIZE);
+
+        while !simulator.is_finished() {
+            simulator.pick(0).unwrap();
+            if let Some(record) = simulator.current_record() {
+                assert_eq!(
+                    record.selection_vec.len(),
+                    record.pick.get_expected_card_selection_count() as usize
+                );
+            }
+        }
+
+        assert_eq!(simulator.human_pool().len(), DRAFT_PICK_COUNT as usize);
+        assert!(simulator
+            .bot_pools()
+            .iter()
+            .all(|pool| pool.len() == DRAFT_PICK_COUNT as usize));
+        assert!(simulator.pick(0).is_err());
+    }
+
+    #[test]
+    fn test_same_seed_same_packs() {
+        let cards = test_cards();
+        let a = DraftSimulator::new("a", &cards, "", &HashMap::new(), 42).unwrap();
+        let b = DraftSimulator::new("b", &cards, "", &HashMap::new(), 42).unwrap();
+
+        assert_eq!(
+            a.current_record().unwrap().selection_vec,
+            b.current_record().unwrap().selection_vec
+        );
+        assert!(DraftSimulator::new("c", &cards, "unknown set", &HashMap::new(), 1).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+
+use crate::draft_replay::taken_card;
+use crate::models::draft_data::DraftRecord;
+use crate::models::draft_game::DraftGame;
+use crate::models::draft_result::DraftResult;
+
+// Cards taken in fewer games are left out of the card table so one lucky run does not top it
+pub const CARD_STATS_MIN_GAMES: usize = 2;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserStats {
+    pub user_id: String,
+    pub games: usize,
+    pub runs: usize,
+    pub wins: usize,
+    pub losses: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardStats {
+    pub name: String,
+    pub times_taken: usize,
+    pub games: usize,
+    pick_total: usize,
+    pub wins: usize,
+    pub losses: usize,
+}
+
+fn win_rate(wins: usize, losses: usize) -> Option<f32> {
+    if wins + losses == 0 {
+        None
+    } else {
+        Some(wins as f32 / (wins + losses) as f32)
+    }
+}
+
+fn win_rate_text(rate: Option<f32>) -> String {
+    rate.map(|rate| format!("{:.0}%", rate * 100.0))
+        .unwrap_or("NA".to_string())
+}
+
+impl UserStats {
+    pub fn win_rate(&self) -> Option<f32> {
+        win_rate(self.wins, self.losses)
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "{} games, {} with results, {}-{} ({})",
+            self.games,
+            self.runs,
+            self.wins,
+            self.losses,
+            win_rate_text(self.win_rate())
+        )
+    }
+}
+
+impl CardStats {
+    // Position within the pack, so 1 is always the first pick of a pack
+    pub fn average_pick(&self) -> f32 {
+        if self.times_taken == 0 {
+            0.0
+        } else {
+            self.pick_total as f32 / self.times_taken as f32
+        }
+    }
+
+    pub fn win_rate(&self) -> Option<f32> {
+        win_rate(self.wins, self.losses)
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "{:30} taken {:>2}, avg pick {:>4.1}, {}-{} ({})",
+            self.name,
+            self.times_taken,
+            self.average_pick(),
+            self.wins,
+            self.losses,
+            win_rate_text(self.win_rate())
+        )
+    }
+}
+
+pub fn user_stats(user_id: &str, games: &[DraftGame], results: &[DraftResult]) -> UserStats {
+    let results: HashMap<&str, &DraftResult> = results
+        .iter()
+        .map(|result| (result.game_id.as_str(), result))
+        .collect();
+
+    games
+        .iter()
+        .filter(|game| game.user_id.as_deref() == Some(user_id) && !game.simulated)
+        .fold(
+            UserStats {
+                user_id: user_id.to_string(),
+                ..Default::default()
+            },
+            |mut stats, game| {
+                stats.games += 1;
+                if let Some(result) = results.get(game.game_id.as_str()) {
+                    stats.runs += 1;
+                    stats.wins += result.wins();
+                    stats.losses += result.losses();
+                }
+                stats
+            },
+        )
+}
+
+// Drops the records, votes or results of the given games, e.g. the simulated ones
+pub fn without_games<T>(
+    items: Vec<T>,
+    game_ids: &HashSet<String>,
+    game_id: fn(&T) -> &str,
+) -> Vec<T> {
+    items
+        .into_iter()
+        .filter(|item| !game_ids.contains(game_id(item)))
+        .collect()
+}
+
+pub fn card_stats(records: &[DraftRecord], results: &[DraftResult]) -> Vec<CardStats> {
+    let results: HashMap<&str, &DraftResult> = results
+        .iter()
+        .map(|result| (result.game_id.as_str(), result))
+        .collect();
+
+    let mut stats: HashMap<String, CardStats> = HashMap::new();
+    let mut counted_games: HashSet<(String, String)> = HashSet::new();
+
+    for record in records.iter() {
+        let name = match taken_card(record) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        let card = stats.entry(name.to_string()).or_insert_with(|| CardStats {
+            name: name.to_string(),
+            ..Default::default()
+        });
+        card.times_taken += 1;
+        card.pick_total += (record.pick.pick_id as usize - 1) % 12 + 1;
+
+        // Several copies taken in one game only count the game's record once
+        if counted_games.insert((record.game_id.to_string(), name.to_string())) {
+            card.games += 1;
+            if let Some(result) = results.get(record.game_id.as_str()) {
+                card.wins += result.wins();
+                card.losses += result.losses();
+            }
+        }
+    }
+
+    stats
+        .into_values()
+        .sorted_by(|a, b| {
+            b.games
+                .cmp(&a.games)
+                .then(a.average_pick().total_cmp(&b.average_pick()))
+                .then(a.name.cmp(&b.name))
+        })
+        .collect()
+}
+
+pub fn best_cards(stats: &[CardStats], limit: usize) -> Vec<&CardStats> {
+    stats
+        .iter()
+        .filter(|card| card.games >= CARD_STATS_MIN_GAMES)
+        .filter(|card| card.win_rate().is_some())
+        .sorted_by(|a, b| {
+            b.win_rate()
+                .unwrap_or(0.0)
+                .total_cmp(&a.win_rate().unwrap_or(0.0))
+                .then(b.games.cmp(&a.games))
+                .then(a.name.cmp(&b.name))
+        })
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::draft_data::DraftPick;
+    use crate::models::draft_game::VoteMode;
+
+    fn record(game_id: &str, pick_id: u8, taken: &str) -> DraftRecord {
+        let mut record = DraftRecord::new(game_id.to_string(), DraftPick::new(pick_id));
+        record.set_selection_vec(&["Filler", taken]);
+        record.pick_card(1);
+        record
+    }
+
+    fn result(game_id: &str, wins: usize, losses: usize) -> DraftResult {
+        let mut result = DraftResult::new(game_id);
+        for _ in 0..wins {
+            result.add_match(true, None).unwrap();
+        }
+        for _ in 0..losses {
+            result.add_match(false, None).unwrap();
+        }
+        result
+    }
+
+    fn game(game_id: &str, user_id: &str) -> DraftGame {
+        DraftGame {
+            game_id: game_id.to_string(),
+            time: String::new(),
+            user_id: Some(user_id.to_string()),
+            vote_mode: VoteMode::default(),
+            thread_id: None,
+            simulated: false,
+        }
+    }
+
+    #[test]
+    fn test_user_stats() {
+        let games = vec![game("a", "1"), game("b", "1"), game("c", "2")];
+        let results = vec![result("a", 6, 2), result("c", 0, 3)];
+
+        let stats = user_stats("1", &games, &results);
+        assert_eq!((stats.games, stats.runs), (2, 1));
+        assert_eq!((stats.wins, stats.losses), (6, 2));
+        assert_eq!(stats.win_rate(), Some(0.75));
+        assert_eq!(user_stats("3", &games, &results).win_rate(), None);
+
+        let mut simulated = game("d", "1");
+        simulated.simulated = true;
+        let games = vec![game("a", "1"), simulated];
+        let results = vec![result("a", 6, 2), result("d", 7, 0)];
+        let stats = user_stats("1", &games, &results);
+        assert_eq!((stats.games, stats.wins, stats.losses), (1, 6, 2));
+    }
+
+    #[test]
+    fn test_without_games() {
+        let simulated = HashSet::from(["sim".to_string()]);
+        let records = without_games(
+            vec![record("a", 1, "Torch"), record("sim", 1, "Harsh Rule")],
+            &simulated,
+            |record| record.game_id.as_str(),
+        );
+        let results = without_games(
+            vec![result("a", 6, 2), result("sim", 7, 0)],
+            &simulated,
+            |result| result.game_id.as_str(),
+        );
+
+        let stats = card_stats(&records, &results);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].name, "Torch");
+        assert_eq!((stats[0].wins, stats[0].losses), (6, 2));
+    }
+
+    #[test]
+    fn test_card_stats() {
+        let records = vec![
+            record("a", 1, "Torch"),
+            record("a", 14, "Torch"),
+            record("a", 2, "Harsh Rule"),
+            record("b", 3, "Torch"),
+            record("c", 5, "Harsh Rule"),
+        ];
+        let results = vec![result("a", 6, 2), result("b", 2, 3)];
+
+        let stats = card_stats(&records, &results);
+        let torch = &stats[0];
+        assert_eq!(torch.name, "Torch");
+        assert_eq!((torch.times_taken, torch.games), (3, 2));
+        assert_eq!(torch.average_pick(), 2.0);
+        assert_eq!((torch.wins, torch.losses), (8, 5));
+
+        let harsh_rule = &stats[1];
+        assert_eq!((harsh_rule.wins, harsh_rule.losses), (6, 2));
+
+        let best = best_cards(&stats, 5);
+        assert_eq!(best[0].name, "Harsh Rule");
+        assert_eq!(best.len(), 2);
+    }
+}
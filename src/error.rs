@@ -0,0 +1,105 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Db(String),
+    Ocr(String),
+    // The android device or emulator the screen is captured from
+    Device(String),
+    Upload(String),
+    Discord(String),
+    Config(String),
+    // Missing or malformed data, user input included
+    Data(String),
+}
+
+impl Error {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Db(_) => "db",
+            Error::Ocr(_) => "ocr",
+            Error::Device(_) => "device",
+            Error::Upload(_) => "upload",
+            Error::Discord(_) => "discord",
+            Error::Config(_) => "config",
+            Error::Data(_) => "data",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Error::Db(message)
+            | Error::Ocr(message)
+            | Error::Device(message)
+            | Error::Upload(message)
+            | Error::Discord(message)
+            | Error::Config(message)
+            | Error::Data(message) => message,
+        }
+    }
+
+    // Services and the device can come back, bad config or data fails the same way again
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Db(_)
+            | Error::Ocr(_)
+            | Error::Device(_)
+            | Error::Upload(_)
+            | Error::Discord(_) => true,
+            Error::Config(_) | Error::Data(_) => false,
+        }
+    }
+
+    // What the bot replies, the details of internal failures only go to the log
+    pub fn user_message(&self) -> String {
+        match self {
+            Error::Data(message) => message.to_string(),
+            Error::Db(_) => {
+                "The draft database is not available, please try again later.".to_string()
+            }
+            Error::Discord(_) => {
+                "Discord did not accept the request, please try again.".to_string()
+            }
+            Error::Config(_) => {
+                "The bot is not configured for this, ask the bot owner.".to_string()
+            }
+            Error::Ocr(_) | Error::Device(_) | Error::Upload(_) => {
+                "Something went wrong, please try again later.".to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable() {
+        assert!(Error::Db("timeout".to_string()).is_retryable());
+        assert!(Error::Device("adb disconnected".to_string()).is_retryable());
+        assert!(Error::Ocr("no text".to_string()).is_retryable());
+        assert!(!Error::Config("db.host is not set".to_string()).is_retryable());
+        assert!(!Error::Data("No card found".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn test_user_message() {
+        let err = Error::Data("Pick must be between 1 and 48".to_string());
+        assert_eq!(err.user_message(), "Pick must be between 1 and 48");
+        assert_eq!(err.to_string(), "Pick must be between 1 and 48");
+
+        let err = Error::Db("connection refused at 10.0.0.3".to_string());
+        assert!(!err.user_message().contains("10.0.0.3"));
+        assert_eq!(err.to_string(), "connection refused at 10.0.0.3");
+        assert_eq!(err.kind(), "db");
+    }
+}
@@ -0,0 +1,124 @@
+use std::str::FromStr;
+
+use itertools::Itertools;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::fmt::format::{DefaultFields, Format, Json, JsonFields};
+use tracing_subscriber::fmt::{MakeWriter, SubscriberBuilder};
+
+use crate::app_config::{LogConfig, LogFormat};
+use crate::opt::*;
+
+pub fn env_filter(config: &LogConfig) -> Res<EnvFilter> {
+    // A bare word is a valid directive for a target of that name, so the level is checked on its own
+    LevelFilter::from_str(config.level.trim())
+        .map_err(|_| Error::Config(format!("Unknown log level [{}]", config.level)))?;
+
+    let directives = [config.level.as_str(), config.filters.as_str()]
+        .iter()
+        .map(|directive| directive.trim())
+        .filter(|directive| !directive.is_empty())
+        .join(",");
+    EnvFilter::try_new(&directives)
+        .map_err(|err| Error::Config(format!("Invalid log filters [{}]: {}", config.filters, err)))
+}
+
+fn json<W>(
+    builder: SubscriberBuilder<DefaultFields, Format, EnvFilter, W>,
+) -> SubscriberBuilder<JsonFields, Format<Json>, EnvFilter, W>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    // Fields of the event and of the enclosing game or command at the top level
+    builder
+        .json()
+        .flatten_event(true)
+        .with_current_span(true)
+        .with_span_list(false)
+}
+
+// Logs go to stderr so command output on stdout can be piped
+pub fn init(config: &LogConfig) -> Res<()> {
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(env_filter(config)?)
+        .with_writer(std::io::stderr);
+
+    match config.format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => json(builder).try_init(),
+    }
+    .err_to(Error::Config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    fn config(level: &str, filters: &str) -> LogConfig {
+        LogConfig {
+            level: level.to_string(),
+            filters: filters.to_string(),
+            format: LogFormat::Json,
+        }
+    }
+
+    #[test]
+    fn test_env_filter() {
+        assert!(env_filter(&LogConfig::default()).is_ok());
+        assert!(env_filter(&config("warn", "draft_claw::db_access=debug,serenity=off")).is_ok());
+        assert!(env_filter(&config("loud", "")).is_err());
+        assert!(env_filter(&config("info", "draft_claw=[")).is_err());
+    }
+
+    #[test]
+    fn test_json_output() {
+        let buffer = Buffer::default();
+        let subscriber = json(
+            tracing_subscriber::fmt()
+                .with_env_filter(env_filter(&config("info", "draft_claw::noisy=warn")).unwrap())
+                .with_writer(buffer.clone()),
+        )
+        .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("game", game_id = "abc");
+            let _entered = span.enter();
+            tracing::info!(pick = "P1p1", "Captured pick");
+            tracing::debug!("Hidden by the level");
+            tracing::info!(target: "draft_claw::noisy", "Hidden by the filter");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines = output.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 1);
+
+        let event: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(event["level"], "INFO");
+        assert_eq!(event["message"], "Captured pick");
+        assert_eq!(event["pick"], "P1p1");
+        assert_eq!(event["span"]["name"], "game");
+        assert_eq!(event["span"]["game_id"], "abc");
+    }
+}
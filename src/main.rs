@@ -2,7 +2,9 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
+use clap::Parser;
 use dotenv::dotenv;
+use tracing::{info, Instrument};
 
 #[cfg(feature = "capture")]
 mod capture;
@@ -10,48 +12,94 @@ mod capture;
 #[cfg(feature = "bot")]
 mod bot;
 
+mod app_config;
 mod app_context;
 mod db_access;
 mod models;
+mod card_abilities;
 mod card_loader;
+mod card_matcher;
+mod card_query;
+mod cli;
+mod deck_builder;
+mod deck_export;
+mod draft_replay;
+mod draft_signals;
+mod draft_simulator;
+mod draft_stats;
+mod error;
+mod logging;
+mod metrics;
+mod pack_generator;
+mod pool_analysis;
+mod rating_validation;
+mod team_rating;
+#[cfg(test)]
+mod test_cards;
+mod vote_aggregation;
 pub mod opt;
 
-fn init() {
-    println!("OS type: {}", std::env::consts::OS);
-    println!("OS version: {}", std::env::consts::ARCH);
+fn exit_with_error(err: opt::Error) -> ! {
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
 
+// Loads the configuration the command needs, anything missing stops here
+fn init(cli: &cli::Cli, command: &cli::Command) {
     dotenv().ok();
-}
 
-#[cfg(all(feature = "capture", not(feature = "bot")))]
-#[tokio::main]
-async fn main() {
-    init();
-    let context = app_context::create_context();
+    command
+        .check_feature()
+        .unwrap_or_else(|err| exit_with_error(err));
 
-    capture::main(&context).await;
+    let config = app_config::load(&cli.config_args()).unwrap_or_else(|err| exit_with_error(err));
+    if command.validates_config() {
+        config
+            .validate(command.config_mode())
+            .unwrap_or_else(|err| exit_with_error(err));
+        logging::init(&config.log).unwrap_or_else(|err| exit_with_error(err));
+    } else {
+        // A bad log filter is one of the issues the command prints
+        logging::init(&config.log).ok();
+    }
+    app_config::init(config);
 }
 
-#[cfg(all(feature = "bot", not(feature = "capture")))]
-#[tokio::main]
-async fn main() {
-    init();
-    let context = app_context::create_context();
-
-    bot::main(&context).await;
+fn print_platform() {
+    info!(
+        os = std::env::consts::OS,
+        arch = std::env::consts::ARCH,
+        "Starting"
+    );
 }
 
-#[cfg(all(feature = "bot", feature = "capture"))]
 #[tokio::main]
 async fn main() {
-    init();
-    let context = app_context::create_context();
+    let cli = cli::Cli::parse();
+    let command = cli.command.clone().unwrap_or_default();
+    init(&cli, &command);
 
-    if cfg!(feature = "bot") {
-        bot::main(&context).await;
-    } else if cfg!(feature = "capture") {
-        capture::main(&context).await;
-    } else {
-        panic!("No feature specified");
+    match command {
+        #[cfg(feature = "bot")]
+        cli::Command::Bot => {
+            print_platform();
+            let context = app_context::create_context().unwrap_or_else(|err| exit_with_error(err));
+            metrics::spawn_server(&app_config::get().metrics);
+            bot::main(&context).await;
+        }
+        #[cfg(feature = "capture")]
+        cli::Command::Capture => {
+            print_platform();
+            let context = app_context::create_context().unwrap_or_else(|err| exit_with_error(err));
+            metrics::spawn_server(&app_config::get().metrics);
+            capture::main(&context).await;
+        }
+        command => match cli::run(&command, &cli.config_args())
+            .instrument(tracing::info_span!("command", name = command.name()))
+            .await
+        {
+            Ok(output) => println!("{}", output),
+            Err(err) => exit_with_error(err),
+        },
     }
 }
@@ -0,0 +1,274 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use lazy_static::lazy_static;
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use tracing::{info, warn};
+
+use crate::app_config::MetricsConfig;
+use crate::opt::*;
+
+const METRICS_NAMESPACE: &str = "draft_claw";
+const METRICS_PATH: &str = "/metrics";
+
+lazy_static! {
+    static ref REGISTRY: Registry =
+        Registry::new_custom(Some(METRICS_NAMESPACE.to_string()), None).unwrap();
+    static ref COMMANDS: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new("commands_total", "Bot commands handled"),
+            &["command", "sub_command"]
+        )
+        .unwrap()
+    );
+    static ref DB_CALL_DURATION: HistogramVec = register(
+        HistogramVec::new(
+            HistogramOpts::new("db_call_duration_seconds", "Time spent on database calls"),
+            &["operation"]
+        )
+        .unwrap()
+    );
+    static ref DB_CALL_FAILURES: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new("db_call_failures_total", "Database calls that returned an error"),
+            &["operation"]
+        )
+        .unwrap()
+    );
+    static ref OCR_DURATION: Histogram = register(
+        Histogram::with_opts(HistogramOpts::new(
+            "ocr_duration_seconds",
+            "Time to read the text of a screenshot"
+        ))
+        .unwrap()
+    );
+    // The match rate is matched over matched and unmatched
+    static ref CARD_MATCHES: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new("card_matches_total", "OCR texts matched to a card name"),
+            &["result"]
+        )
+        .unwrap()
+    );
+    static ref UPLOADS: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new("uploads_total", "Screenshot uploads"),
+            &["result"]
+        )
+        .unwrap()
+    );
+    static ref PICKS: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new("picks_total", "Cards picked on the device"),
+            &["mode", "outcome"]
+        )
+        .unwrap()
+    );
+}
+
+fn register<T: Collector + Clone + 'static>(metric: T) -> T {
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .expect("metric is registered once");
+    metric
+}
+
+fn result_label<T>(result: &Res<T>) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(_) => "failed",
+    }
+}
+
+pub fn count_command(command: &str, sub_command: &str) {
+    COMMANDS.with_label_values(&[command, sub_command]).inc();
+}
+
+// Times a database call until it is dropped, a call dropped before `finish` returned
+// early through `?` and counts as failed
+pub struct DbCall {
+    operation: &'static str,
+    start: Instant,
+    succeeded: bool,
+}
+
+pub fn db_call(operation: &'static str) -> DbCall {
+    DbCall {
+        operation,
+        start: Instant::now(),
+        succeeded: false,
+    }
+}
+
+impl DbCall {
+    pub fn finish<T>(mut self, result: Res<T>) -> Res<T> {
+        self.succeeded = result.is_ok();
+        result
+    }
+}
+
+impl Drop for DbCall {
+    fn drop(&mut self) {
+        DB_CALL_DURATION
+            .with_label_values(&[self.operation])
+            .observe(self.start.elapsed().as_secs_f64());
+        if !self.succeeded {
+            DB_CALL_FAILURES.with_label_values(&[self.operation]).inc();
+        }
+    }
+}
+
+pub fn time_ocr<T>(read: impl FnOnce() -> T) -> T {
+    let _timer = OCR_DURATION.start_timer();
+    read()
+}
+
+pub fn count_card_matches(matched: usize, texts: usize) {
+    CARD_MATCHES
+        .with_label_values(&["matched"])
+        .inc_by(matched as u64);
+    CARD_MATCHES
+        .with_label_values(&["unmatched"])
+        .inc_by(texts.saturating_sub(matched) as u64);
+}
+
+pub fn count_upload<T>(result: &Res<T>) {
+    UPLOADS.with_label_values(&[result_label(result)]).inc();
+}
+
+// `mode` is auto or manual, `outcome` is selected, failed or no_card
+pub fn count_pick(mode: &str, outcome: &str) {
+    PICKS.with_label_values(&[mode, outcome]).inc();
+}
+
+pub fn render() -> Res<String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .err_to(Error::Data)?;
+    String::from_utf8(buffer).err_to(Error::Data)
+}
+
+fn respond(request: &Request<Body>) -> Response<Body> {
+    if request.method() != Method::GET || request.uri().path() != METRICS_PATH {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    match render() {
+        Ok(text) => Response::builder()
+            .header(header::CONTENT_TYPE, TextEncoder::new().format_type())
+            .body(Body::from(text))
+            .unwrap(),
+        Err(err) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from(err.to_string()))
+            .unwrap(),
+    }
+}
+
+pub async fn serve(addr: SocketAddr) -> Res<()> {
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(|request| async move {
+            Ok::<_, Infallible>(respond(&request))
+        }))
+    });
+
+    let server = Server::try_bind(&addr)
+        .map_err(|err| Error::Config(format!("Unable to listen on {}: {}", addr, err)))?
+        .serve(make_service);
+    info!(
+        "Serving metrics on http://{}{}",
+        server.local_addr(),
+        METRICS_PATH
+    );
+    server.await.err_to(Error::Data)
+}
+
+// Runs next to the bot or the capture, nothing is served without `metrics.listen`
+pub fn spawn_server(config: &MetricsConfig) {
+    if config.listen.is_empty() {
+        return;
+    }
+
+    match config.listen.parse::<SocketAddr>() {
+        Ok(addr) => {
+            tokio::spawn(async move {
+                if let Err(err) = serve(addr).await {
+                    warn!("Metrics server stopped: {}", err);
+                }
+            });
+        }
+        Err(err) => warn!("Invalid metrics.listen [{}]: {}", config.listen, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, path: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        count_command("!ping", "");
+        count_command("!draft", "vote");
+        count_card_matches(3, 4);
+        count_pick("auto", "selected");
+        count_upload::<()>(&Err(Error::Upload("timeout".to_string())));
+
+        let text = render().unwrap();
+        assert!(text.contains("draft_claw_commands_total{command=\"!ping\",sub_command=\"\"}"));
+        assert!(text.contains("draft_claw_commands_total{command=\"!draft\",sub_command=\"vote\"}"));
+        assert!(text.contains("draft_claw_card_matches_total{result=\"unmatched\"}"));
+        assert!(text.contains("draft_claw_picks_total{mode=\"auto\",outcome=\"selected\"}"));
+        assert!(text.contains("draft_claw_uploads_total{result=\"failed\"}"));
+    }
+
+    #[test]
+    fn test_db_call() {
+        db_call("test_ok").finish(Ok(())).unwrap();
+        db_call("test_failed")
+            .finish::<()>(Err(Error::Db("down".to_string())))
+            .unwrap_err();
+        drop(db_call("test_early_return"));
+
+        let text = render().unwrap();
+        assert!(text.contains("draft_claw_db_call_duration_seconds_count{operation=\"test_ok\"} 1"));
+        assert!(text.contains("draft_claw_db_call_failures_total{operation=\"test_failed\"} 1"));
+        assert!(
+            text.contains("draft_claw_db_call_failures_total{operation=\"test_early_return\"} 1")
+        );
+        assert!(!text.contains("draft_claw_db_call_failures_total{operation=\"test_ok\"}"));
+    }
+
+    #[test]
+    fn test_respond() {
+        assert_eq!(
+            respond(&request(Method::GET, METRICS_PATH)).status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            respond(&request(Method::GET, "/")).status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            respond(&request(Method::POST, METRICS_PATH)).status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+}
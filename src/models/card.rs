@@ -1,6 +1,7 @@
 use std::{
     fmt::{self, Display, Formatter},
     str::FromStr,
+    sync::OnceLock,
 };
 
 use indicium::simple::Indexable;
@@ -9,7 +10,9 @@ use serde::{Deserialize, Deserializer};
 
 use strum_macros::{Display, EnumString};
 
-#[derive(Debug, Clone, PartialEq, Display, EnumString)]
+use crate::card_abilities::{self, CardAbilities};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
 pub enum Influence {
     #[strum(serialize = "F")]
     Fire,
@@ -21,31 +24,192 @@ pub enum Influence {
     Primal,
     #[strum(serialize = "S")]
     Shadow,
-    #[strum(serialize = "x")]
-    None,
 }
 
-#[derive(Debug, Clone)]
+pub const FACTIONS: [Influence; 5] = [
+    Influence::Fire,
+    Influence::Time,
+    Influence::Justice,
+    Influence::Primal,
+    Influence::Shadow,
+];
+
+// Eternal names every combination of two factions
+pub const FACTION_PAIRS: [(Influence, Influence, &str); 10] = [
+    (Influence::Fire, Influence::Time, "Praxis"),
+    (Influence::Fire, Influence::Justice, "Rakano"),
+    (Influence::Fire, Influence::Primal, "Skycrag"),
+    (Influence::Fire, Influence::Shadow, "Stonescar"),
+    (Influence::Time, Influence::Justice, "Combrei"),
+    (Influence::Time, Influence::Primal, "Elysian"),
+    (Influence::Time, Influence::Shadow, "Xenan"),
+    (Influence::Justice, Influence::Primal, "Hooru"),
+    (Influence::Justice, Influence::Shadow, "Argenport"),
+    (Influence::Primal, Influence::Shadow, "Feln"),
+];
+
+impl Influence {
+    pub fn faction_name(&self) -> &'static str {
+        match self {
+            Influence::Fire => "Fire",
+            Influence::Time => "Time",
+            Influence::Justice => "Justice",
+            Influence::Primal => "Primal",
+            Influence::Shadow => "Shadow",
+        }
+    }
+}
+
+// A single faction, the Eternal name of a pair such as "Rakano", or the factions joined
+pub fn faction_group_name(factions: &[Influence]) -> String {
+    match factions {
+        [] => "Factionless".to_string(),
+        [faction] => faction.faction_name().to_string(),
+        [a, b] => FACTION_PAIRS
+            .iter()
+            .find(|(x, y, _)| (x == a && y == b) || (x == b && y == a))
+            .map(|(_, _, name)| name.to_string())
+            .unwrap_or_else(|| format!("{}/{}", a.faction_name(), b.faction_name())),
+        _ => factions
+            .iter()
+            .map(|faction| faction.faction_name())
+            .join("/"),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InfluenceSymbol {
+    Faction(Influence),
+    // Paid by either faction, written as {F/J}
+    Hybrid(Influence, Influence),
+    // Kept as written so a new symbol in the card data is not silently dropped
+    Unknown(String),
+}
+
+impl InfluenceSymbol {
+    fn parse(symbol: &str) -> Self {
+        let symbol = symbol.trim();
+        if let Ok(faction) = Influence::from_str(symbol) {
+            return InfluenceSymbol::Faction(faction);
+        }
+        if let Some((a, b)) = symbol.split_once('/') {
+            if let (Ok(a), Ok(b)) = (Influence::from_str(a.trim()), Influence::from_str(b.trim())) {
+                return InfluenceSymbol::Hybrid(a, b);
+            }
+        }
+        InfluenceSymbol::Unknown(symbol.to_string())
+    }
+
+    pub fn to_text(&self) -> String {
+        match self {
+            InfluenceSymbol::Faction(faction) => faction.to_string(),
+            InfluenceSymbol::Hybrid(a, b) => format!("{{{}/{}}}", a, b),
+            InfluenceSymbol::Unknown(symbol) => format!("{{{}}}", symbol),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CardInfluence {
-    influences: Vec<Influence>,
+    symbols: Vec<InfluenceSymbol>,
 }
 
 impl CardInfluence {
+    pub fn symbols(&self) -> &[InfluenceSymbol] {
+        &self.symbols
+    }
+    // Single faction symbols only, hybrid and unknown symbols are left out
+    pub fn influences(&self) -> Vec<Influence> {
+        self.symbols
+            .iter()
+            .filter_map(|symbol| match symbol {
+                InfluenceSymbol::Faction(faction) => Some(*faction),
+                _ => None,
+            })
+            .collect()
+    }
     pub fn count(&self) -> u8 {
-        self.influences.len() as u8
+        self.symbols.len() as u8
+    }
+    pub fn count_of(&self, influence: &Influence) -> u8 {
+        self.symbols
+            .iter()
+            .filter(|symbol| **symbol == InfluenceSymbol::Faction(*influence))
+            .count() as u8
+    }
+    // Requirement per faction, in the order of `FACTIONS`
+    pub fn counts(&self) -> [u8; FACTIONS.len()] {
+        FACTIONS.map(|faction| self.count_of(&faction))
+    }
+    pub fn hybrids(&self) -> Vec<(Influence, Influence)> {
+        self.symbols
+            .iter()
+            .filter_map(|symbol| match symbol {
+                InfluenceSymbol::Hybrid(a, b) => Some((*a, *b)),
+                _ => None,
+            })
+            .collect()
+    }
+    pub fn unknown(&self) -> Vec<&str> {
+        self.symbols
+            .iter()
+            .filter_map(|symbol| match symbol {
+                InfluenceSymbol::Unknown(symbol) => Some(symbol.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+    // Factions the card strictly requires
+    pub fn factions(&self) -> Vec<Influence> {
+        FACTIONS
+            .iter()
+            .filter(|faction| self.count_of(faction) > 0)
+            .cloned()
+            .collect()
+    }
+    // Whether a deck in the given factions can meet the requirement. A hybrid
+    // symbol needs either of its factions, an unknown symbol can never be paid
+    pub fn is_payable_with(&self, factions: &[Influence]) -> bool {
+        self.symbols.iter().all(|symbol| match symbol {
+            InfluenceSymbol::Faction(faction) => factions.contains(faction),
+            InfluenceSymbol::Hybrid(a, b) => factions.contains(a) || factions.contains(b),
+            InfluenceSymbol::Unknown(_) => false,
+        })
+    }
+    pub fn is_multi_faction(&self) -> bool {
+        self.factions().len() > 1
+    }
+    pub fn group_name(&self) -> String {
+        faction_group_name(&self.factions())
     }
     pub fn to_text(&self) -> String {
-        self.influences.iter().map(|i| format!("{}", i)).join("")
+        self.symbols.iter().map(|symbol| symbol.to_text()).join("")
     }
 }
 
 impl Display for CardInfluence {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let mut influences_str = String::new();
-        for influence in &self.influences {
-            influences_str.push_str(&format!("{}", influence));
+        write!(f, "{:<6}", self.to_text())
+    }
+}
+
+// Influence is written as a list of braced symbols, e.g. "{F}{F}{J}"
+impl FromStr for CardInfluence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut symbols = Vec::new();
+        let mut rest = s.trim();
+        while !rest.is_empty() {
+            let (symbol, remaining) = rest
+                .strip_prefix('{')
+                .and_then(|rest| rest.split_once('}'))
+                .filter(|(symbol, _)| !symbol.trim().is_empty() && !symbol.contains('{'))
+                .ok_or(format!("Malformed influence [{}]", s))?;
+            symbols.push(InfluenceSymbol::parse(symbol));
+            rest = remaining.trim_start();
         }
-        write!(f, "{:<6}", influences_str)
+        Ok(CardInfluence { symbols })
     }
 }
 
@@ -55,18 +219,12 @@ impl<'de> Deserialize<'de> for CardInfluence {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        let influences: Vec<Influence> = s
-            .chars()
-            .chunks(3)
-            .into_iter()
-            .filter_map(|chunk| chunk.into_iter().nth(1))
-            .map(|c| Influence::from_str(&c.to_string()).unwrap_or(Influence::None))
-            .collect();
-        Ok(CardInfluence { influences })
+        CardInfluence::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, EnumString)]
+#[derive(Debug, Clone, PartialEq, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
 pub enum CardTypeEnum {
     Unit,
     Spell,
@@ -84,6 +242,26 @@ pub struct CardType {
     is_fast: bool,
 }
 
+impl CardType {
+    pub fn types(&self) -> &[CardTypeEnum] {
+        &self.card_type
+    }
+    pub fn is_fast(&self) -> bool {
+        self.is_fast
+    }
+    pub fn is(&self, card_type: &CardTypeEnum) -> bool {
+        self.card_type.contains(card_type)
+    }
+    pub fn to_text(&self) -> String {
+        let types = self.card_type.iter().map(|t| t.to_string()).join(" ");
+        if self.is_fast {
+            format!("Fast {}", types)
+        } else {
+            types
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for CardType {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -109,7 +287,7 @@ impl<'de> Deserialize<'de> for CardType {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, PartialOrd, Ord, PartialEq, Eq, Display, EnumString)]
+#[derive(Debug, Clone, Deserialize, PartialOrd, Ord, PartialEq, Eq, Hash, Display, EnumString)]
 pub enum CardRarity {
     #[strum(serialize = "L")]
     Legendary,
@@ -125,10 +303,25 @@ pub enum CardRarity {
     None,
 }
 
+impl CardRarity {
+    // Position from Common up, Promo and None are outside the order
+    pub fn rank(&self) -> Option<u8> {
+        match self {
+            CardRarity::Common => Some(1),
+            CardRarity::Uncommon => Some(2),
+            CardRarity::Rare => Some(3),
+            CardRarity::Legendary => Some(4),
+            CardRarity::Promo | CardRarity::None => None,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct Card {
     pub set_number: u32,
+    #[serde(rename = "EternalID")]
+    pub eternal_id: Option<u32>,
     pub name: String,
     #[serde(default = "no_card_text")]
     pub card_text: String,
@@ -143,6 +336,9 @@ pub struct Card {
     pub details_url: String,
     pub deck_buildable: bool,
     pub set_name: String,
+    // Parsed from the card text on first use and kept with the card
+    #[serde(skip)]
+    abilities: OnceLock<CardAbilities>,
 }
 
 fn no_card_text() -> String {
@@ -162,6 +358,22 @@ pub const CARD_TO_TEXT_OPT_NO_RARITY: CardToTextOpt = CardToTextOpt {
 };
 
 impl Card {
+    pub fn abilities(&self) -> &CardAbilities {
+        self.abilities
+            .get_or_init(|| card_abilities::parse_card_text(&self.card_text))
+    }
+
+    pub fn plain_text(&self) -> String {
+        self.card_text
+            .replace("<b>", "")
+            .replace("</b>", "")
+            .replace("<i>", "")
+            .replace("</i>", "")
+            .split(';')
+            .map(|line| line.trim())
+            .join("\n")
+    }
+
     pub fn to_text(&self, opt: CardToTextOpt) -> String {
         let extra = match self
             .card_type
@@ -201,3 +413,80 @@ impl Indexable for Card {
         vec![self.name.clone()]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_influence() {
+        let influence = CardInfluence::from_str("{F}{F}{J}").unwrap();
+        assert_eq!(influence.count(), 3);
+        assert_eq!(influence.counts(), [2, 0, 1, 0, 0]);
+        assert_eq!(
+            influence.factions(),
+            vec![Influence::Fire, Influence::Justice]
+        );
+        assert_eq!(influence.group_name(), "Rakano");
+        assert_eq!(influence.to_text(), "FFJ");
+
+        assert_eq!(
+            CardInfluence::from_str("").unwrap().group_name(),
+            "Factionless"
+        );
+        assert!(CardInfluence::from_str("{F}{J").is_err());
+        assert!(CardInfluence::from_str("F{J}").is_err());
+        assert!(CardInfluence::from_str("{}").is_err());
+    }
+
+    #[test]
+    fn test_hybrid_and_unknown_symbols() {
+        let influence = CardInfluence::from_str("{T}{J/P}{X}").unwrap();
+        assert_eq!(influence.count(), 3);
+        assert_eq!(influence.factions(), vec![Influence::Time]);
+        assert_eq!(
+            influence.hybrids(),
+            vec![(Influence::Justice, Influence::Primal)]
+        );
+        assert_eq!(influence.unknown(), vec!["X"]);
+        assert_eq!(influence.to_text(), "T{J/P}{X}");
+        assert!(!influence.is_payable_with(&FACTIONS));
+
+        let hybrid = CardInfluence::from_str("{T}{J/P}").unwrap();
+        assert!(hybrid.is_payable_with(&[Influence::Time, Influence::Primal]));
+        assert!(!hybrid.is_payable_with(&[Influence::Time, Influence::Fire]));
+    }
+
+    #[test]
+    fn test_faction_group_names() {
+        assert_eq!(
+            faction_group_name(&[Influence::Justice, Influence::Time]),
+            "Combrei"
+        );
+        assert_eq!(faction_group_name(&[Influence::Shadow]), "Shadow");
+        assert_eq!(
+            faction_group_name(&[Influence::Fire, Influence::Time, Influence::Justice]),
+            "Fire/Time/Justice"
+        );
+        // Every pair of factions has a name
+        for (idx, a) in FACTIONS.iter().enumerate() {
+            for b in FACTIONS[idx + 1..].iter() {
+                assert!(!faction_group_name(&[*a, *b]).contains('/'));
+            }
+        }
+    }
+
+    #[test]
+    fn test_card_data_influence() {
+        let card_data = std::fs::read_to_string("./resource/eternal-cards.json").unwrap();
+        let cards: Vec<serde_json::Value> = serde_json::from_str(&card_data).unwrap();
+
+        for card in cards.iter() {
+            let text = card["Influence"].as_str().unwrap_or("");
+            let influence = CardInfluence::from_str(text)
+                .unwrap_or_else(|err| panic!("{}: {}", card["Name"], err));
+            assert!(influence.unknown().is_empty(), "{}", card["Name"]);
+            assert_eq!(influence.count() as usize, text.matches('{').count());
+        }
+    }
+}
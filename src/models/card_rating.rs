@@ -6,3 +6,66 @@ pub struct CardRating {
     pub name: String,
     pub rating: String,
 }
+
+const GRADE_LETTERS: &str = "SABCDEF";
+
+// Ratings derived from our own drafts are stored next to the published list under this format
+pub const TEAM_RATING_FORMAT: &str = "team";
+// Cards missing from the rating table are treated as filler
+pub const UNRATED_GRADE: &str = "C-";
+
+// Maps a letter grade such as "B+" to a number so grades can be compared and
+// averaged. "S+" is the highest, "F" the lowest.
+pub fn grade_score(grade: &str) -> Option<i32> {
+    let mut chars = grade.trim().chars();
+    let letter = chars.next()?.to_ascii_uppercase();
+    let base = GRADE_LETTERS.find(letter)? as i32;
+    let modifier = match chars.as_str() {
+        "" => 0,
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+
+    Some((GRADE_LETTERS.len() as i32 - 1 - base) * 3 + modifier)
+}
+
+// Inverse of `grade_score`, used to show averages as a grade again
+pub fn score_grade(score: i32) -> String {
+    let max_base = GRADE_LETTERS.len() as i32 - 1;
+    let score = score.clamp(-1, max_base * 3 + 1);
+    let base = ((score + 1) / 3).clamp(0, max_base);
+    let letter = GRADE_LETTERS
+        .chars()
+        .nth((max_base - base) as usize)
+        .unwrap_or('F');
+    match score - base * 3 {
+        1 => format!("{}+", letter),
+        -1 => format!("{}-", letter),
+        _ => letter.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grade_score() {
+        assert!(grade_score("S+") > grade_score("S"));
+        assert!(grade_score("A-") > grade_score("B+"));
+        assert!(grade_score("B") > grade_score("B-"));
+        assert_eq!(grade_score("b+"), grade_score("B+"));
+        assert_eq!(grade_score("F"), Some(0));
+        assert_eq!(grade_score("NA"), None);
+        assert_eq!(grade_score(""), None);
+    }
+
+    #[test]
+    fn test_score_grade() {
+        for grade in ["S+", "A", "B-", "C+", "F", "F-"] {
+            assert_eq!(score_grade(grade_score(grade).unwrap()), grade);
+        }
+        assert_eq!(score_grade(100), "S+");
+    }
+}
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 
+pub const DRAFT_PICK_COUNT: u8 = 48;
+pub const PACK_SIZE: usize = 12;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DraftRecord {
     pub game_id: String,
@@ -63,12 +66,12 @@ pub struct DraftPick {
 
 impl DraftPick {
     pub fn new(id: u8) -> Self {
-        if id < 1 || id > 48 {
+        if id < 1 || id > DRAFT_PICK_COUNT {
             panic!("Invalid draft pick id: {}", id);
         }
 
-        let pack = (id - 1) / 12 + 1;
-        let pick = (id - 1) % 12 + 1;
+        let pack = (id - 1) / PACK_SIZE as u8 + 1;
+        let pick = (id - 1) % PACK_SIZE as u8 + 1;
 
         DraftPick {
             pick_id: id,
@@ -80,8 +83,12 @@ impl DraftPick {
         format!("{} ({})", self.pick_id, self.pick_str)
     }
 
+    pub fn is_last(&self) -> bool {
+        self.pick_id == DRAFT_PICK_COUNT
+    }
+
     pub fn get_expected_card_selection_count(&self) -> u8 {
-        12 - (self.pick_id - 1) % 12
+        PACK_SIZE as u8 - (self.pick_id - 1) % PACK_SIZE as u8
     }
 }
 
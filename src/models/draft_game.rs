@@ -1,11 +1,48 @@
 use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
 use super::draft_data::*;
 
+const GAME_ID_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const GAME_ID_LENGTH: usize = 8;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DraftGame {
     pub game_id: String,
     pub time: String,
     pub user_id: Option<String>,
+    #[serde(default)]
+    pub vote_mode: VoteMode,
+    #[serde(default)]
+    pub thread_id: Option<String>,
+    // Practice drafts against the bots, kept out of stats, ratings and history
+    #[serde(default)]
+    pub simulated: bool,
+}
+
+impl DraftGame {
+    pub fn generate_id() -> String {
+        nanoid::nanoid!(
+            GAME_ID_LENGTH,
+            &GAME_ID_ALPHABET.to_string().chars().collect::<Vec<char>>()
+        )
+    }
+
+    // Game ids typed by users end up in record ids, only the generated alphabet is accepted
+    pub fn is_valid_id(game_id: &str) -> bool {
+        !game_id.is_empty() && game_id.chars().all(|c| GAME_ID_ALPHABET.contains(c))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum VoteMode {
+    #[default]
+    #[strum(serialize = "plurality")]
+    Plurality,
+    #[strum(serialize = "weighted")]
+    Weighted,
+    #[strum(serialize = "ranked")]
+    RankedChoice,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +51,8 @@ pub struct DraftVote {
     pub user_id: String,
     pub draft_pick: DraftPick,
     pub vote_idx: u8,
+    #[serde(default)]
+    pub ranking: Vec<u8>,
 }
 
 impl DraftVote {
@@ -23,8 +62,26 @@ impl DraftVote {
             user_id: user_id.to_string(),
             draft_pick: draft_pick.clone(),
             vote_idx,
+            ranking: vec![vote_idx],
         }
     }
+
+    pub fn with_ranking(mut self, ranking: &[u8]) -> Self {
+        if let Some(first) = ranking.first() {
+            self.vote_idx = *first;
+            self.ranking = ranking.to_vec();
+        }
+        self
+    }
+
+    pub fn preferences(&self) -> Vec<u8> {
+        if self.ranking.is_empty() {
+            vec![self.vote_idx]
+        } else {
+            self.ranking.clone()
+        }
+    }
+
     pub fn get_id(&self) -> Vec<String> {
         vec![
             self.game_id.to_string(),
@@ -33,3 +90,17 @@ impl DraftVote {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_id() {
+        assert!(DraftGame::is_valid_id(&DraftGame::generate_id()));
+        assert!(DraftGame::is_valid_id("RI37NIhk"));
+        assert!(!DraftGame::is_valid_id(""));
+        assert!(!DraftGame::is_valid_id("abc' OR true"));
+        assert!(!DraftGame::is_valid_id("draft_game:abc"));
+    }
+}
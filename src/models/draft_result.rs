@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use crate::opt::*;
+
+// An Eternal draft run ends at 7 wins or 3 losses
+pub const MAX_WINS: usize = 7;
+pub const MAX_LOSSES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchResult {
+    pub won: bool,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DraftResult {
+    pub game_id: String,
+    pub matches: Vec<MatchResult>,
+    #[serde(default)]
+    pub final_deck: Vec<String>,
+}
+
+impl DraftResult {
+    pub fn new(game_id: &str) -> Self {
+        DraftResult {
+            game_id: game_id.to_string(),
+            matches: Vec::new(),
+            final_deck: Vec::new(),
+        }
+    }
+
+    pub fn add_match(&mut self, won: bool, notes: Option<&str>) -> Res<()> {
+        if self.is_complete() {
+            return Err(Error::Data(format!(
+                "The run is already over at {}",
+                self.record_text()
+            )));
+        }
+        self.matches.push(MatchResult {
+            won,
+            notes: notes
+                .map(|notes| notes.trim().to_string())
+                .filter(|notes| !notes.is_empty()),
+        });
+        Ok(())
+    }
+
+    pub fn wins(&self) -> usize {
+        self.matches.iter().filter(|m| m.won).count()
+    }
+
+    pub fn losses(&self) -> usize {
+        self.matches.iter().filter(|m| !m.won).count()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.wins() >= MAX_WINS || self.losses() >= MAX_LOSSES
+    }
+
+    pub fn record_text(&self) -> String {
+        format!("{}-{}", self.wins(), self.losses())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![format!(
+            "Record {}{}",
+            self.record_text(),
+            if self.is_complete() { " (final)" } else { "" }
+        )];
+        for (idx, m) in self.matches.iter().enumerate() {
+            lines.push(format!(
+                "{:>2}. {} {}",
+                idx + 1,
+                if m.won { "W" } else { "L" },
+                m.notes.as_deref().unwrap_or("")
+            ));
+        }
+        if !self.final_deck.is_empty() {
+            lines.push(format!("Final deck: {} cards", self.final_deck.len()));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut result = DraftResult::new("game");
+        result.add_match(true, Some(" aggro Rakano ")).unwrap();
+        result.add_match(false, None).unwrap();
+        result.add_match(true, Some("")).unwrap();
+
+        assert_eq!(result.record_text(), "2-1");
+        assert_eq!(result.matches[0].notes.as_deref(), Some("aggro Rakano"));
+        assert_eq!(result.matches[2].notes, None);
+        assert!(!result.is_complete());
+    }
+
+    #[test]
+    fn test_run_ends_at_three_losses() {
+        let mut result = DraftResult::new("game");
+        for _ in 0..MAX_LOSSES {
+            result.add_match(false, None).unwrap();
+        }
+
+        assert!(result.is_complete());
+        assert!(result.add_match(true, None).is_err());
+        assert!(result.to_text().starts_with("Record 0-3 (final)"));
+    }
+}
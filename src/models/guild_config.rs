@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_THREAD_PREFIX: &str = "draft";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuildConfig {
+    pub guild_id: String,
+    pub channel_ids: Vec<String>,
+    pub thread_prefix: String,
+}
+
+impl GuildConfig {
+    pub fn new(guild_id: &str) -> Self {
+        GuildConfig {
+            guild_id: guild_id.to_string(),
+            channel_ids: Vec::new(),
+            thread_prefix: DEFAULT_THREAD_PREFIX.to_string(),
+        }
+    }
+
+    pub fn add_channel(&mut self, channel_id: &str) -> bool {
+        if self.has_channel(channel_id) {
+            return false;
+        }
+        self.channel_ids.push(channel_id.to_string());
+        true
+    }
+
+    pub fn remove_channel(&mut self, channel_id: &str) -> bool {
+        let count = self.channel_ids.len();
+        self.channel_ids.retain(|id| id != channel_id);
+        count != self.channel_ids.len()
+    }
+
+    pub fn has_channel(&self, channel_id: &str) -> bool {
+        self.channel_ids.iter().any(|id| id == channel_id)
+    }
+
+    pub fn matches_prefix(&self, channel_name: &str) -> bool {
+        !self.thread_prefix.is_empty() && channel_name.starts_with(&self.thread_prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_remove_channel() {
+        let mut config = GuildConfig::new("1");
+
+        assert!(config.add_channel("10"));
+        assert!(!config.add_channel("10"));
+        assert!(config.has_channel("10"));
+
+        assert!(config.remove_channel("10"));
+        assert!(!config.remove_channel("10"));
+        assert!(!config.has_channel("10"));
+    }
+
+    #[test]
+    fn test_matches_prefix() {
+        let mut config = GuildConfig::new("1");
+        assert!(config.matches_prefix("draft-abc"));
+        assert!(!config.matches_prefix("general"));
+
+        config.thread_prefix = String::new();
+        assert!(!config.matches_prefix("draft-abc"));
+    }
+}
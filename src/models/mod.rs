@@ -2,3 +2,5 @@ pub mod card;
 pub mod card_rating;
 pub mod draft_data;
 pub mod draft_game;
+pub mod draft_result;
+pub mod guild_config;
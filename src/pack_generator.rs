@@ -0,0 +1,291 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::models::card::*;
+use crate::opt::*;
+
+// Roughly one pack in eight has a legendary in place of the rare
+pub const DEFAULT_LEGENDARY_CHANCE: f64 = 0.125;
+pub const DEFAULT_PACK_LAYOUT: &str = "1R 3U 8C";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackSlot {
+    pub rarity: CardRarity,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackLayout {
+    pub slots: Vec<PackSlot>,
+    // Chance for each rare slot card to be drawn from the legendaries instead
+    pub legendary_chance: f64,
+}
+
+impl Default for PackLayout {
+    fn default() -> Self {
+        PackLayout::parse(DEFAULT_PACK_LAYOUT).expect("Invalid default pack layout")
+    }
+}
+
+impl PackLayout {
+    // A layout is a list of "<count><rarity>" slots, e.g. "1R 3U 8C"
+    pub fn parse(text: &str) -> Res<PackLayout> {
+        let slots = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                let split = token
+                    .find(|c: char| !c.is_ascii_digit())
+                    .ok_or(Error::Data(format!(
+                        "Missing rarity in pack slot [{}]",
+                        token
+                    )))?;
+                let count = token[..split]
+                    .parse::<usize>()
+                    .map_err(|_| Error::Data(format!("Missing count in pack slot [{}]", token)))?;
+                let rarity = CardRarity::from_str(&token[split..].to_uppercase())
+                    .map_err(|_| Error::Data(format!("Unknown rarity in pack slot [{}]", token)))?;
+                Ok(PackSlot { rarity, count })
+            })
+            .collect::<Res<Vec<PackSlot>>>()?;
+
+        if slots.is_empty() {
+            return Err(Error::Data(
+                "A pack layout needs at least one slot".to_string(),
+            ));
+        }
+
+        Ok(PackLayout {
+            slots,
+            legendary_chance: DEFAULT_LEGENDARY_CHANCE,
+        })
+    }
+
+    pub fn with_legendary_chance(mut self, legendary_chance: f64) -> Self {
+        self.legendary_chance = legendary_chance.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn pack_size(&self) -> usize {
+        self.slots.iter().map(|slot| slot.count).sum()
+    }
+}
+
+// Cards that can show up in a pack; basic sigils and promos are never opened
+pub fn is_draftable(card: &Card) -> bool {
+    card.deck_buildable
+        && matches!(
+            card.rarity,
+            CardRarity::Common | CardRarity::Uncommon | CardRarity::Rare | CardRarity::Legendary
+        )
+}
+
+pub struct PackGenerator {
+    layout: PackLayout,
+    cards_by_rarity: HashMap<CardRarity, Vec<Card>>,
+    rng: StdRng,
+}
+
+impl PackGenerator {
+    pub fn new(cards: &[Card], set_name: &str, layout: PackLayout, seed: u64) -> Res<Self> {
+        let set_name = set_name.trim().to_lowercase();
+        let mut cards_by_rarity: HashMap<CardRarity, Vec<Card>> = HashMap::new();
+        for card in cards
+            .iter()
+            .filter(|card| is_draftable(card))
+            .filter(|card| set_name.is_empty() || card.set_name.to_lowercase().contains(&set_name))
+        {
+            cards_by_rarity
+                .entry(card.rarity.clone())
+                .or_default()
+                .push(card.clone());
+        }
+
+        for slot in layout.slots.iter() {
+            let needed: usize = layout
+                .slots
+                .iter()
+                .filter(|other| other.rarity == slot.rarity)
+                .map(|other| other.count)
+                .sum();
+            let available = cards_by_rarity
+                .get(&slot.rarity)
+                .map_or(0, |cards| cards.len());
+            if available < needed {
+                return Err(Error::Data(format!(
+                    "Set [{}] has {} draftable {:?} cards, the pack layout needs {}",
+                    set_name, available, slot.rarity, needed
+                )));
+            }
+        }
+
+        Ok(PackGenerator {
+            layout,
+            cards_by_rarity,
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    pub fn layout(&self) -> &PackLayout {
+        &self.layout
+    }
+
+    fn slot_rarity(&mut self, rarity: &CardRarity) -> CardRarity {
+        let has_legendaries = self
+            .cards_by_rarity
+            .get(&CardRarity::Legendary)
+            .map_or(false, |cards| !cards.is_empty());
+        if *rarity == CardRarity::Rare
+            && has_legendaries
+            && self.rng.gen_bool(self.layout.legendary_chance)
+        {
+            CardRarity::Legendary
+        } else {
+            rarity.clone()
+        }
+    }
+
+    fn candidates(&self, rarity: &CardRarity, names: &HashSet<String>) -> Vec<Card> {
+        self.cards_by_rarity
+            .get(rarity)
+            .map(|cards| {
+                cards
+                    .iter()
+                    .filter(|card| !names.contains(&card.name))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // A pack never holds two copies of the same card
+    pub fn generate(&mut self) -> Vec<Card> {
+        let mut pack = Vec::with_capacity(self.layout.pack_size());
+        let mut names = HashSet::new();
+
+        for slot in self.layout.slots.clone().iter() {
+            for _ in 0..slot.count {
+                let rarity = self.slot_rarity(&slot.rarity);
+                let mut candidates = self.candidates(&rarity, &names);
+                // Small sets can run out of legendaries, the slot then stays a rare
+                if candidates.is_empty() {
+                    candidates = self.candidates(&slot.rarity, &names);
+                }
+                if let Some(card) = candidates.choose(&mut self.rng) {
+                    names.insert(card.name.to_string());
+                    pack.push(card.clone());
+                }
+            }
+        }
+
+        pack
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_cards::test_card;
+
+    fn test_cards() -> Vec<Card> {
+        let mut cards = Vec::new();
+        for (rarity, count) in [
+            ("Common", 12),
+            ("Uncommon", 6),
+            ("Rare", 3),
+            ("Legendary", 2),
+        ] {
+            for i in 0..count {
+                cards.push(
+                    test_card(&format!("{} {}", rarity, i))
+                        .rarity(rarity)
+                        .build(),
+                );
+            }
+        }
+        cards.push(test_card("Fire Sigil").rarity("None").build());
+        cards.push(test_card("Promo Card").rarity("Promo").build());
+        cards.push(test_card("Token").deck_buildable(false).build());
+        cards.push(test_card("Other Common").set_name("Other Set").build());
+        cards
+    }
+
+    fn count_rarity(pack: &[Card], rarity: CardRarity) -> usize {
+        pack.iter().filter(|card| card.rarity == rarity).count()
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        let layout = PackLayout::parse("1r, 3U 8c").unwrap();
+        assert_eq!(layout, PackLayout::default());
+        assert_eq!(layout.pack_size(), 12);
+
+        assert!(PackLayout::parse("").is_err());
+        assert!(PackLayout::parse("3").is_err());
+        assert!(PackLayout::parse("U").is_err());
+        assert!(PackLayout::parse("2X").is_err());
+    }
+
+    #[test]
+    fn test_generate_default_layout() {
+        let mut generator =
+            PackGenerator::new(&test_cards(), "test", PackLayout::default(), 3).unwrap();
+
+        for _ in 0..20 {
+            let pack = generator.generate();
+            assert_eq!(pack.len(), 12);
+            assert_eq!(
+                count_rarity(&pack, CardRarity::Rare) + count_rarity(&pack, CardRarity::Legendary),
+                1
+            );
+            assert_eq!(count_rarity(&pack, CardRarity::Uncommon), 3);
+            assert_eq!(count_rarity(&pack, CardRarity::Common), 8);
+            assert!(pack.iter().all(|card| is_draftable(card)));
+            assert!(pack.iter().all(|card| card.set_name == "Test Set"));
+        }
+    }
+
+    #[test]
+    fn test_legendary_chance() {
+        let layout = PackLayout::parse("2R").unwrap();
+
+        let mut always = PackGenerator::new(
+            &test_cards(),
+            "",
+            layout.clone().with_legendary_chance(1.0),
+            1,
+        )
+        .unwrap();
+        assert_eq!(count_rarity(&always.generate(), CardRarity::Legendary), 2);
+
+        let mut never =
+            PackGenerator::new(&test_cards(), "", layout.with_legendary_chance(0.0), 1).unwrap();
+        assert_eq!(count_rarity(&never.generate(), CardRarity::Rare), 2);
+    }
+
+    #[test]
+    fn test_seeded_packs_repeat() {
+        let names = |seed| {
+            let mut generator =
+                PackGenerator::new(&test_cards(), "", PackLayout::default(), seed).unwrap();
+            (0..3)
+                .flat_map(|_| generator.generate())
+                .map(|card| card.name)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(names(9), names(9));
+        assert_ne!(names(9), names(10));
+    }
+
+    #[test]
+    fn test_not_enough_cards() {
+        let layout = PackLayout::parse("4R").unwrap();
+        assert!(PackGenerator::new(&test_cards(), "", layout, 1).is_err());
+        assert!(PackGenerator::new(&test_cards(), "unknown", PackLayout::default(), 1).is_err());
+    }
+}
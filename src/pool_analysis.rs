@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use serde::Serialize;
+use strum_macros::{Display, EnumString};
+
+use crate::card_abilities::Keyword;
+use crate::models::card::*;
+use crate::models::card_rating::{grade_score, score_grade};
+use crate::opt::*;
+
+// Costs at or above the last bucket are grouped together
+pub const COST_CURVE_BUCKETS: usize = 8;
+pub const TOP_CARDS_PER_FACTION: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Default, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum PoolAnalysisFormat {
+    #[default]
+    #[strum(serialize = "text")]
+    Text,
+    #[strum(serialize = "json")]
+    Json,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TypeSplit {
+    pub units: usize,
+    pub spells: usize,
+    pub fast_spells: usize,
+    pub relics: usize,
+    pub weapons: usize,
+    pub power: usize,
+    pub other: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AbilityCounts {
+    pub removal: usize,
+    pub sweepers: usize,
+    pub card_draw: usize,
+    pub fliers: usize,
+    pub token_makers: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FactionInfluence {
+    pub faction: String,
+    pub cards: usize,
+    pub total_pips: usize,
+    // The highest requirement of a single card, i.e. how many sigils the faction needs to support
+    pub max_pips: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RatedCard {
+    pub name: String,
+    pub rating: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FactionTopCards {
+    pub faction: String,
+    pub cards: Vec<RatedCard>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolAnalysis {
+    pub card_count: usize,
+    pub cost_curve: [usize; COST_CURVE_BUCKETS],
+    pub type_split: TypeSplit,
+    pub abilities: AbilityCounts,
+    pub influence: Vec<FactionInfluence>,
+    pub rated_count: usize,
+    pub average_grade: Option<String>,
+    pub top_cards: Vec<FactionTopCards>,
+}
+
+fn is_power(card: &Card) -> bool {
+    card.card_type.is(&CardTypeEnum::Power)
+}
+
+fn count_abilities(counts: &mut AbilityCounts, card: &Card) {
+    let abilities = card.abilities();
+    if let Some(removal) = abilities.removal {
+        counts.removal += 1;
+        if removal.sweeper {
+            counts.sweepers += 1;
+        }
+    }
+    if abilities.draw > 0 {
+        counts.card_draw += 1;
+    }
+    if abilities.has_keyword(&Keyword::Flying) {
+        counts.fliers += 1;
+    }
+    if abilities.makes_tokens() {
+        counts.token_makers += 1;
+    }
+}
+
+fn count_type(split: &mut TypeSplit, card: &Card) {
+    // Multi-typed cards such as "Relic Weapon" are counted by their last type
+    match card.card_type.types().last() {
+        Some(CardTypeEnum::Unit) => split.units += 1,
+        Some(CardTypeEnum::Spell) if card.card_type.is_fast() => split.fast_spells += 1,
+        Some(CardTypeEnum::Spell) => split.spells += 1,
+        Some(CardTypeEnum::Relic) => split.relics += 1,
+        Some(CardTypeEnum::Weapon) => split.weapons += 1,
+        Some(CardTypeEnum::Power) => split.power += 1,
+        _ => split.other += 1,
+    }
+}
+
+pub fn analyze_pool(pool: &[Card], ratings: &HashMap<String, String>) -> PoolAnalysis {
+    let mut cost_curve = [0; COST_CURVE_BUCKETS];
+    let mut type_split = TypeSplit::default();
+
+    for card in pool.iter() {
+        count_type(&mut type_split, card);
+        if !is_power(card) {
+            cost_curve[(card.cost as usize).min(COST_CURVE_BUCKETS - 1)] += 1;
+        }
+    }
+
+    let non_power = pool
+        .iter()
+        .filter(|card| !is_power(card))
+        .collect::<Vec<&Card>>();
+
+    let mut abilities = AbilityCounts::default();
+    for card in non_power.iter() {
+        count_abilities(&mut abilities, card);
+    }
+
+    let influence = FACTIONS
+        .iter()
+        .map(|faction| {
+            let pips = non_power
+                .iter()
+                .map(|card| card.influence.count_of(faction))
+                .filter(|count| *count > 0)
+                .collect::<Vec<u8>>();
+            FactionInfluence {
+                faction: faction.faction_name().to_string(),
+                cards: pips.len(),
+                total_pips: pips.iter().map(|count| *count as usize).sum(),
+                max_pips: pips.iter().max().cloned().unwrap_or(0),
+            }
+        })
+        .collect::<Vec<FactionInfluence>>();
+
+    let scores = non_power
+        .iter()
+        .filter_map(|card| ratings.get(&card.name).and_then(|r| grade_score(r)))
+        .collect::<Vec<i32>>();
+    let average_grade = if scores.is_empty() {
+        None
+    } else {
+        let average = scores.iter().sum::<i32>() as f32 / scores.len() as f32;
+        Some(score_grade(average.round() as i32))
+    };
+
+    let top_cards = FACTIONS
+        .iter()
+        .map(|faction| FactionTopCards {
+            faction: faction.faction_name().to_string(),
+            cards: non_power
+                .iter()
+                .filter(|card| card.influence.count_of(faction) > 0)
+                .filter_map(|card| {
+                    let rating = ratings.get(&card.name)?;
+                    Some((grade_score(rating)?, card, rating))
+                })
+                .sorted_by(|a, b| b.0.cmp(&a.0).then(a.1.name.cmp(&b.1.name)))
+                .dedup_by(|a, b| a.1.name == b.1.name)
+                .take(TOP_CARDS_PER_FACTION)
+                .map(|(_, card, rating)| RatedCard {
+                    name: card.name.to_string(),
+                    rating: rating.to_string(),
+                })
+                .collect(),
+        })
+        .filter(|top| !top.cards.is_empty())
+        .collect();
+
+    PoolAnalysis {
+        card_count: pool.len(),
+        cost_curve,
+        type_split,
+        abilities,
+        influence,
+        rated_count: scores.len(),
+        average_grade,
+        top_cards,
+    }
+}
+
+impl PoolAnalysis {
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![format!(
+            "Cards: {}, rated: {}, average grade: {}",
+            self.card_count,
+            self.rated_count,
+            self.average_grade.as_deref().unwrap_or("NA")
+        )];
+
+        lines.push(String::new());
+        lines.push("Curve".to_string());
+        for (cost, count) in self.cost_curve.iter().enumerate() {
+            let label = if cost == COST_CURVE_BUCKETS - 1 {
+                format!("{}+", cost)
+            } else {
+                cost.to_string()
+            };
+            lines.push(format!("{:>2} {:<2} {}", label, count, "#".repeat(*count)));
+        }
+
+        let split = &self.type_split;
+        lines.push(String::new());
+        lines.push(format!(
+            "Units {} | Spells {} | Fast {} | Relics {} | Weapons {} | Power {}",
+            split.units, split.spells, split.fast_spells, split.relics, split.weapons, split.power
+        ));
+        let abilities = &self.abilities;
+        lines.push(format!(
+            "Removal {} ({} sweepers) | Draw {} | Fliers {} | Tokens {}",
+            abilities.removal,
+            abilities.sweepers,
+            abilities.card_draw,
+            abilities.fliers,
+            abilities.token_makers
+        ));
+
+        lines.push(String::new());
+        lines.push("Influence".to_string());
+        for influence in self.influence.iter().filter(|i| i.cards > 0) {
+            lines.push(format!(
+                "{:8} {:>2} cards {:>2} pips, max {}",
+                influence.faction, influence.cards, influence.total_pips, influence.max_pips
+            ));
+        }
+
+        if !self.top_cards.is_empty() {
+            lines.push(String::new());
+            lines.push("Top cards".to_string());
+            for top in self.top_cards.iter() {
+                lines.push(format!(
+                    "{:8} {}",
+                    top.faction,
+                    top.cards
+                        .iter()
+                        .map(|card| format!("{} ({})", card.name, card.rating))
+                        .join(", ")
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    pub fn render(&self, format: &PoolAnalysisFormat) -> Res<String> {
+        match format {
+            PoolAnalysisFormat::Text => Ok(self.to_text()),
+            PoolAnalysisFormat::Json => serde_json::to_string_pretty(self).err_to(Error::Data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_cards::{card, test_card};
+
+    fn test_pool() -> (Vec<Card>, HashMap<String, String>) {
+        let pool = vec![
+            test_card("Torch")
+                .cost(1)
+                .card_type("Fast Spell")
+                .text("Deal 2 damage to a unit or player.")
+                .build(),
+            test_card("Rakano Outlaw")
+                .cost(3)
+                .influence("{F}{J}")
+                .text("<b>Flying</b>")
+                .build(),
+            card("Vara, Fiery Dancer", 4, "{F}{F}{J}", "Unit"),
+            card("Auric Runehammer", 2, "{J}", "Relic Weapon"),
+            test_card("Harsh Rule")
+                .cost(9)
+                .influence("{J}{J}{J}")
+                .card_type("Spell")
+                .text("Kill all units.")
+                .build(),
+            card("Seat of Glory", 0, "{F}{J}", "Power"),
+        ];
+        let ratings = HashMap::from([
+            ("Torch".to_string(), "B+".to_string()),
+            ("Rakano Outlaw".to_string(), "C".to_string()),
+            ("Vara, Fiery Dancer".to_string(), "A".to_string()),
+            ("Harsh Rule".to_string(), "B".to_string()),
+        ]);
+        (pool, ratings)
+    }
+
+    #[test]
+    fn test_analyze_pool() {
+        let (pool, ratings) = test_pool();
+        let analysis = analyze_pool(&pool, &ratings);
+
+        assert_eq!(analysis.card_count, 6);
+        assert_eq!(analysis.cost_curve, [0, 1, 1, 1, 1, 0, 0, 1]);
+        assert_eq!(
+            analysis.type_split,
+            TypeSplit {
+                units: 2,
+                spells: 1,
+                fast_spells: 1,
+                weapons: 1,
+                power: 1,
+                ..Default::default()
+            }
+        );
+
+        assert_eq!(
+            analysis.abilities,
+            AbilityCounts {
+                removal: 2,
+                sweepers: 1,
+                fliers: 1,
+                ..Default::default()
+            }
+        );
+
+        let justice = &analysis.influence[2];
+        assert_eq!(justice.faction, "Justice");
+        assert_eq!(
+            (justice.cards, justice.total_pips, justice.max_pips),
+            (4, 6, 3)
+        );
+
+        assert_eq!(analysis.rated_count, 4);
+        assert_eq!(analysis.average_grade.as_deref(), Some("B"));
+
+        assert_eq!(analysis.top_cards[0].faction, "Fire");
+        assert_eq!(
+            analysis.top_cards[0]
+                .cards
+                .iter()
+                .map(|card| card.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["Vara, Fiery Dancer", "Torch", "Rakano Outlaw"]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let (pool, ratings) = test_pool();
+        let analysis = analyze_pool(&pool, &ratings);
+
+        let text = analysis.render(&PoolAnalysisFormat::Text).unwrap();
+        assert!(text.contains("7+ 1"));
+        assert!(text.contains("Removal 2 (1 sweepers)"));
+        assert!(text.contains("Justice   4 cards  6 pips, max 3"));
+
+        let json = analysis.render(&PoolAnalysisFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["type_split"]["fast_spells"], 1);
+        assert_eq!(value["average_grade"], "B");
+    }
+
+    #[test]
+    fn test_empty_pool() {
+        let analysis = analyze_pool(&[], &HashMap::new());
+        assert_eq!(analysis.average_grade, None);
+        assert!(analysis.top_cards.is_empty());
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::{HashMap, HashSet};
+
+use indicium::simple::SearchIndex;
+use itertools::Itertools;
+
+use crate::card_loader::{self, RatingEntry};
+use crate::card_matcher;
+use crate::models::card::Card;
+use crate::models::card_rating::grade_score;
+use crate::opt::*;
+
+pub const RATING_SUGGESTION_LIMIT: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RatingIssue {
+    Unmatched {
+        entry: RatingEntry,
+        suggestions: Vec<String>,
+    },
+    // Only the last entry of a name is used
+    Duplicate {
+        name: String,
+        entries: Vec<RatingEntry>,
+    },
+    UnknownGrade(RatingEntry),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RatingValidation {
+    pub checked: usize,
+    pub issues: Vec<RatingIssue>,
+}
+
+impl RatingIssue {
+    pub fn to_text(&self) -> String {
+        match self {
+            RatingIssue::Unmatched { entry, suggestions } => format!(
+                "Line {}: [{}] is not a card{}",
+                entry.line,
+                entry.name,
+                if suggestions.is_empty() {
+                    String::new()
+                } else {
+                    format!(", did you mean {}?", suggestions.join(" / "))
+                }
+            ),
+            RatingIssue::Duplicate { name, entries } => format!(
+                "[{}] is rated {} times: {}",
+                name,
+                entries.len(),
+                entries
+                    .iter()
+                    .map(|entry| format!("{} on line {}", entry.rating, entry.line))
+                    .join(", ")
+            ),
+            RatingIssue::UnknownGrade(entry) => format!(
+                "Line {}: [{}] has an unknown grade [{}]",
+                entry.line, entry.name, entry.rating
+            ),
+        }
+    }
+}
+
+impl RatingValidation {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![format!(
+            "Checked {} rating entries, {} issues",
+            self.checked,
+            self.issues.len()
+        )];
+        lines.extend(self.issues.iter().map(|issue| issue.to_text()));
+        lines.join("\n")
+    }
+}
+
+// Best guess from the OCR matcher first, then the closest names by edit distance
+fn suggestions(
+    name: &str,
+    cards: &HashMap<String, Card>,
+    card_index: &SearchIndex<String>,
+    card_name_tokens: &HashSet<String>,
+) -> Vec<String> {
+    card_matcher::find_card_name_matches(card_index, card_name_tokens, &[name])
+        .into_iter()
+        .chain(card_matcher::suggest_card_names(
+            name,
+            cards.keys(),
+            RATING_SUGGESTION_LIMIT,
+        ))
+        .unique()
+        .take(RATING_SUGGESTION_LIMIT)
+        .collect()
+}
+
+pub fn validate_ratings(
+    entries: &[RatingEntry],
+    cards: &HashMap<String, Card>,
+) -> RatingValidation {
+    let mut issues = Vec::new();
+
+    let unmatched = entries
+        .iter()
+        .filter(|entry| !cards.contains_key(&entry.name))
+        .collect::<Vec<&RatingEntry>>();
+    // The search index is only worth building when something needs a suggestion
+    if !unmatched.is_empty() {
+        let card_index = cards
+            .iter()
+            .fold(SearchIndex::default(), |mut acc, (name, card)| {
+                acc.insert(name, card);
+                acc
+            });
+        let card_name_tokens = cards
+            .keys()
+            .flat_map(|name| name.split_whitespace().map(|token| token.to_string()))
+            .collect::<HashSet<String>>();
+
+        for entry in unmatched {
+            issues.push(RatingIssue::Unmatched {
+                entry: entry.clone(),
+                suggestions: suggestions(&entry.name, cards, &card_index, &card_name_tokens),
+            });
+        }
+    }
+
+    for (name, entries) in entries
+        .iter()
+        .into_group_map_by(|entry| entry.name.to_string())
+        .into_iter()
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+    {
+        if entries.len() > 1 {
+            issues.push(RatingIssue::Duplicate {
+                name,
+                entries: entries.into_iter().cloned().collect(),
+            });
+        }
+    }
+
+    for entry in entries.iter() {
+        if grade_score(&entry.rating).is_none() {
+            issues.push(RatingIssue::UnknownGrade(entry.clone()));
+        }
+    }
+
+    RatingValidation {
+        checked: entries.len(),
+        issues,
+    }
+}
+
+pub fn check_card_ratings(cards: &HashMap<String, Card>) -> Res<RatingValidation> {
+    Ok(validate_ratings(
+        &card_loader::load_card_rating_entries()?,
+        cards,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_cards::test_card;
+
+    fn entry(line: usize, rating: &str, name: &str) -> RatingEntry {
+        RatingEntry {
+            line,
+            rating: rating.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    fn test_cards() -> HashMap<String, Card> {
+        ["Torch", "Harsh Rule", "Oni Ronin"]
+            .iter()
+            .map(|name| (name.to_string(), test_card(name).build()))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_ratings() {
+        let entries = vec![entry(2, "B", "Torch"), entry(3, "A", "Harsh Rule")];
+        let validation = validate_ratings(&entries, &test_cards());
+
+        assert!(validation.is_ok());
+        assert_eq!(validation.checked, 2);
+    }
+
+    #[test]
+    fn test_rating_issues() {
+        let entries = vec![
+            entry(2, "B", "Torch"),
+            entry(3, "B+", "Harsh Rul"),
+            entry(4, "C", "Torch"),
+            entry(5, "Q", "Oni Ronin"),
+        ];
+        let validation = validate_ratings(&entries, &test_cards());
+
+        assert_eq!(
+            validation.issues[0],
+            RatingIssue::Unmatched {
+                entry: entry(3, "B+", "Harsh Rul"),
+                suggestions: vec!["Harsh Rule".to_string()],
+            }
+        );
+        assert_eq!(
+            validation.issues[1].to_text(),
+            "[Torch] is rated 2 times: B on line 2, C on line 4"
+        );
+        assert_eq!(
+            validation.issues[2],
+            RatingIssue::UnknownGrade(entry(5, "Q", "Oni Ronin"))
+        );
+        assert!(validation.to_text().contains("did you mean Harsh Rule?"));
+    }
+
+    #[test]
+    fn test_bundled_ratings() {
+        let cards = card_loader::load_card_hashmap_by_name().unwrap();
+        assert!(check_card_ratings(&cards).unwrap().is_ok());
+    }
+}
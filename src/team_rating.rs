@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::draft_replay::{passed_cards, taken_card};
+use crate::draft_stats::{self, CARD_STATS_MIN_GAMES};
+use crate::models::card_rating::*;
+use crate::models::draft_data::DraftRecord;
+use crate::models::draft_game::DraftVote;
+use crate::models::draft_result::DraftResult;
+
+// A card the team consistently prefers over better rated cards moves up one full letter at most
+const MAX_PICK_ADJUSTMENT: f32 = 3.0;
+// Votes are individual opinions, a committed pick is what the group agreed on
+const VOTE_WEIGHT: f32 = 0.5;
+// A 75% win rate when drafted is worth one grade step
+const RESULT_WEIGHT: f32 = 4.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeamRating {
+    pub name: String,
+    pub published: Option<String>,
+    pub team: String,
+    pub delta: i32,
+    pub samples: usize,
+}
+
+impl TeamRating {
+    pub fn to_text(&self) -> String {
+        format!(
+            "{:30} {:<2} -> {:<2} ({:+}, {} samples)",
+            self.name,
+            self.published.as_deref().unwrap_or("NA"),
+            self.team,
+            self.delta,
+            self.samples
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct Evidence {
+    // Sum of weights of comparisons that went against the published list
+    net: f32,
+    weight: f32,
+    samples: usize,
+}
+
+fn published_score(name: &str, ratings: &HashMap<String, String>) -> i32 {
+    ratings
+        .get(name)
+        .and_then(|rating| grade_score(rating))
+        .or_else(|| grade_score(UNRATED_GRADE))
+        .unwrap_or(0)
+}
+
+// Every comparison is "preferred over"; only those that disagree with the
+// published grades move a card, agreeing comparisons just add to its weight
+fn compare(
+    evidence: &mut HashMap<String, Evidence>,
+    ratings: &HashMap<String, String>,
+    preferred: &str,
+    other: &str,
+    weight: f32,
+) {
+    let disagrees = published_score(preferred, ratings) < published_score(other, ratings);
+
+    for (name, direction) in [(preferred, 1.0), (other, -1.0)] {
+        let entry = evidence.entry(name.to_string()).or_default();
+        entry.weight += weight;
+        entry.samples += 1;
+        if disagrees {
+            entry.net += direction * weight;
+        }
+    }
+}
+
+pub fn compute_team_ratings(
+    ratings: &HashMap<String, String>,
+    records: &[DraftRecord],
+    votes: &[DraftVote],
+    results: &[DraftResult],
+) -> Vec<TeamRating> {
+    let mut evidence: HashMap<String, Evidence> = HashMap::new();
+
+    for record in records.iter() {
+        let taken = match taken_card(record) {
+            Some(taken) => taken,
+            None => continue,
+        };
+        for passed in passed_cards(record) {
+            compare(&mut evidence, ratings, taken, passed, 1.0);
+        }
+    }
+
+    let records_by_pick: HashMap<(&str, u8), &DraftRecord> = records
+        .iter()
+        .map(|record| ((record.game_id.as_str(), record.pick.pick_id), record))
+        .collect();
+    for vote in votes.iter() {
+        let record = match records_by_pick.get(&(vote.game_id.as_str(), vote.draft_pick.pick_id)) {
+            Some(record) => record,
+            None => continue,
+        };
+        let names = vote
+            .preferences()
+            .iter()
+            .filter_map(|idx| record.selection_vec.get(*idx as usize))
+            .filter(|name| !name.is_empty())
+            .cloned()
+            .collect::<Vec<String>>();
+
+        // Ranked cards beat everything below them, including the cards left unranked
+        for (rank, preferred) in names.iter().enumerate() {
+            let below = names[rank + 1..]
+                .iter()
+                .chain(
+                    record
+                        .selection_vec
+                        .iter()
+                        .filter(|name| !name.is_empty() && !names.contains(name)),
+                )
+                .cloned()
+                .collect::<Vec<String>>();
+            for other in below.iter() {
+                compare(&mut evidence, ratings, preferred, other, VOTE_WEIGHT);
+            }
+        }
+    }
+
+    let card_stats = draft_stats::card_stats(records, results)
+        .into_iter()
+        .map(|stats| (stats.name.to_string(), stats))
+        .collect::<HashMap<_, _>>();
+
+    evidence
+        .into_iter()
+        .map(|(name, evidence)| {
+            let mut delta = if evidence.weight > 0.0 {
+                evidence.net / evidence.weight * MAX_PICK_ADJUSTMENT
+            } else {
+                0.0
+            };
+
+            let mut samples = evidence.samples;
+            if let Some(stats) = card_stats.get(&name) {
+                if stats.games >= CARD_STATS_MIN_GAMES {
+                    if let Some(win_rate) = stats.win_rate() {
+                        delta += (win_rate - 0.5) * RESULT_WEIGHT;
+                        samples += stats.wins + stats.losses;
+                    }
+                }
+            }
+
+            let delta = delta.round() as i32;
+            TeamRating {
+                team: score_grade(published_score(&name, ratings) + delta),
+                published: ratings.get(&name).cloned(),
+                name,
+                delta,
+                samples,
+            }
+        })
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect()
+}
+
+pub fn to_card_ratings(team_ratings: &[TeamRating]) -> Vec<CardRating> {
+    team_ratings
+        .iter()
+        .map(|rating| CardRating {
+            format: TEAM_RATING_FORMAT.to_string(),
+            name: rating.name.to_string(),
+            rating: rating.team.to_string(),
+        })
+        .collect()
+}
+
+// Cards where the team differs most from the published list come first
+pub fn disagreements(team_ratings: &[TeamRating], limit: usize) -> Vec<&TeamRating> {
+    team_ratings
+        .iter()
+        .filter(|rating| rating.delta != 0)
+        .sorted_by(|a, b| {
+            b.delta
+                .abs()
+                .cmp(&a.delta.abs())
+                .then(b.samples.cmp(&a.samples))
+                .then(a.name.cmp(&b.name))
+        })
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::draft_data::DraftPick;
+
+    fn record(game_id: &str, pick_id: u8, selections: &[&str], taken: u8) -> DraftRecord {
+        let mut record = DraftRecord::new(game_id.to_string(), DraftPick::new(pick_id));
+        record.set_selection_vec(selections);
+        record.pick_card(taken);
+        record
+    }
+
+    fn test_ratings() -> HashMap<String, String> {
+        HashMap::from([
+            ("Torch".to_string(), "B".to_string()),
+            ("Harsh Rule".to_string(), "A".to_string()),
+            ("Oni Ronin".to_string(), "C".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_picks_against_published_list() {
+        let records = vec![
+            record("a", 1, &["Torch", "Harsh Rule"], 0),
+            record("b", 1, &["Torch", "Harsh Rule"], 0),
+        ];
+        let team = compute_team_ratings(&test_ratings(), &records, &[], &[]);
+
+        let torch = team.iter().find(|r| r.name == "Torch").unwrap();
+        assert_eq!((torch.team.as_str(), torch.delta), ("A", 3));
+        let harsh_rule = team.iter().find(|r| r.name == "Harsh Rule").unwrap();
+        assert_eq!((harsh_rule.team.as_str(), harsh_rule.delta), ("B", -3));
+
+        assert_eq!(disagreements(&team, 1)[0].name, "Harsh Rule");
+    }
+
+    #[test]
+    fn test_agreeing_picks_keep_the_grade() {
+        let records = vec![record("a", 1, &["Torch", "Oni Ronin"], 0)];
+        let team = compute_team_ratings(&test_ratings(), &records, &[], &[]);
+
+        assert!(team.iter().all(|r| r.delta == 0));
+        assert!(disagreements(&team, 5).is_empty());
+    }
+
+    #[test]
+    fn test_votes_and_results() {
+        let records = vec![
+            record("a", 1, &["Torch", "Oni Ronin"], 0),
+            record("b", 1, &["Torch", "Oni Ronin"], 0),
+        ];
+        let votes = vec![
+            DraftVote::new("a", "1", &DraftPick::new(1), 1),
+            DraftVote::new("a", "2", &DraftPick::new(1), 1),
+        ];
+        let mut result = DraftResult::new("a");
+        for _ in 0..7 {
+            result.add_match(true, None).unwrap();
+        }
+
+        let team = compute_team_ratings(&test_ratings(), &records, &votes, &[result]);
+        let oni_ronin = team.iter().find(|r| r.name == "Oni Ronin").unwrap();
+        assert_eq!(oni_ronin.delta, 1);
+        assert_eq!(oni_ronin.samples, 4);
+
+        let card_ratings = to_card_ratings(&team);
+        assert!(card_ratings.iter().all(|r| r.format == TEAM_RATING_FORMAT));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::models::card::Card;
+
+// Test cards go through the same json as the card data, a test only sets the
+// fields it cares about
+pub struct TestCard(serde_json::Value);
+
+pub fn test_card(name: &str) -> TestCard {
+    TestCard(serde_json::json!({
+        "SetNumber": 1,
+        "Name": name,
+        "CardText": "",
+        "Cost": 2,
+        "Influence": "{F}",
+        "Attack": 1,
+        "Health": 1,
+        "Rarity": "Common",
+        "Type": "Unit",
+        "ImageUrl": "",
+        "DetailsUrl": "",
+        "DeckBuildable": true,
+        "SetName": "Test Set"
+    }))
+}
+
+// The usual card of a drafted pool
+pub fn card(name: &str, cost: u32, influence: &str, card_type: &str) -> Card {
+    test_card(name)
+        .cost(cost)
+        .influence(influence)
+        .card_type(card_type)
+        .build()
+}
+
+impl TestCard {
+    fn set(mut self, key: &str, value: impl Into<serde_json::Value>) -> Self {
+        self.0[key] = value.into();
+        self
+    }
+
+    pub fn text(self, card_text: &str) -> Self {
+        self.set("CardText", card_text)
+    }
+
+    pub fn cost(self, cost: u32) -> Self {
+        self.set("Cost", cost)
+    }
+
+    pub fn influence(self, influence: &str) -> Self {
+        self.set("Influence", influence)
+    }
+
+    pub fn stats(self, attack: i32, health: i32) -> Self {
+        self.set("Attack", attack).set("Health", health)
+    }
+
+    pub fn rarity(self, rarity: &str) -> Self {
+        self.set("Rarity", rarity)
+    }
+
+    pub fn card_type(self, card_type: &str) -> Self {
+        self.set("Type", card_type)
+    }
+
+    pub fn set_name(self, set_name: &str) -> Self {
+        self.set("SetName", set_name)
+    }
+
+    pub fn deck_buildable(self, deck_buildable: bool) -> Self {
+        self.set("DeckBuildable", deck_buildable)
+    }
+
+    pub fn build(self) -> Card {
+        serde_json::from_value(self.0).unwrap()
+    }
+}
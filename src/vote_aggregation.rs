@@ -0,0 +1,235 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+
+use crate::models::draft_data::PACK_SIZE;
+use crate::models::draft_game::{DraftVote, VoteMode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateTally {
+    pub vote_idx: u8,
+    pub first_choice_count: u32,
+    pub score: u32,
+    pub voters: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VoteTally {
+    pub mode: VoteMode,
+    pub candidates: Vec<CandidateTally>,
+}
+
+impl VoteTally {
+    pub fn winner(&self) -> Option<u8> {
+        self.candidates.first().map(|c| c.vote_idx)
+    }
+
+    pub fn total_voters(&self) -> usize {
+        self.candidates.iter().map(|c| c.voters.len()).sum()
+    }
+}
+
+pub fn tally_votes(votes: &[DraftVote], mode: &VoteMode) -> VoteTally {
+    let mut candidates: HashMap<u8, CandidateTally> = HashMap::new();
+
+    for vote in votes {
+        let candidate = candidates
+            .entry(vote.vote_idx)
+            .or_insert_with(|| CandidateTally {
+                vote_idx: vote.vote_idx,
+                first_choice_count: 0,
+                score: 0,
+                voters: Vec::new(),
+            });
+        candidate.first_choice_count += 1;
+        candidate.voters.push(vote.user_id.to_string());
+    }
+
+    let scores = match mode {
+        VoteMode::Plurality => candidates
+            .iter()
+            .map(|(idx, c)| (*idx, c.first_choice_count))
+            .collect(),
+        VoteMode::Weighted => weighted_scores(votes),
+        VoteMode::RankedChoice => ranked_choice_scores(votes),
+    };
+
+    // Candidates that only appear further down a ranking still get a row
+    for (idx, _) in scores.iter() {
+        candidates.entry(*idx).or_insert_with(|| CandidateTally {
+            vote_idx: *idx,
+            first_choice_count: 0,
+            score: 0,
+            voters: Vec::new(),
+        });
+    }
+
+    let candidates = candidates
+        .into_values()
+        .map(|mut c| {
+            c.score = scores.get(&c.vote_idx).cloned().unwrap_or(0);
+            c.voters.sort();
+            c
+        })
+        .sorted_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(b.first_choice_count.cmp(&a.first_choice_count))
+                .then(a.vote_idx.cmp(&b.vote_idx))
+        })
+        .collect();
+
+    VoteTally {
+        mode: mode.clone(),
+        candidates,
+    }
+}
+
+// Borda count on the size of a pack: a first choice is worth 12 points, the second 11,
+// however many cards the voter ranked, so a longer ballot does not weigh more
+fn weighted_scores(votes: &[DraftVote]) -> HashMap<u8, u32> {
+    let mut scores = HashMap::new();
+    for vote in votes {
+        for (rank, idx) in vote.preferences().iter().enumerate() {
+            *scores.entry(*idx).or_insert(0) += PACK_SIZE.saturating_sub(rank) as u32;
+        }
+    }
+    scores
+}
+
+// Instant runoff: repeatedly eliminate the card with the fewest first choices
+// until one card holds a majority of the remaining ballots. The score of each
+// card is the number of ballots it held in the last round it took part in.
+fn ranked_choice_scores(votes: &[DraftVote]) -> HashMap<u8, u32> {
+    let ballots: Vec<Vec<u8>> = votes.iter().map(|v| v.preferences()).collect();
+    let mut eliminated: HashSet<u8> = HashSet::new();
+    let mut scores: HashMap<u8, u32> = HashMap::new();
+
+    loop {
+        let mut round: HashMap<u8, u32> = HashMap::new();
+        for ballot in ballots.iter() {
+            if let Some(idx) = ballot.iter().find(|idx| !eliminated.contains(idx)) {
+                *round.entry(*idx).or_insert(0) += 1;
+            }
+        }
+
+        for (idx, count) in round.iter() {
+            scores.insert(*idx, *count);
+        }
+
+        let active_ballots: u32 = round.values().sum();
+        let leader = round
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|(idx, count)| (*idx, *count));
+
+        match leader {
+            Some((_, count)) if count * 2 > active_ballots || round.len() <= 1 => {
+                break;
+            }
+            Some(_) => {
+                let min_count = round.values().min().cloned().unwrap_or(0);
+                round
+                    .iter()
+                    .filter(|(_, count)| **count == min_count)
+                    .for_each(|(idx, _)| {
+                        eliminated.insert(*idx);
+                    });
+
+                if eliminated.len() >= scores.len() {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::draft_data::DraftPick;
+
+    fn vote(user: &str, ranking: &[u8]) -> DraftVote {
+        DraftVote::new("game", user, &DraftPick::new(1), ranking[0]).with_ranking(ranking)
+    }
+
+    #[test]
+    fn test_plurality() {
+        let votes = vec![
+            vote("a", &[1]),
+            vote("b", &[2]),
+            vote("c", &[2]),
+            vote("d", &[0]),
+        ];
+
+        let tally = tally_votes(&votes, &VoteMode::Plurality);
+        assert_eq!(tally.winner(), Some(2));
+        assert_eq!(tally.candidates[0].voters, vec!["b", "c"]);
+        assert_eq!(tally.total_voters(), 4);
+    }
+
+    #[test]
+    fn test_plurality_tie_breaks_on_lowest_index() {
+        let votes = vec![vote("a", &[3]), vote("b", &[1])];
+
+        let tally = tally_votes(&votes, &VoteMode::Plurality);
+        assert_eq!(tally.winner(), Some(1));
+    }
+
+    #[test]
+    fn test_weighted() {
+        let votes = vec![
+            vote("a", &[0, 1, 2]),
+            vote("b", &[2, 1, 0]),
+            vote("c", &[1, 2, 0]),
+        ];
+
+        let tally = tally_votes(&votes, &VoteMode::Weighted);
+        assert_eq!(tally.winner(), Some(1));
+        assert_eq!(tally.candidates[0].score, 34);
+    }
+
+    #[test]
+    fn test_weighted_ballot_length() {
+        let votes = vec![vote("a", &[0, 1, 2, 3, 4, 5, 6, 7]), vote("b", &[1])];
+
+        let tally = tally_votes(&votes, &VoteMode::Weighted);
+        assert_eq!(tally.winner(), Some(1));
+
+        // A first choice counts the same on a short ballot as on a long one
+        let votes = vec![vote("a", &[0, 1, 2]), vote("b", &[3])];
+        let tally = tally_votes(&votes, &VoteMode::Weighted);
+        let score = |vote_idx: u8| {
+            tally
+                .candidates
+                .iter()
+                .find(|c| c.vote_idx == vote_idx)
+                .map(|c| c.score)
+        };
+        assert_eq!(score(0), score(3));
+    }
+
+    #[test]
+    fn test_ranked_choice() {
+        let votes = vec![
+            vote("a", &[0, 2]),
+            vote("b", &[0, 2]),
+            vote("c", &[1, 2]),
+            vote("d", &[1, 2]),
+            vote("e", &[2, 1]),
+        ];
+
+        let tally = tally_votes(&votes, &VoteMode::RankedChoice);
+        assert_eq!(tally.winner(), Some(1));
+        assert_eq!(tally.candidates[0].score, 3);
+    }
+
+    #[test]
+    fn test_no_votes() {
+        let tally = tally_votes(&[], &VoteMode::RankedChoice);
+        assert_eq!(tally.winner(), None);
+    }
+}